@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chip8_emulator::instruction::Instruction;
+use chip8_emulator::variant::Variant;
+use error::{AssembleError, Location};
+use lexer::{tokenize, Token, TokenKind};
+
+pub const PROGRAM_START: u16 = 0x200;
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+const MNEMONICS: [&str; 30] = [
+    "CLS", "RET", "SCD", "SCR", "SCL", "EXIT", "LOW", "HIGH", "JP", "CALL", "SE", "SNE",
+    "SAVE", "LOAD", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SHR", "SUBN", "SHL", "RND",
+    "DRW", "SKP", "SKNP", "PLANE", "AUDIO", "PITCH",
+];
+
+#[derive(Clone, Debug)]
+enum Term {
+    Number(i64),
+    Symbol(String),
+}
+
+#[derive(Clone, Debug)]
+struct Expr {
+    terms: Vec<(bool, Term, usize)>,
+    column: usize,
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Register(u8),
+    I,
+    IndirectI,
+    DelayTimer,
+    SoundTimer,
+    Key,
+    Font,
+    BigFont,
+    Bcd,
+    Flags,
+    Long(Expr),
+    Value(Expr),
+}
+
+#[derive(Clone, Debug)]
+enum DataItem {
+    Value(Expr),
+    Text(String),
+}
+
+#[derive(Clone, Debug)]
+enum StatementKind {
+    Instruction { mnemonic: String, operands: Vec<Operand> },
+    Bytes(Vec<DataItem>),
+    Words(Vec<Expr>),
+}
+
+#[derive(Clone, Debug)]
+struct Statement {
+    location: Location,
+    kind: StatementKind,
+}
+
+struct FirstPass {
+    statements: Vec<Statement>,
+    symbols: HashMap<String, i64>,
+    address: u32,
+}
+
+pub struct Assembler {
+    variant: Variant,
+    include_dir: PathBuf,
+}
+
+impl Assembler {
+    pub fn new(variant: Variant) -> Assembler {
+        Assembler::with_include_dir(variant, ".")
+    }
+
+    // Includes in sources passed to `assemble` are resolved relative to this directory
+    pub fn with_include_dir<P: Into<PathBuf>> (variant: Variant, include_dir: P) -> Assembler {
+        Assembler { variant: variant, include_dir: include_dir.into() }
+    }
+
+    pub fn assemble (&self, source: &str) -> Result<Vec<u8>, AssembleError> {
+        let mut pass = FirstPass {
+            statements: Vec::new(),
+            symbols: HashMap::new(),
+            address: PROGRAM_START as u32,
+        };
+        self.parse_source(source, None, &self.include_dir, 0, &mut pass)?;
+        emit(&pass, self.variant)
+    }
+
+    pub fn assemble_file (&self, path: &Path) -> Result<Vec<u8>, AssembleError> {
+        let location = Location { file: Some(path.display().to_string()), line: 0, column: 0 };
+        let source = fs::read_to_string(path)
+            .map_err(|e| AssembleError::new(location, format!("cannot read {}: {}", path.display(), e)))?;
+        let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| self.include_dir.clone());
+
+        let mut pass = FirstPass {
+            statements: Vec::new(),
+            symbols: HashMap::new(),
+            address: PROGRAM_START as u32,
+        };
+        self.parse_source(&source, Some(path.display().to_string()), &dir, 0, &mut pass)?;
+        emit(&pass, self.variant)
+    }
+
+    fn parse_source (&self, source: &str, file: Option<String>, dir: &Path, depth: usize, pass: &mut FirstPass) -> Result<(), AssembleError> {
+        for (index, line) in source.lines().enumerate() {
+            let at = |column: usize| Location { file: file.clone(), line: index + 1, column: column };
+            let tokens = tokenize(line).map_err(|(column, message)| AssembleError::new(at(column), message))?;
+            let mut rest = &tokens[..];
+
+            if let (Some(TokenKind::Ident(name)), Some(TokenKind::Colon)) = (kind_at(rest, 0), kind_at(rest, 1)) {
+                define(pass, name, pass.address as i64, at(rest[0].column))?;
+                rest = &rest[2..];
+            }
+
+            if rest.is_empty() {
+                continue;
+            }
+
+            let keyword = match rest[0].kind {
+                TokenKind::Ident(ref word) => word.clone(),
+                _ => return Err(AssembleError::new(at(rest[0].column), "expected a mnemonic or directive")),
+            };
+
+            if let Some(TokenKind::Ident(word)) = kind_at(rest, 1) {
+                if word.eq_ignore_ascii_case("EQU") {
+                    let expr = parse_expr(&rest[2..], rest[1].column).map_err(|(c, m)| AssembleError::new(at(c), m))?;
+                    let value = evaluate(&expr, &pass.symbols, &at)?;
+                    define(pass, &keyword, value, at(rest[0].column))?;
+                    continue;
+                }
+            }
+
+            let location = at(rest[0].column);
+            let upper = keyword.to_uppercase();
+            let (kind, size) = match upper.as_str() {
+                "INCLUDE" => {
+                    let name = match kind_at(rest, 1) {
+                        Some(TokenKind::Str(name)) if rest.len() == 2 => name.clone(),
+                        _ => return Err(AssembleError::new(location, "INCLUDE expects a quoted file name")),
+                    };
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(AssembleError::new(location, "includes are nested too deeply"));
+                    }
+                    let path = dir.join(&name);
+                    let included = fs::read_to_string(&path)
+                        .map_err(|e| AssembleError::new(location.clone(), format!("cannot include {}: {}", path.display(), e)))?;
+                    let included_dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| dir.to_path_buf());
+                    self.parse_source(&included, Some(path.display().to_string()), &included_dir, depth + 1, pass)?;
+                    continue;
+                },
+                "DB" => {
+                    let items = parse_data(&rest[1..], rest[0].column).map_err(|(c, m)| AssembleError::new(at(c), m))?;
+                    let size = items.iter().map(|item| match *item {
+                        DataItem::Value(_) => 1,
+                        DataItem::Text(ref text) => text.len() as u32,
+                    }).sum();
+                    (StatementKind::Bytes(items), size)
+                },
+                "DW" => {
+                    let items = parse_data(&rest[1..], rest[0].column).map_err(|(c, m)| AssembleError::new(at(c), m))?;
+                    let mut words = Vec::new();
+                    for item in items {
+                        match item {
+                            DataItem::Value(expr) => words.push(expr),
+                            DataItem::Text(_) => return Err(AssembleError::new(location, "DW does not accept strings")),
+                        }
+                    }
+                    let size = words.len() as u32 * 2;
+                    (StatementKind::Words(words), size)
+                },
+                _ => {
+                    if !MNEMONICS.contains(&upper.as_str()) {
+                        return Err(AssembleError::new(location, format!("unknown mnemonic '{}'", keyword)));
+                    }
+                    let operands = parse_operands(&rest[1..], rest[0].column).map_err(|(c, m)| AssembleError::new(at(c), m))?;
+                    let size = if operands.iter().any(|o| matches!(*o, Operand::Long(_))) { 4 } else { 2 };
+                    (StatementKind::Instruction { mnemonic: upper.clone(), operands: operands }, size)
+                },
+            };
+
+            pass.address += size;
+            if pass.address as usize > self.variant.memory_size() {
+                return Err(AssembleError::new(location, "program does not fit in memory"));
+            }
+            pass.statements.push(Statement { location: location, kind: kind });
+        }
+
+        Ok(())
+    }
+}
+
+pub fn assemble (source: &str, variant: Variant) -> Result<Vec<u8>, AssembleError> {
+    Assembler::new(variant).assemble(source)
+}
+
+fn kind_at (tokens: &[Token], index: usize) -> Option<&TokenKind> {
+    tokens.get(index).map(|token| &token.kind)
+}
+
+fn define (pass: &mut FirstPass, name: &str, value: i64, location: Location) -> Result<(), AssembleError> {
+    if parse_register(name).is_some() || parse_keyword(name).is_some() {
+        return Err(AssembleError::new(location, format!("'{}' is a reserved name", name)));
+    }
+    if pass.symbols.contains_key(name) {
+        return Err(AssembleError::new(location, format!("'{}' is already defined", name)));
+    }
+    pass.symbols.insert(name.to_string(), value);
+    Ok(())
+}
+
+fn parse_register (word: &str) -> Option<u8> {
+    let mut chars = word.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('V'), Some(digit), None) | (Some('v'), Some(digit), None) =>
+            digit.to_digit(16).map(|value| value as u8),
+        _ => None,
+    }
+}
+
+fn parse_keyword (word: &str) -> Option<Operand> {
+    match word.to_uppercase().as_str() {
+        "I" => Some(Operand::I),
+        "DT" => Some(Operand::DelayTimer),
+        "ST" => Some(Operand::SoundTimer),
+        "K" => Some(Operand::Key),
+        "F" => Some(Operand::Font),
+        "HF" => Some(Operand::BigFont),
+        "B" => Some(Operand::Bcd),
+        "R" => Some(Operand::Flags),
+        _ => None,
+    }
+}
+
+fn split_commas (tokens: &[Token]) -> Vec<&[Token]> {
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+    tokens.split(|token| token.kind == TokenKind::Comma).collect()
+}
+
+fn missing_column (tokens: &[Token], previous: usize) -> usize {
+    tokens.last().map(|token| token.column).unwrap_or(previous)
+}
+
+fn parse_operands (tokens: &[Token], mnemonic_column: usize) -> Result<Vec<Operand>, (usize, String)> {
+    let mut operands = Vec::new();
+    let mut column = mnemonic_column;
+
+    for group in split_commas(tokens) {
+        if group.is_empty() {
+            return Err((column, "missing operand".to_string()));
+        }
+        column = group[0].column;
+
+        let kinds: Vec<&TokenKind> = group.iter().map(|token| &token.kind).collect();
+        let operand = match kinds.as_slice() {
+            [TokenKind::Ident(word)] if parse_register(word).is_some() =>
+                Operand::Register(parse_register(word).unwrap()),
+            [TokenKind::Ident(word)] if parse_keyword(word).is_some() =>
+                parse_keyword(word).unwrap(),
+            [TokenKind::LeftBracket, TokenKind::Ident(word), TokenKind::RightBracket] if word.eq_ignore_ascii_case("I") =>
+                Operand::IndirectI,
+            [TokenKind::Ident(word), ..] if word.eq_ignore_ascii_case("LONG") =>
+                Operand::Long(parse_expr(&group[1..], column)?),
+            _ => Operand::Value(parse_expr(group, column)?),
+        };
+        operands.push(operand);
+    }
+
+    Ok(operands)
+}
+
+fn parse_data (tokens: &[Token], directive_column: usize) -> Result<Vec<DataItem>, (usize, String)> {
+    let mut items = Vec::new();
+    let mut column = directive_column;
+
+    for group in split_commas(tokens) {
+        if group.is_empty() {
+            return Err((column, "missing value".to_string()));
+        }
+        column = group[0].column;
+
+        match group[0].kind {
+            TokenKind::Str(ref text) if group.len() == 1 => items.push(DataItem::Text(text.clone())),
+            _ => items.push(DataItem::Value(parse_expr(group, column)?)),
+        }
+    }
+
+    if items.is_empty() {
+        return Err((directive_column, "expected at least one value".to_string()));
+    }
+    Ok(items)
+}
+
+fn parse_expr (tokens: &[Token], column: usize) -> Result<Expr, (usize, String)> {
+    if tokens.is_empty() {
+        return Err((column, "expected a value".to_string()));
+    }
+
+    let mut terms = Vec::new();
+    let mut position = 0;
+    while position < tokens.len() {
+        let mut negative = false;
+        if !terms.is_empty() || tokens[position].kind == TokenKind::Minus || tokens[position].kind == TokenKind::Plus {
+            match tokens[position].kind {
+                TokenKind::Plus => {},
+                TokenKind::Minus => negative = true,
+                _ => return Err((tokens[position].column, "expected '+' or '-'".to_string())),
+            }
+            position += 1;
+        }
+
+        let token = match tokens.get(position) {
+            Some(token) => token,
+            None => return Err((missing_column(tokens, column), "expected a value".to_string())),
+        };
+        let term = match token.kind {
+            TokenKind::Number(value) => Term::Number(value),
+            TokenKind::Ident(ref name) => Term::Symbol(name.clone()),
+            _ => return Err((token.column, "expected a number or symbol".to_string())),
+        };
+        terms.push((negative, term, token.column));
+        position += 1;
+    }
+
+    Ok(Expr { terms: terms, column: tokens[0].column })
+}
+
+fn evaluate<F> (expr: &Expr, symbols: &HashMap<String, i64>, at: &F) -> Result<i64, AssembleError>
+    where F: Fn(usize) -> Location {
+    let mut total = 0;
+    for &(negative, ref term, column) in &expr.terms {
+        let value = match *term {
+            Term::Number(value) => value,
+            Term::Symbol(ref name) => match symbols.get(name) {
+                Some(value) => *value,
+                None => return Err(AssembleError::new(at(column), format!("undefined symbol '{}'", name))),
+            },
+        };
+        total = if negative { total - value } else { total + value };
+    }
+    Ok(total)
+}
+
+fn emit (pass: &FirstPass, variant: Variant) -> Result<Vec<u8>, AssembleError> {
+    let mut bytes = Vec::new();
+
+    for statement in &pass.statements {
+        let location = &statement.location;
+        let at = |column: usize| Location { file: location.file.clone(), line: location.line, column: column };
+        let value = |expr: &Expr, min: i64, max: i64| -> Result<i64, AssembleError> {
+            let value = evaluate(expr, &pass.symbols, &at)?;
+            if value < min || value > max {
+                return Err(AssembleError::new(at(expr.column), format!("value {} is out of range ({}..{})", value, min, max)));
+            }
+            Ok(value)
+        };
+
+        match statement.kind {
+            StatementKind::Bytes(ref items) => {
+                for item in items {
+                    match *item {
+                        DataItem::Value(ref expr) => bytes.push(value(expr, -0x80, 0xFF)? as u8),
+                        DataItem::Text(ref text) => bytes.extend_from_slice(text.as_bytes()),
+                    }
+                }
+            },
+            StatementKind::Words(ref words) => {
+                for expr in words {
+                    let word = value(expr, -0x8000, 0xFFFF)? as u16;
+                    bytes.push((word >> 8) as u8);
+                    bytes.push(word as u8);
+                }
+            },
+            StatementKind::Instruction { ref mnemonic, ref operands } => {
+                let address = |expr: &Expr| value(expr, 0, 0xFFF).map(|v| v as u16);
+                let byte = |expr: &Expr| value(expr, -0x80, 0xFF).map(|v| v as u8);
+                let nibble = |expr: &Expr| value(expr, 0, 0xF).map(|v| v as u8);
+                let mut long_address = None;
+
+                let instruction = match (mnemonic.as_str(), operands.as_slice()) {
+                    ("CLS", []) => Instruction::ClearScreen,
+                    ("RET", []) => Instruction::Return,
+                    ("SCD", [Operand::Value(n)]) => Instruction::ScrollDown(nibble(n)?),
+                    ("SCR", []) => Instruction::ScrollRight,
+                    ("SCL", []) => Instruction::ScrollLeft,
+                    ("EXIT", []) => Instruction::Exit,
+                    ("LOW", []) => Instruction::LowRes,
+                    ("HIGH", []) => Instruction::HighRes,
+                    ("JP", [Operand::Value(nnn)]) => Instruction::Jump(address(nnn)?),
+                    ("JP", [Operand::Register(0), Operand::Value(nnn)]) => Instruction::JumpOffset(address(nnn)?),
+                    ("CALL", [Operand::Value(nnn)]) => Instruction::Call(address(nnn)?),
+                    ("SE", [Operand::Register(x), Operand::Register(y)]) => Instruction::SkipEqReg(*x, *y),
+                    ("SE", [Operand::Register(x), Operand::Value(kk)]) => Instruction::SkipEqImm(*x, byte(kk)?),
+                    ("SNE", [Operand::Register(x), Operand::Register(y)]) => Instruction::SkipNeReg(*x, *y),
+                    ("SNE", [Operand::Register(x), Operand::Value(kk)]) => Instruction::SkipNeImm(*x, byte(kk)?),
+                    ("SAVE", [Operand::Register(x), Operand::Register(y)]) => Instruction::SaveRange(*x, *y),
+                    ("LOAD", [Operand::Register(x), Operand::Register(y)]) => Instruction::LoadRange(*x, *y),
+                    ("LD", [Operand::Register(x), Operand::Value(kk)]) => Instruction::LoadImm(*x, byte(kk)?),
+                    ("LD", [Operand::Register(x), Operand::Register(y)]) => Instruction::LoadReg(*x, *y),
+                    ("LD", [Operand::I, Operand::Value(nnn)]) => Instruction::LoadI(address(nnn)?),
+                    ("LD", [Operand::I, Operand::Long(nnnn)]) => {
+                        long_address = Some(value(nnnn, 0, 0xFFFF)? as u16);
+                        Instruction::LongLoadI
+                    },
+                    ("LD", [Operand::Register(x), Operand::DelayTimer]) => Instruction::LoadDelay(*x),
+                    ("LD", [Operand::Register(x), Operand::Key]) => Instruction::WaitKey(*x),
+                    ("LD", [Operand::DelayTimer, Operand::Register(x)]) => Instruction::SetDelay(*x),
+                    ("LD", [Operand::SoundTimer, Operand::Register(x)]) => Instruction::SetSound(*x),
+                    ("LD", [Operand::Font, Operand::Register(x)]) => Instruction::LoadFont(*x),
+                    ("LD", [Operand::BigFont, Operand::Register(x)]) => Instruction::LoadBigFont(*x),
+                    ("LD", [Operand::Bcd, Operand::Register(x)]) => Instruction::Bcd(*x),
+                    ("LD", [Operand::IndirectI, Operand::Register(x)]) => Instruction::Store(*x),
+                    ("LD", [Operand::Register(x), Operand::IndirectI]) => Instruction::Load(*x),
+                    ("LD", [Operand::Flags, Operand::Register(x)]) => Instruction::StoreFlags(*x),
+                    ("LD", [Operand::Register(x), Operand::Flags]) => Instruction::LoadFlags(*x),
+                    ("ADD", [Operand::Register(x), Operand::Value(kk)]) => Instruction::AddImm(*x, byte(kk)?),
+                    ("ADD", [Operand::Register(x), Operand::Register(y)]) => Instruction::Add(*x, *y),
+                    ("ADD", [Operand::I, Operand::Register(x)]) => Instruction::AddI(*x),
+                    ("OR", [Operand::Register(x), Operand::Register(y)]) => Instruction::Or(*x, *y),
+                    ("AND", [Operand::Register(x), Operand::Register(y)]) => Instruction::And(*x, *y),
+                    ("XOR", [Operand::Register(x), Operand::Register(y)]) => Instruction::Xor(*x, *y),
+                    ("SUB", [Operand::Register(x), Operand::Register(y)]) => Instruction::Sub(*x, *y),
+                    ("SUBN", [Operand::Register(x), Operand::Register(y)]) => Instruction::SubN(*x, *y),
+                    ("SHR", [Operand::Register(x)]) => Instruction::ShiftRight(*x, *x),
+                    ("SHR", [Operand::Register(x), Operand::Register(y)]) => Instruction::ShiftRight(*x, *y),
+                    ("SHL", [Operand::Register(x)]) => Instruction::ShiftLeft(*x, *x),
+                    ("SHL", [Operand::Register(x), Operand::Register(y)]) => Instruction::ShiftLeft(*x, *y),
+                    ("RND", [Operand::Register(x), Operand::Value(kk)]) => Instruction::Random(*x, byte(kk)?),
+                    ("DRW", [Operand::Register(x), Operand::Register(y), Operand::Value(n)]) => Instruction::Draw(*x, *y, nibble(n)?),
+                    ("SKP", [Operand::Register(x)]) => Instruction::SkipKey(*x),
+                    ("SKNP", [Operand::Register(x)]) => Instruction::SkipNotKey(*x),
+                    ("PLANE", [Operand::Value(n)]) => Instruction::Plane(nibble(n)?),
+                    ("AUDIO", []) => Instruction::Audio,
+                    ("PITCH", [Operand::Register(x)]) => Instruction::Pitch(*x),
+                    _ => return Err(AssembleError::new(location.clone(), format!("invalid operands for {}", mnemonic))),
+                };
+                if !instruction.is_supported_by(variant) {
+                    return Err(AssembleError::new(location.clone(), format!("{} is not supported by {:?}", mnemonic, variant)));
+                }
+
+                let opcode = instruction.encode();
+                bytes.push((opcode >> 8) as u8);
+                bytes.push(opcode as u8);
+                if let Some(address) = long_address {
+                    bytes.push((address >> 8) as u8);
+                    bytes.push(address as u8);
+                }
+            },
+        }
+    }
+
+    Ok(bytes)
+}
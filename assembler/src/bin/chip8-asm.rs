@@ -0,0 +1,70 @@
+extern crate chip8_assembler;
+extern crate chip8_emulator;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use chip8_assembler::{Assembler, OctoCompiler};
+use chip8_emulator::variant::Variant;
+
+fn usage() -> ! {
+    eprintln!("usage: chip8-asm <source.asm|source.8o> [-o <output.ch8>] [--variant chip8|schip|xochip]");
+    process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut input = None;
+    let mut output = None;
+    let mut variant = Variant::XoChip;
+
+    let mut index = 0;
+    while index < args.len() {
+        match args[index].as_str() {
+            "-o" => {
+                index += 1;
+                output = Some(PathBuf::from(args.get(index).unwrap_or_else(|| usage())));
+            },
+            "--variant" => {
+                index += 1;
+                variant = match args.get(index).map(|s| s.as_str()) {
+                    Some("chip8") => Variant::Chip8,
+                    Some("schip") => Variant::SuperChip,
+                    Some("xochip") => Variant::XoChip,
+                    _ => usage(),
+                };
+            },
+            "-h" | "--help" => usage(),
+            path if input.is_none() => input = Some(PathBuf::from(path)),
+            _ => usage(),
+        }
+        index += 1;
+    }
+
+    let input = input.unwrap_or_else(|| usage());
+    let output = output.unwrap_or_else(|| input.with_extension("ch8"));
+
+    let is_octo = input.extension().map(|extension| extension == "8o").unwrap_or(false);
+    let result = if is_octo {
+        fs::read_to_string(&input)
+            .map_err(|e| format!("cannot read {}: {}", input.display(), e))
+            .and_then(|source| OctoCompiler::new(variant).compile(&source)
+                .map_err(|e| format!("{}:{}", input.display(), e)))
+    } else {
+        Assembler::new(variant).assemble_file(Path::new(&input)).map_err(|e| e.to_string())
+    };
+
+    let rom = match result {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        },
+    };
+
+    if let Err(e) = fs::write(&output, &rom) {
+        eprintln!("error: cannot write {}: {}", output.display(), e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}:{}:{}", file, self.line, self.column),
+            None => write!(f, "{}:{}", self.line, self.column),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssembleError {
+    pub location: Location,
+    pub message: String,
+}
+
+impl AssembleError {
+    pub fn new<S: Into<String>> (location: Location, message: S) -> AssembleError {
+        AssembleError { location: location, message: message.into() }
+    }
+
+    pub fn line (&self) -> usize {
+        self.location.line
+    }
+
+    pub fn column (&self) -> usize {
+        self.location.column
+    }
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+impl Error for AssembleError {}
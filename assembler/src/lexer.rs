@@ -0,0 +1,98 @@
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident(String),
+    Number(i64),
+    Str(String),
+    Comma,
+    Colon,
+    Plus,
+    Minus,
+    LeftBracket,
+    RightBracket,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub column: usize,
+}
+
+// Splits one source line into tokens. Errors carry the 1-based column.
+pub fn tokenize (line: &str) -> Result<Vec<Token>, (usize, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+
+    while position < chars.len() {
+        let c = chars[position];
+        let column = position + 1;
+
+        if c == ';' {
+            break;
+        }
+        if c.is_whitespace() {
+            position += 1;
+            continue;
+        }
+
+        let single = match c {
+            ',' => Some(TokenKind::Comma),
+            ':' => Some(TokenKind::Colon),
+            '+' => Some(TokenKind::Plus),
+            '-' => Some(TokenKind::Minus),
+            '[' => Some(TokenKind::LeftBracket),
+            ']' => Some(TokenKind::RightBracket),
+            _ => None,
+        };
+        if let Some(kind) = single {
+            tokens.push(Token { kind: kind, column: column });
+            position += 1;
+            continue;
+        }
+
+        if c == '"' {
+            let start = position + 1;
+            let mut end = start;
+            while end < chars.len() && chars[end] != '"' {
+                end += 1;
+            }
+            if end >= chars.len() {
+                return Err((column, "unterminated string".to_string()));
+            }
+            let text: String = chars[start..end].iter().collect();
+            tokens.push(Token { kind: TokenKind::Str(text), column: column });
+            position = end + 1;
+            continue;
+        }
+
+        if c.is_alphanumeric() || c == '_' || c == '.' {
+            let start = position;
+            while position < chars.len() && (chars[position].is_alphanumeric() || chars[position] == '_' || chars[position] == '.') {
+                position += 1;
+            }
+            let word: String = chars[start..position].iter().collect();
+            let kind = if c.is_ascii_digit() {
+                TokenKind::Number(parse_number(&word).ok_or((column, format!("invalid number '{}'", word)))?)
+            } else {
+                TokenKind::Ident(word)
+            };
+            tokens.push(Token { kind: kind, column: column });
+            continue;
+        }
+
+        return Err((column, format!("unexpected character '{}'", c)));
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number (word: &str) -> Option<i64> {
+    let lower = word.to_lowercase();
+    if let Some(digits) = lower.strip_prefix("0x") {
+        i64::from_str_radix(digits, 16).ok()
+    } else if let Some(digits) = lower.strip_prefix("0b") {
+        i64::from_str_radix(digits, 2).ok()
+    } else {
+        lower.parse::<i64>().ok()
+    }
+}
@@ -0,0 +1,10 @@
+extern crate chip8_emulator;
+
+pub mod assembler;
+pub mod error;
+mod lexer;
+pub mod octo;
+
+pub use assembler::{assemble, Assembler, PROGRAM_START};
+pub use error::{AssembleError, Location};
+pub use octo::{compile_octo, OctoCompiler};
@@ -0,0 +1,824 @@
+use std::collections::{HashMap, VecDeque};
+use chip8_emulator::instruction::Instruction;
+use chip8_emulator::variant::Variant;
+use assembler::PROGRAM_START;
+use error::{AssembleError, Location};
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+struct Fixup {
+    offset: usize,
+    label: Token,
+    long: bool,
+}
+
+enum Frame {
+    If { jump: usize, has_else: bool },
+    Loop { start: u16, breaks: Vec<usize> },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Compare {
+    Equal,
+    NotEqual,
+    Key,
+    NotKey,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+}
+
+impl Compare {
+    fn negate (&self) -> Compare {
+        match *self {
+            Compare::Equal => Compare::NotEqual,
+            Compare::NotEqual => Compare::Equal,
+            Compare::Key => Compare::NotKey,
+            Compare::NotKey => Compare::Key,
+            Compare::Less => Compare::GreaterEqual,
+            Compare::GreaterEqual => Compare::Less,
+            Compare::Greater => Compare::LessEqual,
+            Compare::LessEqual => Compare::Greater,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Operand {
+    Register(u8),
+    Value(u8),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Condition {
+    x: u8,
+    compare: Compare,
+    operand: Option<Operand>,
+}
+
+pub struct OctoCompiler {
+    variant: Variant,
+}
+
+impl OctoCompiler {
+    pub fn new(variant: Variant) -> OctoCompiler {
+        OctoCompiler { variant: variant }
+    }
+
+    pub fn compile (&self, source: &str) -> Result<Vec<u8>, AssembleError> {
+        let mut compilation = Compilation {
+            variant: self.variant,
+            tokens: tokenize(source),
+            rom: Vec::new(),
+            offset: 0,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            frames: Vec::new(),
+            last_line: 1,
+        };
+        compilation.run()?;
+        Ok(compilation.rom)
+    }
+}
+
+pub fn compile_octo (source: &str, variant: Variant) -> Result<Vec<u8>, AssembleError> {
+    OctoCompiler::new(variant).compile(source)
+}
+
+fn tokenize (source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut position = 0;
+        while position < chars.len() {
+            if chars[position].is_whitespace() {
+                position += 1;
+                continue;
+            }
+            if chars[position] == '#' {
+                break;
+            }
+            let start = position;
+            while position < chars.len() && !chars[position].is_whitespace() {
+                position += 1;
+            }
+            tokens.push_back(Token {
+                text: chars[start..position].iter().collect(),
+                line: index + 1,
+                column: start + 1,
+            });
+        }
+    }
+
+    tokens
+}
+
+fn parse_number (text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let lower = digits.to_lowercase();
+    let value = if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if lower.chars().all(|c| c.is_ascii_digit()) && !lower.is_empty() {
+        lower.parse::<i64>().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn is_identifier (text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-'),
+        _ => false,
+    }
+}
+
+struct Compilation {
+    variant: Variant,
+    tokens: VecDeque<Token>,
+    rom: Vec<u8>,
+    offset: usize,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, i64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    frames: Vec<Frame>,
+    last_line: usize,
+}
+
+impl Compilation {
+    fn run (&mut self) -> Result<(), AssembleError> {
+        // Octo programs start with a jump to `main`, dropped again if nothing precedes it
+        let main = Token { text: "main".to_string(), line: 1, column: 1 };
+        self.fixups.push(Fixup { offset: self.offset, label: main, long: false });
+        self.write_word(0x1000);
+
+        while !self.tokens.is_empty() {
+            self.statement()?;
+        }
+
+        if let Some(frame) = self.frames.last() {
+            let message = match *frame {
+                Frame::If { .. } => "'begin' without matching 'end'",
+                Frame::Loop { .. } => "'loop' without matching 'again'",
+            };
+            return Err(AssembleError::new(self.end_location(), message));
+        }
+
+        if PROGRAM_START as usize + self.rom.len() > self.variant.memory_size() {
+            return Err(AssembleError::new(self.end_location(), "program does not fit in memory"));
+        }
+
+        let fixups: Vec<Fixup> = self.fixups.drain(..).collect();
+        for fixup in fixups {
+            let address = match self.labels.get(&fixup.label.text) {
+                Some(address) => *address,
+                None => return Err(self.error(&fixup.label, format!("undefined label '{}'", fixup.label.text))),
+            };
+            if fixup.long {
+                self.rom[fixup.offset] = (address >> 8) as u8;
+                self.rom[fixup.offset + 1] = address as u8;
+            } else {
+                if address > 0xFFF {
+                    return Err(self.error(&fixup.label, format!("label '{}' is out of 12-bit range", fixup.label.text)));
+                }
+                self.rom[fixup.offset] |= (address >> 8) as u8;
+                self.rom[fixup.offset + 1] = address as u8;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn location (&self, token: &Token) -> Location {
+        Location { file: None, line: token.line, column: token.column }
+    }
+
+    fn end_location (&self) -> Location {
+        Location { file: None, line: self.last_line, column: 1 }
+    }
+
+    fn error<S: Into<String>> (&self, token: &Token, message: S) -> AssembleError {
+        AssembleError::new(self.location(token), message)
+    }
+
+    fn here (&self) -> u16 {
+        (PROGRAM_START as usize + self.offset) as u16
+    }
+
+    fn next (&mut self) -> Result<Token, AssembleError> {
+        match self.tokens.pop_front() {
+            Some(token) => {
+                self.last_line = token.line;
+                Ok(token)
+            },
+            None => Err(AssembleError::new(self.end_location(), "unexpected end of file")),
+        }
+    }
+
+    fn peek_is (&self, text: &str) -> bool {
+        self.tokens.front().map(|token| token.text == text).unwrap_or(false)
+    }
+
+    fn expect (&mut self, text: &str) -> Result<Token, AssembleError> {
+        let token = self.next()?;
+        if token.text != text {
+            return Err(self.error(&token, format!("expected '{}', found '{}'", text, token.text)));
+        }
+        Ok(token)
+    }
+
+    fn write_byte (&mut self, byte: u8) {
+        if self.rom.len() <= self.offset {
+            self.rom.resize(self.offset + 1, 0);
+        }
+        self.rom[self.offset] = byte;
+        self.offset += 1;
+    }
+
+    fn write_word (&mut self, word: u16) {
+        self.write_byte((word >> 8) as u8);
+        self.write_byte(word as u8);
+    }
+
+    fn emit (&mut self, instruction: Instruction, token: &Token) -> Result<(), AssembleError> {
+        if !instruction.is_supported_by(self.variant) {
+            return Err(self.error(token, format!("'{}' is not supported by {:?}", token.text, self.variant)));
+        }
+        self.write_word(instruction.encode());
+        Ok(())
+    }
+
+    fn define_label (&mut self, token: &Token) -> Result<(), AssembleError> {
+        self.check_name(token)?;
+        if token.text == "main" && self.offset == 2 && self.rom.len() == 2 {
+            self.rom.clear();
+            self.offset = 0;
+            self.fixups.retain(|fixup| fixup.offset != 0);
+        }
+        let here = self.here();
+        self.labels.insert(token.text.clone(), here);
+        Ok(())
+    }
+
+    fn check_name (&self, token: &Token) -> Result<(), AssembleError> {
+        if !is_identifier(&token.text) || self.register_index(&token.text).is_some() {
+            return Err(self.error(token, format!("invalid name '{}'", token.text)));
+        }
+        if self.labels.contains_key(&token.text) || self.constants.contains_key(&token.text) {
+            return Err(self.error(token, format!("'{}' is already defined", token.text)));
+        }
+        Ok(())
+    }
+
+    fn register_index (&self, text: &str) -> Option<u8> {
+        if let Some(index) = self.aliases.get(text) {
+            return Some(*index);
+        }
+        let mut chars = text.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some('v'), Some(digit), None) | (Some('V'), Some(digit), None) =>
+                digit.to_digit(16).map(|value| value as u8),
+            _ => None,
+        }
+    }
+
+    fn register (&mut self) -> Result<u8, AssembleError> {
+        let token = self.next()?;
+        match self.register_index(&token.text) {
+            Some(index) => Ok(index),
+            None => Err(self.error(&token, format!("expected a register, found '{}'", token.text))),
+        }
+    }
+
+    fn lookup (&self, text: &str) -> Option<i64> {
+        parse_number(text)
+            .or_else(|| self.constants.get(text).cloned())
+            .or_else(|| self.labels.get(text).map(|address| *address as i64))
+    }
+
+    fn value (&mut self, min: i64, max: i64) -> Result<i64, AssembleError> {
+        let token = self.next()?;
+        let value = match self.lookup(&token.text) {
+            Some(value) => value,
+            None => return Err(self.error(&token, format!("undefined name '{}'", token.text))),
+        };
+        if value < min || value > max {
+            return Err(self.error(&token, format!("value {} is out of range ({}..{})", value, min, max)));
+        }
+        Ok(value)
+    }
+
+    fn byte_value (&mut self) -> Result<u8, AssembleError> {
+        self.value(-0x80, 0xFF).map(|value| value as u8)
+    }
+
+    fn nibble_value (&mut self) -> Result<u8, AssembleError> {
+        self.value(0, 0xF).map(|value| value as u8)
+    }
+
+    // Address operand which may be a label defined further down
+    fn address (&mut self, long: bool) -> Result<u16, AssembleError> {
+        let token = self.next()?;
+        let max = if long { 0xFFFF } else { 0xFFF };
+        match self.lookup(&token.text) {
+            Some(value) if value >= 0 && value <= max => Ok(value as u16),
+            Some(value) => Err(self.error(&token, format!("address {} is out of range", value))),
+            None if is_identifier(&token.text) => {
+                // Short operands are patched into the instruction emitted next,
+                // long ones into the word following the already emitted F000
+                let offset = self.offset;
+                self.fixups.push(Fixup { offset: offset, label: token, long: long });
+                Ok(0)
+            },
+            None => Err(self.error(&token, format!("expected an address, found '{}'", token.text))),
+        }
+    }
+
+    fn statement (&mut self) -> Result<(), AssembleError> {
+        let token = self.next()?;
+
+        if let Some(x) = self.register_index(&token.text) {
+            return self.register_statement(x, &token);
+        }
+
+        match token.text.as_str() {
+            ":" => {
+                let name = self.next()?;
+                self.define_label(&name)
+            },
+            ":const" => {
+                let name = self.next()?;
+                self.check_name(&name)?;
+                let value = self.value(i64::MIN, i64::MAX)?;
+                self.constants.insert(name.text, value);
+                Ok(())
+            },
+            ":alias" => {
+                let name = self.next()?;
+                if !is_identifier(&name.text) {
+                    return Err(self.error(&name, format!("invalid name '{}'", name.text)));
+                }
+                let index = self.register()?;
+                self.aliases.insert(name.text, index);
+                Ok(())
+            },
+            ":calc" => {
+                let name = self.next()?;
+                self.check_name(&name)?;
+                let value = self.calc()?;
+                self.constants.insert(name.text, value);
+                Ok(())
+            },
+            ":macro" => self.define_macro(),
+            ":byte" => {
+                let value = if self.peek_is("{") {
+                    self.calc()? as u8
+                } else {
+                    self.byte_value()?
+                };
+                self.write_byte(value);
+                Ok(())
+            },
+            ":org" => {
+                let address = self.value(PROGRAM_START as i64, self.variant.memory_size() as i64 - 1)?;
+                self.offset = address as usize - PROGRAM_START as usize;
+                Ok(())
+            },
+            ":breakpoint" => self.next().map(|_| ()),
+            "clear" => self.emit(Instruction::ClearScreen, &token),
+            "return" | ";" => self.emit(Instruction::Return, &token),
+            "hires" => self.emit(Instruction::HighRes, &token),
+            "lores" => self.emit(Instruction::LowRes, &token),
+            "exit" => self.emit(Instruction::Exit, &token),
+            "scroll-left" => self.emit(Instruction::ScrollLeft, &token),
+            "scroll-right" => self.emit(Instruction::ScrollRight, &token),
+            "scroll-down" => {
+                let rows = self.nibble_value()?;
+                self.emit(Instruction::ScrollDown(rows), &token)
+            },
+            "audio" => self.emit(Instruction::Audio, &token),
+            "plane" => {
+                let planes = self.nibble_value()?;
+                self.emit(Instruction::Plane(planes), &token)
+            },
+            "bcd" => {
+                let x = self.register()?;
+                self.emit(Instruction::Bcd(x), &token)
+            },
+            "save" | "load" => {
+                let x = self.register()?;
+                if self.peek_is("-") {
+                    self.next()?;
+                    let y = self.register()?;
+                    let instruction = if token.text == "save" { Instruction::SaveRange(x, y) } else { Instruction::LoadRange(x, y) };
+                    return self.emit(instruction, &token);
+                }
+                let instruction = if token.text == "save" { Instruction::Store(x) } else { Instruction::Load(x) };
+                self.emit(instruction, &token)
+            },
+            "saveflags" => {
+                let x = self.register()?;
+                self.emit(Instruction::StoreFlags(x), &token)
+            },
+            "loadflags" => {
+                let x = self.register()?;
+                self.emit(Instruction::LoadFlags(x), &token)
+            },
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let height = self.nibble_value()?;
+                self.emit(Instruction::Draw(x, y, height), &token)
+            },
+            "jump" => {
+                let address = self.address(false)?;
+                self.emit(Instruction::Jump(address), &token)
+            },
+            "jump0" => {
+                let address = self.address(false)?;
+                self.emit(Instruction::JumpOffset(address), &token)
+            },
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                let instruction = match token.text.as_str() {
+                    "delay" => Instruction::SetDelay(x),
+                    "buzzer" => Instruction::SetSound(x),
+                    _ => Instruction::Pitch(x),
+                };
+                self.emit(instruction, &token)
+            },
+            "i" => self.i_statement(&token),
+            "if" => self.if_statement(&token),
+            "else" => {
+                match self.frames.pop() {
+                    Some(Frame::If { jump, has_else: false }) => {
+                        let end_jump = self.offset;
+                        self.emit(Instruction::Jump(0), &token)?;
+                        self.patch_jump(jump);
+                        self.frames.push(Frame::If { jump: end_jump, has_else: true });
+                        Ok(())
+                    },
+                    _ => Err(self.error(&token, "'else' without matching 'begin'")),
+                }
+            },
+            "end" => {
+                match self.frames.pop() {
+                    Some(Frame::If { jump, .. }) => {
+                        self.patch_jump(jump);
+                        Ok(())
+                    },
+                    _ => Err(self.error(&token, "'end' without matching 'begin'")),
+                }
+            },
+            "loop" => {
+                let start = self.here();
+                self.frames.push(Frame::Loop { start: start, breaks: Vec::new() });
+                Ok(())
+            },
+            "while" => {
+                let condition = self.condition()?;
+                let negated = Condition { compare: condition.compare.negate(), ..condition };
+                self.skip_unless(negated, &token)?;
+                let jump = self.offset;
+                self.emit(Instruction::Jump(0), &token)?;
+                for frame in self.frames.iter_mut().rev() {
+                    if let Frame::Loop { ref mut breaks, .. } = *frame {
+                        breaks.push(jump);
+                        return Ok(());
+                    }
+                }
+                Err(self.error(&token, "'while' outside of a loop"))
+            },
+            "again" => {
+                match self.frames.pop() {
+                    Some(Frame::Loop { start, breaks }) => {
+                        self.emit(Instruction::Jump(start), &token)?;
+                        for jump in breaks {
+                            self.patch_jump(jump);
+                        }
+                        Ok(())
+                    },
+                    _ => Err(self.error(&token, "'again' without matching 'loop'")),
+                }
+            },
+            _ => {
+                if let Some(value) = parse_number(&token.text).or_else(|| self.constants.get(&token.text).cloned()) {
+                    if !(-0x80..=0xFF).contains(&value) {
+                        return Err(self.error(&token, format!("byte {} is out of range", value)));
+                    }
+                    self.write_byte(value as u8);
+                    return Ok(());
+                }
+                if self.macros.contains_key(&token.text) {
+                    return self.expand_macro(&token);
+                }
+                if is_identifier(&token.text) {
+                    self.tokens.push_front(token.clone());
+                    let address = self.address(false)?;
+                    return self.emit(Instruction::Call(address), &token);
+                }
+                Err(self.error(&token, format!("unexpected '{}'", token.text)))
+            },
+        }
+    }
+
+    fn register_statement (&mut self, x: u8, register: &Token) -> Result<(), AssembleError> {
+        let operator = self.next()?;
+        let source = self.next()?;
+        let y = self.register_index(&source.text);
+
+        let instruction = match (operator.text.as_str(), y) {
+            (":=", Some(y)) => Instruction::LoadReg(x, y),
+            (":=", None) if source.text == "random" => Instruction::Random(x, self.byte_value()?),
+            (":=", None) if source.text == "key" => Instruction::WaitKey(x),
+            (":=", None) if source.text == "delay" => Instruction::LoadDelay(x),
+            ("+=", Some(y)) => Instruction::Add(x, y),
+            ("-=", Some(y)) => Instruction::Sub(x, y),
+            ("=-", Some(y)) => Instruction::SubN(x, y),
+            ("|=", Some(y)) => Instruction::Or(x, y),
+            ("&=", Some(y)) => Instruction::And(x, y),
+            ("^=", Some(y)) => Instruction::Xor(x, y),
+            (">>=", Some(y)) => Instruction::ShiftRight(x, y),
+            ("<<=", Some(y)) => Instruction::ShiftLeft(x, y),
+            (":=", None) | ("+=", None) | ("-=", None) => {
+                self.tokens.push_front(source.clone());
+                let value = self.byte_value()?;
+                match operator.text.as_str() {
+                    ":=" => Instruction::LoadImm(x, value),
+                    "+=" => Instruction::AddImm(x, value),
+                    _ => Instruction::AddImm(x, value.wrapping_neg()),
+                }
+            },
+            _ => return Err(self.error(&operator, format!("invalid operation '{} {} {}'", register.text, operator.text, source.text))),
+        };
+
+        self.emit(instruction, register)
+    }
+
+    fn i_statement (&mut self, token: &Token) -> Result<(), AssembleError> {
+        let operator = self.next()?;
+        match operator.text.as_str() {
+            "+=" => {
+                let x = self.register()?;
+                self.emit(Instruction::AddI(x), token)
+            },
+            ":=" => {
+                if self.peek_is("hex") || self.peek_is("bighex") {
+                    let kind = self.next()?;
+                    let x = self.register()?;
+                    let instruction = if kind.text == "hex" { Instruction::LoadFont(x) } else { Instruction::LoadBigFont(x) };
+                    return self.emit(instruction, token);
+                }
+                if self.peek_is("long") {
+                    self.next()?;
+                    self.emit(Instruction::LongLoadI, token)?;
+                    let address = self.address(true)?;
+                    self.write_word(address);
+                    return Ok(());
+                }
+                let address = self.address(false)?;
+                self.emit(Instruction::LoadI(address), token)
+            },
+            _ => Err(self.error(&operator, format!("invalid operation 'i {}'", operator.text))),
+        }
+    }
+
+    fn condition (&mut self) -> Result<Condition, AssembleError> {
+        let x = self.register()?;
+        let operator = self.next()?;
+        let compare = match operator.text.as_str() {
+            "==" => Compare::Equal,
+            "!=" => Compare::NotEqual,
+            "key" => return Ok(Condition { x: x, compare: Compare::Key, operand: None }),
+            "-key" => return Ok(Condition { x: x, compare: Compare::NotKey, operand: None }),
+            "<" => Compare::Less,
+            ">" => Compare::Greater,
+            "<=" => Compare::LessEqual,
+            ">=" => Compare::GreaterEqual,
+            _ => return Err(self.error(&operator, format!("unknown comparison '{}'", operator.text))),
+        };
+
+        let operand = match self.tokens.front().and_then(|token| self.register_index(&token.text)) {
+            Some(y) => {
+                self.next()?;
+                Operand::Register(y)
+            },
+            None => Operand::Value(self.byte_value()?),
+        };
+
+        Ok(Condition { x: x, compare: compare, operand: Some(operand) })
+    }
+
+    // Emits code that skips the next instruction when the condition does not hold
+    fn skip_unless (&mut self, condition: Condition, token: &Token) -> Result<(), AssembleError> {
+        let x = condition.x;
+        let operand = condition.operand;
+
+        match (condition.compare, operand) {
+            (Compare::Equal, Some(Operand::Value(kk))) => self.emit(Instruction::SkipNeImm(x, kk), token),
+            (Compare::Equal, Some(Operand::Register(y))) => self.emit(Instruction::SkipNeReg(x, y), token),
+            (Compare::NotEqual, Some(Operand::Value(kk))) => self.emit(Instruction::SkipEqImm(x, kk), token),
+            (Compare::NotEqual, Some(Operand::Register(y))) => self.emit(Instruction::SkipEqReg(x, y), token),
+            (Compare::Key, _) => self.emit(Instruction::SkipNotKey(x), token),
+            (Compare::NotKey, _) => self.emit(Instruction::SkipKey(x), token),
+            (compare, Some(operand)) => {
+                // Comparisons go through VF: vf := operand, then subtract so the carry holds the result
+                match operand {
+                    Operand::Register(y) => self.emit(Instruction::LoadReg(0xF, y), token)?,
+                    Operand::Value(kk) => self.emit(Instruction::LoadImm(0xF, kk), token)?,
+                }
+                let (subtract, flag_when_true) = match compare {
+                    Compare::Less => (Instruction::SubN(0xF, x), 0),
+                    Compare::GreaterEqual => (Instruction::SubN(0xF, x), 1),
+                    Compare::Greater => (Instruction::Sub(0xF, x), 0),
+                    _ => (Instruction::Sub(0xF, x), 1),
+                };
+                self.emit(subtract, token)?;
+                self.emit(Instruction::SkipNeImm(0xF, flag_when_true), token)
+            },
+            (_, None) => Err(self.error(token, "incomplete condition")),
+        }
+    }
+
+    fn if_statement (&mut self, token: &Token) -> Result<(), AssembleError> {
+        let condition = self.condition()?;
+        let keyword = self.next()?;
+
+        match keyword.text.as_str() {
+            "then" => self.skip_unless(condition, token),
+            "begin" => {
+                let negated = Condition { compare: condition.compare.negate(), ..condition };
+                self.skip_unless(negated, token)?;
+                let jump = self.offset;
+                self.emit(Instruction::Jump(0), token)?;
+                self.frames.push(Frame::If { jump: jump, has_else: false });
+                Ok(())
+            },
+            _ => Err(self.error(&keyword, format!("expected 'then' or 'begin', found '{}'", keyword.text))),
+        }
+    }
+
+    fn patch_jump (&mut self, offset: usize) {
+        let target = self.here();
+        self.rom[offset] = 0x10 | (target >> 8) as u8;
+        self.rom[offset + 1] = target as u8;
+    }
+
+    fn define_macro (&mut self) -> Result<(), AssembleError> {
+        let name = self.next()?;
+        if !is_identifier(&name.text) {
+            return Err(self.error(&name, format!("invalid macro name '{}'", name.text)));
+        }
+
+        let mut params = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token.text == "{" {
+                break;
+            }
+            params.push(token.text);
+        }
+
+        let body = self.braced_tokens()?;
+        self.macros.insert(name.text, Macro { params: params, body: body });
+        Ok(())
+    }
+
+    // Collects tokens up to the matching closing brace, the opening brace already consumed
+    fn braced_tokens (&mut self) -> Result<Vec<Token>, AssembleError> {
+        let mut body = Vec::new();
+        let mut depth = 1;
+        loop {
+            let token = self.next()?;
+            if token.text == "{" {
+                depth += 1;
+            } else if token.text == "}" {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(body);
+                }
+            }
+            body.push(token);
+        }
+    }
+
+    fn expand_macro (&mut self, name: &Token) -> Result<(), AssembleError> {
+        let param_count = self.macros[&name.text].params.len();
+        let mut args = Vec::new();
+        for _ in 0..param_count {
+            args.push(self.next()?);
+        }
+
+        let expanded: Vec<Token> = {
+            let definition = &self.macros[&name.text];
+            definition.body
+                .iter()
+                .map(|token| match definition.params.iter().position(|param| *param == token.text) {
+                    Some(index) => args[index].clone(),
+                    None => token.clone(),
+                })
+                .collect()
+        };
+
+        for token in expanded.into_iter().rev() {
+            self.tokens.push_front(token);
+        }
+        Ok(())
+    }
+
+    fn calc (&mut self) -> Result<i64, AssembleError> {
+        let open = self.expect("{")?;
+        let tokens = self.braced_tokens()?;
+        if tokens.is_empty() {
+            return Err(self.error(&open, "empty expression"));
+        }
+
+        let mut position = 0;
+        let value = self.calc_expression(&tokens, &mut position)?;
+        if position < tokens.len() {
+            return Err(self.error(&tokens[position], format!("unexpected '{}' in expression", tokens[position].text)));
+        }
+        Ok(value)
+    }
+
+    // Octo evaluates expressions right to left without operator precedence
+    fn calc_expression (&self, tokens: &[Token], position: &mut usize) -> Result<i64, AssembleError> {
+        let left = self.calc_term(tokens, position)?;
+        let operator = match tokens.get(*position) {
+            Some(token) if token.text != ")" => token.clone(),
+            _ => return Ok(left),
+        };
+        *position += 1;
+        let right = self.calc_expression(tokens, position)?;
+
+        let value = match operator.text.as_str() {
+            "+" => left.wrapping_add(right),
+            "-" => left.wrapping_sub(right),
+            "*" => left.wrapping_mul(right),
+            "/" | "%" if right == 0 => return Err(self.error(&operator, "division by zero")),
+            "/" => left / right,
+            "%" => left % right,
+            "&" => left & right,
+            "|" => left | right,
+            "^" => left ^ right,
+            "<<" => left.wrapping_shl(right as u32),
+            ">>" => left.wrapping_shr(right as u32),
+            "min" => left.min(right),
+            "max" => left.max(right),
+            _ => return Err(self.error(&operator, format!("unknown operator '{}'", operator.text))),
+        };
+        Ok(value)
+    }
+
+    fn calc_term (&self, tokens: &[Token], position: &mut usize) -> Result<i64, AssembleError> {
+        let token = match tokens.get(*position) {
+            Some(token) => token.clone(),
+            None => return Err(self.error(&tokens[tokens.len() - 1], "incomplete expression")),
+        };
+        *position += 1;
+
+        match token.text.as_str() {
+            "(" => {
+                let value = self.calc_expression(tokens, position)?;
+                match tokens.get(*position) {
+                    Some(close) if close.text == ")" => {
+                        *position += 1;
+                        Ok(value)
+                    },
+                    _ => Err(self.error(&token, "unbalanced parenthesis")),
+                }
+            },
+            "-" => self.calc_term(tokens, position).map(|value| -value),
+            "~" => self.calc_term(tokens, position).map(|value| !value),
+            "HERE" => Ok(self.here() as i64),
+            text => match self.lookup(text) {
+                Some(value) => Ok(value),
+                None => Err(self.error(&token, format!("undefined name '{}'", text))),
+            },
+        }
+    }
+}
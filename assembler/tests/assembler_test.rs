@@ -0,0 +1,151 @@
+extern crate chip8_assembler;
+extern crate chip8_emulator;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use chip8_assembler::*;
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::disasm::disassemble;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::variant::Variant;
+
+fn error_at(source: &str) -> (usize, usize, String) {
+    let error = assemble(source, Variant::Chip8).unwrap_err();
+    (error.line(), error.column(), error.message)
+}
+
+#[test]
+fn test_assemble_instructions() {
+    let rom = assemble("
+        CLS
+        LD V1, 0x20
+        ld v2, 10
+        DRW V0, V1, 5
+        LD [I], V3
+        ADD I, VA
+        SHR V4
+    ", Variant::Chip8).unwrap();
+
+    assert_eq!(rom, vec![0x00, 0xE0, 0x61, 0x20, 0x62, 0x0A, 0xD0, 0x15, 0xF3, 0x55, 0xFA, 0x1E, 0x84, 0x46]);
+}
+
+#[test]
+fn test_assemble_labels_and_constants() {
+    let rom = assemble("
+        SPEED EQU 3
+    start:
+        ADD V0, SPEED
+        CALL routine
+        JP start
+    routine: RET
+        LD I, sprite + 1
+    sprite:
+        DB 0b11110000, 0x90
+    ", Variant::Chip8).unwrap();
+
+    assert_eq!(rom, vec![0x70, 0x03, 0x22, 0x06, 0x12, 0x00, 0x00, 0xEE, 0xA2, 0x0B, 0xF0, 0x90]);
+}
+
+#[test]
+fn test_assemble_data_directives() {
+    let rom = assemble("DB \"HI\", -1\nDW 0x1234, end\nend:", Variant::Chip8).unwrap();
+
+    assert_eq!(rom, vec![b'H', b'I', 0xFF, 0x12, 0x34, 0x02, 0x07]);
+}
+
+#[test]
+fn test_assemble_long_load() {
+    let rom = assemble("LD I, LONG 0x1234\nPLANE 3", Variant::XoChip).unwrap();
+
+    assert_eq!(rom, vec![0xF0, 0x00, 0x12, 0x34, 0xF3, 0x01]);
+}
+
+#[test]
+fn test_assemble_include() {
+    let dir = env::temp_dir().join("chip8_assembler_include_test");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("sprites.inc"), "digit:\n    DB 0xF0, 0x90\n").unwrap();
+    fs::write(dir.join("main.asm"), "LD I, digit\nINCLUDE \"sprites.inc\"\n").unwrap();
+
+    let rom = Assembler::new(Variant::Chip8).assemble_file(&dir.join("main.asm")).unwrap();
+
+    assert_eq!(rom, vec![0xA2, 0x02, 0xF0, 0x90]);
+}
+
+#[test]
+fn test_errors_report_line_and_column() {
+    assert_eq!(error_at("CLS\n  FOO V1"), (2, 3, "unknown mnemonic 'FOO'".to_string()));
+    assert_eq!(error_at("JP nowhere"), (1, 4, "undefined symbol 'nowhere'".to_string()));
+    assert_eq!(error_at("LD V1, 0x100"), (1, 8, "value 256 is out of range (-128..255)".to_string()));
+    assert_eq!(error_at("LD DT, 5"), (1, 1, "invalid operands for LD".to_string()));
+    assert_eq!(error_at("a:\na:"), (2, 1, "'a' is already defined".to_string()));
+    assert_eq!(error_at("DB 1,, 2"), (1, 4, "missing value".to_string()));
+    assert_eq!(error_at("LD V1, $"), (1, 8, "unexpected character '$'".to_string()));
+}
+
+#[test]
+fn test_program_must_fit_variant_memory() {
+    let source = format!("DB {}", vec!["0"; 0xE01].join(", "));
+
+    assert_eq!(assemble(&source, Variant::Chip8).unwrap_err().message, "program does not fit in memory");
+    assert_eq!(assemble(&source, Variant::XoChip).unwrap().len(), 0xE01);
+    assert_eq!(assemble(&source[..source.len() - 3], Variant::Chip8).unwrap().len(), 0xE00);
+}
+
+#[test]
+fn test_rejects_instructions_the_variant_lacks() {
+    assert_eq!(error_at("CLS
+EXIT"), (2, 1, "EXIT is not supported by Chip8".to_string()));
+    assert_eq!(assemble("LD I, LONG 0x1234", Variant::SuperChip).unwrap_err().message,
+        "LD is not supported by SuperChip");
+    assert!(assemble("EXIT", Variant::SuperChip).is_ok());
+}
+
+#[test]
+fn test_round_trip_through_disassembler() {
+    let rom = assemble("
+    loop:
+        LD I, sprite
+        RND V0, 0x3F
+        SE V0, 0
+        DRW V0, V1, 4
+        SKNP V2
+        JP loop
+        CALL draw
+        EXIT
+    draw:
+        SCD 2
+        RET
+    sprite:
+        DB 0x60, 0x90, 0x90, 0x60
+    ", Variant::SuperChip).unwrap();
+
+    let source = disassemble(&rom).to_source();
+
+    assert_eq!(assemble(&source, Variant::SuperChip).unwrap(), rom);
+}
+
+#[test]
+fn test_output_runs_on_cpu() {
+    let rom = assemble("LD V0, 5\nADD V0, 7\nhalt: JP halt", Variant::Chip8).unwrap();
+    let mut cpu = Cpu::new(Keypad::new(), Variant::Chip8, Quirks::default());
+    cpu.load_program(&rom);
+
+    for _ in 0..3 {
+        cpu.execute_next_op_code().unwrap();
+    }
+
+    assert_eq!(cpu.registers.v[0], 12);
+    assert_eq!(cpu.program_counter, 0x204);
+}
+
+#[test]
+fn test_emulator_test_roms_match_sources() {
+    let roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../emulator/tests/roms");
+    for name in &["flags", "display", "quirks"] {
+        let rom = Assembler::new(Variant::Chip8).assemble_file(&roms.join(format!("{}.asm", name))).unwrap();
+        assert_eq!(rom, fs::read(roms.join(format!("{}.ch8", name))).unwrap(), "{}.ch8 is out of date", name);
+    }
+}
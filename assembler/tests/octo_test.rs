@@ -0,0 +1,159 @@
+extern crate chip8_assembler;
+extern crate chip8_emulator;
+
+use chip8_assembler::*;
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::variant::Variant;
+
+fn compile(source: &str) -> Vec<u8> {
+    compile_octo(source, Variant::XoChip).unwrap()
+}
+
+fn error_at(source: &str, variant: Variant) -> (usize, usize, String) {
+    let error = compile_octo(source, variant).unwrap_err();
+    (error.line(), error.column(), error.message)
+}
+
+fn run(rom: &[u8], steps: usize) -> Cpu {
+    let mut cpu = Cpu::new(Keypad::new(), Variant::XoChip, Quirks::default());
+    cpu.load_program(rom);
+    for _ in 0..steps {
+        cpu.execute_next_op_code().unwrap();
+    }
+    cpu
+}
+
+#[test]
+fn test_compile_statements() {
+    let rom = compile("
+        : main
+            clear
+            v1 := 0x20
+            v2 := v1
+            v2 += 3
+            v3 -= 1
+            v4 =- v5
+            v6 >>= v6
+            i := digit
+            sprite v1 v2 5
+            v0 := random 0xFF
+            v7 := key
+            delay := v7
+            return
+        : digit 0xF0 0x90
+    ");
+
+    assert_eq!(rom, vec![
+        0x00, 0xE0, 0x61, 0x20, 0x82, 0x10, 0x72, 0x03, 0x73, 0xFF, 0x84, 0x57, 0x86, 0x66,
+        0xA2, 0x1A, 0xD1, 0x25, 0xC0, 0xFF, 0xF7, 0x0A, 0xF7, 0x15, 0x00, 0xEE, 0xF0, 0x90,
+    ]);
+}
+
+#[test]
+fn test_jump_to_main_when_not_first() {
+    let rom = compile(": helper return : main helper");
+
+    assert_eq!(rom, vec![0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+}
+
+#[test]
+fn test_const_alias_and_calc() {
+    let rom = compile("
+        :const WIDTH 64
+        :alias px v3
+        :calc CENTER { WIDTH / 2 - 4 }
+        : main
+            px := CENTER
+            :byte { 1 + 2 * 3 }
+    ");
+
+    // Octo evaluates right to left, so 64 / (2 - 4) and 1 + (2 * 3)
+    assert_eq!(rom, vec![0x63, 0xE0, 0x07]);
+}
+
+#[test]
+fn test_macro_expansion() {
+    let rom = compile("
+        :macro swap A B { vf := A A := B B := vf }
+        : main
+            swap v1 v2
+    ");
+
+    assert_eq!(rom, vec![0x8F, 0x10, 0x81, 0x20, 0x82, 0xF0]);
+}
+
+#[test]
+fn test_if_then_and_begin_else_end() {
+    let rom = compile("
+        : main
+            if v0 == 5 then v1 := 1
+            if v0 key begin
+                v2 := 2
+            else
+                v2 := 3
+            end
+    ");
+
+    assert_eq!(rom, vec![
+        0x40, 0x05, 0x61, 0x01,
+        0xE0, 0x9E, 0x12, 0x0C, 0x62, 0x02, 0x12, 0x0E,
+        0x62, 0x03,
+    ]);
+}
+
+#[test]
+fn test_loop_again_and_while() {
+    let rom = compile("
+        : main
+            loop
+                v0 += 1
+                while v0 != 10
+            again
+    ");
+
+    assert_eq!(rom, vec![0x70, 0x01, 0x40, 0x0A, 0x12, 0x08, 0x12, 0x00]);
+}
+
+#[test]
+fn test_comparison_expands_through_vf() {
+    let rom = compile(": main if v1 < 8 then v2 := 1");
+
+    assert_eq!(rom, vec![0x6F, 0x08, 0x8F, 0x17, 0x4F, 0x00, 0x62, 0x01]);
+}
+
+#[test]
+fn test_xo_chip_statements() {
+    let rom = compile(": main i := long data plane 3 save v1 - v4 pitch := v0 : data 1");
+
+    assert_eq!(rom, vec![0xF0, 0x00, 0x02, 0x0A, 0xF3, 0x01, 0x51, 0x42, 0xF0, 0x3A, 0x01]);
+}
+
+#[test]
+fn test_compiled_loop_runs_on_cpu() {
+    let rom = compile("
+        : main
+            v1 := 0
+            loop
+                v1 += 2
+                while v1 != 6
+            again
+            v2 := 0xAA
+            : halt jump halt
+    ");
+
+    let cpu = run(&rom, 14);
+
+    assert_eq!(cpu.registers.v[1], 6);
+    assert_eq!(cpu.registers.v[2], 0xAA);
+}
+
+#[test]
+fn test_errors_report_line_and_column() {
+    assert_eq!(error_at(": main\n  jump nowhere", Variant::XoChip), (2, 8, "undefined label 'nowhere'".to_string()));
+    assert_eq!(error_at(": main v1 := 300", Variant::XoChip), (1, 14, "value 300 is out of range (-128..255)".to_string()));
+    assert_eq!(error_at(": main hires", Variant::Chip8), (1, 8, "'hires' is not supported by Chip8".to_string()));
+    assert_eq!(error_at(": main\nloop", Variant::XoChip), (2, 1, "'loop' without matching 'again'".to_string()));
+    assert_eq!(error_at("clear", Variant::XoChip), (1, 1, "undefined label 'main'".to_string()));
+}
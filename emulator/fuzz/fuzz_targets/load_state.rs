@@ -0,0 +1,27 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate chip8_emulator;
+
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::variant::Variant;
+
+// Any state that loads has to save back to the same bytes and run without panicking
+fuzz_target!(|data: &[u8]| {
+    let mut cpu = Cpu::new(Keypad::new(), Variant::Chip8, Quirks::default());
+    if cpu.load_state(data).is_err() {
+        return;
+    }
+    let state = cpu.save_state();
+    let mut other = Cpu::new(Keypad::new(), Variant::Chip8, Quirks::default());
+    other.load_state(&state).unwrap();
+    assert_eq!(other.save_state(), state);
+
+    for _ in 0..100 {
+        if cpu.execute_next_op_code().is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate chip8_emulator;
+
+use chip8_emulator::movie::Movie;
+
+// Any movie that parses has to serialize back to something that parses to the same movie
+fuzz_target!(|data: &[u8]| {
+    if let Ok(movie) = Movie::from_bytes(data) {
+        let bytes = movie.to_bytes();
+        assert_eq!(Movie::from_bytes(&bytes).unwrap(), movie);
+    }
+});
@@ -0,0 +1,43 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate chip8_emulator;
+
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::random::SeededRandom;
+use chip8_emulator::variant::Variant;
+
+const STEPS: usize = 10000;
+
+// The first bytes pick the variant, the quirks and the held keys, the rest is the ROM
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 4 {
+        return;
+    }
+    let variant = match data[0] % 3 {
+        0 => Variant::Chip8,
+        1 => Variant::SuperChip,
+        _ => Variant::XoChip,
+    };
+    let mut cpu = Cpu::new(Keypad::new(), variant, Quirks::from_bits(data[1]));
+    cpu.set_random_source(Box::new(SeededRandom::new(1)));
+    let keys = (data[2] as u16) << 8 | data[3] as u16;
+    for key in (0..16).filter(|key| keys & (1 << key) != 0) {
+        cpu.keypad.press_key(key);
+    }
+    cpu.load_program(&data[4..]);
+
+    for step in 0..STEPS {
+        if step % 8 == 0 {
+            cpu.tick_timers();
+        }
+        let pc = cpu.program_counter;
+        if let Err(error) = cpu.execute_next_op_code() {
+            assert_eq!(error.pc(), pc);
+            break;
+        }
+        assert!((cpu.program_counter as usize) < cpu.memory.len());
+    }
+});
@@ -0,0 +1,19 @@
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+
+pub fn crc32 (bytes: &[u8]) -> u32 {
+    crc32_update(0, bytes)
+}
+
+pub fn crc32_update (crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (CRC32_POLYNOMIAL & mask);
+        }
+    }
+
+    !crc
+}
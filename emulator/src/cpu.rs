@@ -1,13 +1,20 @@
+use error::CpuError;
 use keypad::Keypad;
-use ::nibbles::*;
+use quirks::Quirks;
+use variant::Variant;
+use instruction::Instruction;
 use ::sprites::*;
-use rand::rngs::{OsRng};
-use rand::RngCore;
+use random::{RandomSource, SeededRandom};
+use trace::{self, TraceBuffer, TraceEntry, TraceSink};
 
-pub const DISPLAY_WIDTH: usize = 64;
-pub const DISPLAY_HEIGHT: usize = 32;
+pub const LORES_DISPLAY_WIDTH: usize = 64;
+pub const LORES_DISPLAY_HEIGHT: usize = 32;
+pub const HIRES_DISPLAY_WIDTH: usize = 128;
+pub const HIRES_DISPLAY_HEIGHT: usize = 64;
 pub const PC_STEP: u16 = 2;
 const PROGRAM_START: usize = 0x200;
+const BIG_FONT_START: usize = 0x50;
+const DEFAULT_AUDIO_PITCH: u8 = 64;
 
 enum Action {
     Nothing,
@@ -17,6 +24,19 @@ enum Action {
     Jump(u16),
     EnterSubroutine(u16),
     ExitSubroutine,
+    Exit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioEvent {
+    Started,
+    Stopped,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read { addr: usize },
+    Write { addr: usize, value: u8 },
 }
 
 pub struct Registers {
@@ -27,23 +47,39 @@ pub struct Registers {
 }
 
 pub struct Cpu {
-    pub memory: [u8;4096],
-    pub video_memory: [[bool; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+    pub memory: Vec<u8>,
+    pub video_memory: Vec<Vec<bool>>,
+    pub video_memory_plane2: Vec<Vec<bool>>,
     pub registers: Registers,
     pub program_counter: u16,
     pub stack: [u16; 16],
     pub stack_pointer: usize,
+    pub rpl_flags: [u8; 16],
 
-    await_key: Option<u8>,
+    pub(crate) await_key: Option<u8>,
+    pub(crate) awaited_key: Option<u8>,
+    pub(crate) hires: bool,
+    pub(crate) exited: bool,
+    pub(crate) reported_sound_active: bool,
+    pub(crate) selected_planes: u8,
+    pub(crate) audio_pattern: Option<[u8; 16]>,
+    pub(crate) audio_pitch: u8,
 
     pub keypad: Keypad,
+    pub variant: Variant,
+    pub quirks: Quirks,
+
+    pub(crate) rng: Box<dyn RandomSource>,
+    pub(crate) memory_accesses: Option<Vec<MemoryAccess>>,
+    pub(crate) trace_sink: Option<TraceSink>,
 }
 
 impl Cpu {
-    pub fn new(keypad: Keypad) -> Cpu {
+    pub fn new(keypad: Keypad, variant: Variant, quirks: Quirks) -> Cpu {
         let mut cpu = Cpu {
-            memory: [0; 4096],
-            video_memory: [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+            memory: vec![0; variant.memory_size()],
+            video_memory: vec![vec![false; LORES_DISPLAY_WIDTH]; LORES_DISPLAY_HEIGHT],
+            video_memory_plane2: vec![vec![false; LORES_DISPLAY_WIDTH]; LORES_DISPLAY_HEIGHT],
             registers: Registers {
                 v: [0; 16],
                 i: 0,
@@ -53,21 +89,37 @@ impl Cpu {
             stack: [0; 16],
             stack_pointer: 0,
             program_counter: PROGRAM_START as u16,
+            rpl_flags: [0; 16],
             await_key: None,
+            awaited_key: None,
+            hires: false,
+            exited: false,
+            reported_sound_active: false,
+            selected_planes: 1,
+            audio_pattern: None,
+            audio_pitch: DEFAULT_AUDIO_PITCH,
             keypad: keypad,
+            variant: variant,
+            quirks: quirks,
+            rng: Box::new(SeededRandom::default()),
+            memory_accesses: None,
+            trace_sink: None,
         };
 
-        FONT_SPRITES.iter()
-            .flat_map(|x| x)
-            .enumerate()
-            .for_each(|(i, x)| cpu.memory[i] = *x);
+        cpu.load_fonts();
 
         return cpu;
     }
 
     pub fn reset (&mut self) {
-        self.memory = [0; 4096];
-        self.video_memory = [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+        self.memory = vec![0; self.variant.memory_size()];
+        self.hires = false;
+        self.exited = false;
+        self.reported_sound_active = false;
+        self.selected_planes = 1;
+        self.audio_pattern = None;
+        self.audio_pitch = DEFAULT_AUDIO_PITCH;
+        self.resize_display();
         self.registers = Registers {
             v: [0; 16],
             i: 0,
@@ -78,11 +130,97 @@ impl Cpu {
         self.stack_pointer = 0;
         self.program_counter = PROGRAM_START as u16;
         self.await_key = None;
+        self.awaited_key = None;
+        if let Some(ref mut accesses) = self.memory_accesses {
+            accesses.clear();
+        }
+
+        self.load_fonts();
+    }
 
+    fn load_fonts (&mut self) {
         FONT_SPRITES.iter()
             .flat_map(|x| x)
             .enumerate()
             .for_each(|(i, x)| self.memory[i] = *x);
+
+        BIG_FONT_SPRITES.iter()
+            .flat_map(|x| x)
+            .enumerate()
+            .for_each(|(i, x)| self.memory[BIG_FONT_START + i] = *x);
+    }
+
+    pub fn display_width (&self) -> usize {
+        if self.hires { HIRES_DISPLAY_WIDTH } else { LORES_DISPLAY_WIDTH }
+    }
+
+    pub fn display_height (&self) -> usize {
+        if self.hires { HIRES_DISPLAY_HEIGHT } else { LORES_DISPLAY_HEIGHT }
+    }
+
+    pub fn is_hires (&self) -> bool {
+        self.hires
+    }
+
+    pub fn set_random_source (&mut self, rng: Box<dyn RandomSource>) {
+        self.rng = rng;
+    }
+
+    pub fn has_exited (&self) -> bool {
+        self.exited
+    }
+
+    // The register an Fx0A is waiting to fill, if any
+    pub fn key_wait_register (&self) -> Option<u8> {
+        self.await_key
+    }
+
+    pub fn pixel (&self, x: usize, y: usize) -> u8 {
+        (self.video_memory[y][x] as u8) | ((self.video_memory_plane2[y][x] as u8) << 1)
+    }
+
+    pub fn selected_planes (&self) -> u8 {
+        self.selected_planes
+    }
+
+    pub fn audio_pattern (&self) -> Option<[u8; 16]> {
+        self.audio_pattern
+    }
+
+    pub fn audio_pitch (&self) -> u8 {
+        self.audio_pitch
+    }
+
+    pub fn audio_playback_rate (&self) -> f64 {
+        4000.0 * 2f64.powf((self.audio_pitch as f64 - 64.0) / 48.0)
+    }
+
+    // Records the data reads and writes of each instruction, excluding opcode fetches
+    pub fn set_memory_access_tracking (&mut self, enabled: bool) {
+        self.memory_accesses = if enabled { Some(Vec::new()) } else { None };
+    }
+
+    pub fn last_memory_accesses (&self) -> &[MemoryAccess] {
+        match self.memory_accesses {
+            Some(ref accesses) => accesses,
+            None => &[],
+        }
+    }
+
+    // Every executed instruction is passed to the sink until it is taken back
+    pub fn set_trace_sink (&mut self, sink: TraceSink) {
+        self.trace_sink = Some(sink);
+    }
+
+    pub fn take_trace_sink (&mut self) -> Option<TraceSink> {
+        self.trace_sink.take()
+    }
+
+    pub fn trace_buffer (&self) -> Option<&TraceBuffer> {
+        match self.trace_sink {
+            Some(TraceSink::Buffer(ref buffer)) => Some(buffer),
+            _ => None,
+        }
     }
 
     pub fn load_program (&mut self, bytes: &[u8]) {
@@ -98,105 +236,375 @@ impl Cpu {
         }
     }
 
-    pub fn execute_cycle(&mut self) -> [[bool; DISPLAY_WIDTH]; DISPLAY_HEIGHT] {
+    pub fn is_sound_active (&self) -> bool {
+        self.registers.sound_timer > 0
+    }
+
+    pub fn poll_audio_event (&mut self) -> Option<AudioEvent> {
+        let sound_active = self.is_sound_active();
+        if sound_active == self.reported_sound_active {
+            return None;
+        }
+
+        self.reported_sound_active = sound_active;
+        if sound_active { Some(AudioEvent::Started) } else { Some(AudioEvent::Stopped) }
+    }
+
+    pub fn tick_timers(&mut self) {
         if self.registers.delay_timer > 0 {
             self.registers.delay_timer -= 1
         }
 
+        if self.registers.sound_timer > 0 {
+            self.registers.sound_timer -= 1
+        }
+    }
+
+    pub fn execute_cycle(&mut self) -> Result<Vec<Vec<bool>>, CpuError> {
+        self.tick_timers();
+
         for _ in 0..8 {
-            self.execute_next_op_code();
+            self.execute_next_op_code()?;
+        }
+        self.keypad.clear_edges();
+
+        return Ok(self.video_memory.clone());
+    }
+
+    pub fn execute_next_op_code(&mut self) -> Result<(), CpuError> {
+        // Nothing runs while exited or waiting for a key, so there is nothing to trace
+        let is_idle = self.exited
+            || (self.await_key.is_some() && self.poll_key_wait().is_none());
+        if self.trace_sink.is_none() || is_idle {
+            return self.run_next_op_code();
         }
 
-        return self.video_memory;
+        let tracks_memory = self.memory_accesses.is_some();
+        if !tracks_memory {
+            self.memory_accesses = Some(Vec::new());
+        }
+        let pc = self.program_counter;
+        let opcode = self.get_opcode_at(pc)?;
+        let before = trace::snapshot_registers(self);
+
+        let result = self.run_next_op_code();
+
+        let entry = TraceEntry {
+            pc: pc,
+            opcode: opcode,
+            instruction: self.decode_op_code(opcode).ok(),
+            register_changes: trace::register_changes(&before, &trace::snapshot_registers(self)),
+            memory_writes: self.last_memory_accesses()
+                .iter()
+                .filter_map(|access| match *access {
+                    MemoryAccess::Write { addr, value } => Some((addr, value)),
+                    MemoryAccess::Read { .. } => None,
+                })
+                .collect(),
+        };
+        if !tracks_memory {
+            self.memory_accesses = None;
+        }
+        // Failing instructions are traced too, they are usually the interesting part
+        if let Some(ref mut sink) = self.trace_sink {
+            sink.record(entry);
+        }
+        result
     }
 
-    pub fn execute_next_op_code(&mut self) {
+    fn run_next_op_code(&mut self) -> Result<(), CpuError> {
+        if self.exited {
+            return Ok(());
+        }
+
+        if let Some(ref mut accesses) = self.memory_accesses {
+            accesses.clear();
+        }
+
         let next_action = match self.await_key {
             Some(address) => {
-                match self.keypad.get_first_pressed_key() {
+                match self.poll_key_wait() {
                     Some(key) => {
                         self.registers.v[address as usize] = key;
                         self.await_key = None;
+                        self.awaited_key = None;
 
-                        let opcode = self.get_current_opcode();
-                        self.run_opcode(opcode)
+                        let opcode = self.get_opcode_at(self.program_counter)?;
+                        self.run_opcode(opcode)?
                     },
                     None => Action::Nothing,
                 }
             },
             None => {
-                let opcode = self.get_current_opcode();
-                self.run_opcode(opcode)
+                let opcode = self.get_opcode_at(self.program_counter)?;
+                self.run_opcode(opcode)?
             },
         };
 
-        self.handle_action(next_action);
+        self.handle_action(next_action)
     }
 
-    fn get_current_opcode (&mut self) -> u16 {
-        let part1 = self.memory[self.program_counter as usize];
-        let part2 = self.memory[(self.program_counter + 1) as usize];
+    // The key that ends the current Fx0A wait, if any. Like the VIP, a key has to be pressed
+    // while waiting and is only taken once released, so a key still held from an earlier
+    // prompt doesn't answer the next one
+    fn poll_key_wait (&mut self) -> Option<u8> {
+        if self.quirks.key_wait_on_press {
+            return self.keypad.get_first_pressed_key();
+        }
+
+        if self.awaited_key.is_none() {
+            self.awaited_key = self.keypad.get_first_just_pressed_key();
+        }
+        match self.awaited_key {
+            Some(key) if !self.keypad.key_is_pressed(key) => Some(key),
+            _ => None,
+        }
+    }
+
+    fn peek_memory (&self, address: usize) -> Result<u8, CpuError> {
+        match self.memory.get(address) {
+            Some(value) => Ok(*value),
+            None => Err(CpuError::MemoryOutOfBounds { pc: self.program_counter, addr: address }),
+        }
+    }
+
+    fn read_memory (&mut self, address: usize) -> Result<u8, CpuError> {
+        let value = self.peek_memory(address)?;
+        if let Some(ref mut accesses) = self.memory_accesses {
+            // Sprites read each byte once per pixel column, log it once
+            let access = MemoryAccess::Read { addr: address };
+            if accesses.last() != Some(&access) {
+                accesses.push(access);
+            }
+        }
+        Ok(value)
+    }
+
+    fn write_memory (&mut self, address: usize, value: u8) -> Result<(), CpuError> {
+        let pc = self.program_counter;
+        match self.memory.get_mut(address) {
+            Some(byte) => {
+                *byte = value;
+            },
+            None => return Err(CpuError::MemoryOutOfBounds { pc: pc, addr: address }),
+        }
+        if let Some(ref mut accesses) = self.memory_accesses {
+            accesses.push(MemoryAccess::Write { addr: address, value: value });
+        }
+        Ok(())
+    }
+
+    fn get_opcode_at (&self, address: u16) -> Result<u16, CpuError> {
+        let part1 = self.peek_memory(address as usize)?;
+        let part2 = self.peek_memory(address as usize + 1)?;
 
         let opcode: u16 = ((part1 as u16) << 8) | (part2 as u16);
 
-        return opcode;
+        return Ok(opcode);
     }
 
-    fn handle_action (&mut self, action: Action) {
+    fn handle_action (&mut self, action: Action) -> Result<(), CpuError> {
         match action {
             Action::Nothing => {},
             Action::ContinueAfterKeypress(address) => {
                 self.await_key = Some(address);
-                self.program_counter += PC_STEP;
+                self.program_counter = self.program_counter.wrapping_add(PC_STEP);
             },
             Action::Continue => {
-                self.program_counter += PC_STEP;
+                self.program_counter = self.program_counter.wrapping_add(PC_STEP);
             },
             Action::SkipNext => {
-                self.program_counter += PC_STEP * 2;
+                let next_address = self.program_counter.wrapping_add(PC_STEP);
+                let skips_long_load = self.variant.supports_xo_chip() &&
+                    self.get_opcode_at(next_address) == Ok(0xF000);
+                self.program_counter = self.program_counter.wrapping_add(PC_STEP * 2);
+
+                if skips_long_load {
+                    self.program_counter = self.program_counter.wrapping_add(PC_STEP);
+                }
             },
             Action::Jump(address) => {
                 self.program_counter = address;
             },
             Action::EnterSubroutine(address) => {
-                self.stack[self.stack_pointer] = self.program_counter + PC_STEP;
+                if self.stack_pointer >= self.stack.len() {
+                    return Err(CpuError::StackOverflow { pc: self.program_counter });
+                }
+                self.stack[self.stack_pointer] = self.program_counter.wrapping_add(PC_STEP);
                 self.stack_pointer += 1;
                 self.program_counter = address;
             },
             Action::ExitSubroutine => {
+                if self.stack_pointer == 0 {
+                    return Err(CpuError::StackUnderflow { pc: self.program_counter });
+                }
                 self.stack_pointer -= 1;
                 self.program_counter = self.stack[self.stack_pointer];
             },
+            Action::Exit => {
+                self.exited = true;
+            },
         }
+
+        // Addresses wrap at the end of memory, so the program counter never points outside it
+        self.program_counter = (self.program_counter as usize % self.memory.len()) as u16;
+        Ok(())
+    }
+
+    fn resize_display (&mut self) {
+        self.video_memory = vec![vec![false; self.display_width()]; self.display_height()];
+        self.video_memory_plane2 = vec![vec![false; self.display_width()]; self.display_height()];
+    }
+
+    fn plane_mut (&mut self, plane: usize) -> &mut Vec<Vec<bool>> {
+        if plane == 0 { &mut self.video_memory } else { &mut self.video_memory_plane2 }
+    }
+
+    fn selected_plane_indices (&self) -> Vec<usize> {
+        (0..2).filter(|plane| self.selected_planes & (1 << plane) != 0).collect()
+    }
+
+    fn clear_display (&mut self) {
+        let width = self.display_width();
+        let height = self.display_height();
+
+        for plane in self.selected_plane_indices() {
+            *self.plane_mut(plane) = vec![vec![false; width]; height];
+        }
+    }
+
+    fn set_hires (&mut self, hires: bool) {
+        self.hires = hires;
+        self.resize_display();
     }
 
-    fn run_opcode (&mut self, opcode: u16) -> Action {
-        let nibbles: (u8, u8, u8, u8) = (
-            get_nibble_1(opcode),
-            get_nibble_2(opcode),
-            get_nibble_3(opcode),
-            get_nibble_4(opcode)
-        );
+    fn scroll_down (&mut self, rows: usize) {
+        let width = self.display_width();
+        let height = self.display_height();
 
-        match nibbles {
-            (0x0, 0x0, 0xE, 0x0) => {
-                self.video_memory = [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+        for plane in self.selected_plane_indices() {
+            let video_memory = self.plane_mut(plane);
+            for y in (0..height).rev() {
+                video_memory[y] = if y >= rows {
+                    video_memory[y - rows].clone()
+                } else {
+                    vec![false; width]
+                };
+            }
+        }
+    }
+
+    fn scroll_right (&mut self, columns: usize) {
+        for plane in self.selected_plane_indices() {
+            for row in self.plane_mut(plane).iter_mut() {
+                row.rotate_right(columns);
+                row.iter_mut().take(columns).for_each(|pixel| *pixel = false);
+            }
+        }
+    }
+
+    fn scroll_left (&mut self, columns: usize) {
+        for plane in self.selected_plane_indices() {
+            for row in self.plane_mut(plane).iter_mut() {
+                let width = row.len();
+                row.rotate_left(columns);
+                row.iter_mut().skip(width - columns).for_each(|pixel| *pixel = false);
+            }
+        }
+    }
+
+    fn draw_sprite (&mut self, x: u8, y: u8, sprite_width: usize, sprite_height: usize) -> Result<Action, CpuError> {
+        let display_width = self.display_width();
+        let display_height = self.display_height();
+        let bytes_per_row = sprite_width / 8;
+        let sprite_size = bytes_per_row * sprite_height;
+
+        let x_origin = self.registers.v[x as usize] as usize % display_width;
+        let y_origin = self.registers.v[y as usize] as usize % display_height;
+
+        self.registers.v[0xF] = 0;
+
+        for (plane_index, plane) in self.selected_plane_indices().into_iter().enumerate() {
+            let sprite_start = self.registers.i as usize + plane_index * sprite_size;
+
+            for row in 0..sprite_height {
+                let y_draw_position = y_origin + row;
+                if self.quirks.clip_sprites && y_draw_position >= display_height {
+                    break;
+                }
+                for column in 0..sprite_width {
+                    let x_draw_position = x_origin + column;
+                    if self.quirks.clip_sprites && x_draw_position >= display_width {
+                        break;
+                    }
+                    let sprite_byte = self.read_memory(sprite_start + row * bytes_per_row + column / 8)?;
+                    let sprite_pixel = ((sprite_byte >> (7 - column % 8)) & 1) != 0;
+                    let pixel_erased = {
+                        let pixel = &mut self.plane_mut(plane)[y_draw_position % display_height][x_draw_position % display_width];
+                        let erased = sprite_pixel & *pixel;
+                        *pixel ^= sprite_pixel;
+                        erased
+                    };
+                    self.registers.v[0xF] |= pixel_erased as u8;
+                }
+            }
+        }
+
+        Ok(Action::Continue)
+    }
+
+    pub fn decode_op_code (&self, opcode: u16) -> Result<Instruction, CpuError> {
+        match Instruction::decode(opcode) {
+            Some(instruction) if instruction.is_supported_by(self.variant) => Ok(instruction),
+            _ => Err(CpuError::UnknownOpcode { pc: self.program_counter, opcode: opcode }),
+        }
+    }
+
+    fn run_opcode (&mut self, opcode: u16) -> Result<Action, CpuError> {
+        let instruction = self.decode_op_code(opcode)?;
+        self.execute_instruction(instruction)
+    }
+
+    fn execute_instruction (&mut self, instruction: Instruction) -> Result<Action, CpuError> {
+        let action = match instruction {
+            Instruction::ScrollDown(n) => {
+                self.scroll_down(n as usize);
+                Action::Continue
+            },
+            Instruction::ClearScreen => {
+                self.clear_display();
                 Action::Continue
             },
-            (0x0, 0x0, 0xE, 0xE) => {
+            Instruction::Return => {
                 Action::ExitSubroutine
             },
-            (0x1, n1, n2, n3) => {
-                let address = concat_nibbles_3(n1, n2, n3);
+            Instruction::ScrollRight => {
+                self.scroll_right(4);
+                Action::Continue
+            },
+            Instruction::ScrollLeft => {
+                self.scroll_left(4);
+                Action::Continue
+            },
+            Instruction::Exit => {
+                Action::Exit
+            },
+            Instruction::LowRes => {
+                self.set_hires(false);
+                Action::Continue
+            },
+            Instruction::HighRes => {
+                self.set_hires(true);
+                Action::Continue
+            },
+            Instruction::Jump(address) => {
                 Action::Jump(address)
             },
-            (0x2, n1, n2, n3) => {
-                let address = concat_nibbles_3(n1, n2, n3);
+            Instruction::Call(address) => {
                 Action::EnterSubroutine(address)
             },
-            (0x3, x, k1, k2) => {
+            Instruction::SkipEqImm(x, value) => {
                 let v_address = x as usize;
-                let value = concat_nibbles_2(k1, k2);
 
                 if self.registers.v[v_address] == value {
                     Action::SkipNext
@@ -204,9 +612,8 @@ impl Cpu {
                     Action::Continue
                 }
             },
-            (0x4, x, k1, k2) => {
+            Instruction::SkipNeImm(x, value) => {
                 let v_address = x as usize;
-                let value = concat_nibbles_2(k1, k2);
 
                 if self.registers.v[v_address] != value {
                     Action::SkipNext
@@ -214,7 +621,7 @@ impl Cpu {
                     Action::Continue
                 }
             },
-            (0x5, x, y, 0x0) => {
+            Instruction::SkipEqReg(x, y) => {
                 let v_address1 = x as usize;
                 let v_address2 = y as usize;
                 let v_value1 = self.registers.v[v_address1];
@@ -226,57 +633,77 @@ impl Cpu {
                     Action::Continue
                 }
             },
-            (0x6, x, k1, k2) => {
+            Instruction::SaveRange(x, y) => {
+                for (offset, v_address) in register_range(x, y).into_iter().enumerate() {
+                    let value = self.registers.v[v_address];
+                    self.write_memory(self.registers.i as usize + offset, value)?;
+                }
+                Action::Continue
+            },
+            Instruction::LoadRange(x, y) => {
+                for (offset, v_address) in register_range(x, y).into_iter().enumerate() {
+                    self.registers.v[v_address] = self.read_memory(self.registers.i as usize + offset)?;
+                }
+                Action::Continue
+            },
+            Instruction::LoadImm(x, value) => {
                 let v_address = x as usize;
-                let value = concat_nibbles_2(k1, k2);
 
                 self.registers.v[v_address] = value;
                 Action::Continue
             },
-            (0x7, x, k1, k2) => {
-                let value = concat_nibbles_2(k1, k2) as u16;
+            Instruction::AddImm(x, value) => {
                 let v_address = x as usize;
                 let v_value = self.registers.v[v_address] as u16;
-                let sum: u16 = v_value + value;
+                let sum: u16 = v_value + value as u16;
 
                 self.registers.v[v_address] = sum as u8;
                 Action::Continue
             },
-            (0x8, x, y, 0x0) => {
+            Instruction::LoadReg(x, y) => {
                 let v_address1 = x as usize;
                 let v_address2 = y as usize;
 
                 self.registers.v[v_address1] = self.registers.v[v_address2];
                 Action::Continue
             },
-            (0x8, x, y, 0x1) => {
+            Instruction::Or(x, y) => {
                 let v_address1 = x as usize;
                 let v_address2 = y as usize;
                 let v_value1 = self.registers.v[v_address1];
                 let v_value2 = self.registers.v[v_address2];
 
                 self.registers.v[v_address1] = v_value1 | v_value2;
+                if self.quirks.logic_resets_vf {
+                    self.registers.v[0xF] = 0;
+                }
                 Action::Continue
             },
-            (0x8, x, y, 0x2) => {
+            Instruction::And(x, y) => {
                 let v_address1 = x as usize;
                 let v_address2 = y as usize;
                 let v_value1 = self.registers.v[v_address1];
                 let v_value2 = self.registers.v[v_address2];
 
                 self.registers.v[v_address1] = v_value1 & v_value2;
+                if self.quirks.logic_resets_vf {
+                    self.registers.v[0xF] = 0;
+                }
                 Action::Continue
             },
-            (0x8, x, y, 0x3) => {
+            Instruction::Xor(x, y) => {
                 let v_address1 = x as usize;
                 let v_address2 = y as usize;
                 let v_value1 = self.registers.v[v_address1];
                 let v_value2 = self.registers.v[v_address2];
 
                 self.registers.v[v_address1] = v_value1 ^ v_value2;
+                if self.quirks.logic_resets_vf {
+                    self.registers.v[0xF] = 0;
+                }
                 Action::Continue
             },
-            (0x8, x, y, 0x4) => {
+            Instruction::Add(x, y) => {
                 let v_address1 = x as usize;
                 let v_address2 = y as usize;
                 let v_value1 = self.registers.v[v_address1] as u16;
@@ -287,51 +714,48 @@ impl Cpu {
                 self.registers.v[0xF] = (result > 0xFF) as u8;
                 Action::Continue
             },
-            (0x8, x, y, 0x5) => {
+            Instruction::Sub(x, y) => {
                 let result_address = x as usize;
                 let v_address1 = x as usize;
                 let v_address2 = y as usize;
                 let v_value1 = self.registers.v[v_address1];
                 let v_value2 = self.registers.v[v_address2];
 
-                self.registers.v[0xF] = (v_value1 > v_value2) as u8;
-
-                let result = v_value1.checked_sub(v_value2);
-                self.registers.v[result_address] = result.unwrap_or(0);
+                // VF is written last so it wins when it is also the destination
+                self.registers.v[result_address] = v_value1.wrapping_sub(v_value2);
+                self.registers.v[0xF] = (v_value1 >= v_value2) as u8;
                 Action::Continue
             },
-            (0x8, x, _, 0x6) => {
+            Instruction::ShiftRight(x, y) => {
                 let v_address = x as usize;
-                let v_value = self.registers.v[v_address];
+                let source_address = if self.quirks.shift_uses_vy { y as usize } else { v_address };
+                let v_value = self.registers.v[source_address];
 
+                self.registers.v[v_address] = v_value >> 1;
                 self.registers.v[0xF] = v_value & 0x1;
-
-                self.registers.v[v_address] = self.registers.v[v_address] >> 1;
                 Action::Continue
             },
-            (0x8, x, y, 0x7) => {
+            Instruction::SubN(x, y) => {
                 let result_address = x as usize;
                 let v_address1 = x as usize;
                 let v_address2 = y as usize;
                 let v_value1 = self.registers.v[v_address1];
                 let v_value2 = self.registers.v[v_address2];
 
-                self.registers.v[0xF] = (v_value1 < v_value2) as u8;
-
-                let result = v_value2.checked_sub(v_value1);
-                self.registers.v[result_address] = result.unwrap_or(0);
+                self.registers.v[result_address] = v_value2.wrapping_sub(v_value1);
+                self.registers.v[0xF] = (v_value2 >= v_value1) as u8;
                 Action::Continue
             },
-            (0x8, x, _, 0xE) => {
+            Instruction::ShiftLeft(x, y) => {
                 let v_address1 = x as usize;
-                let v_value1 = self.registers.v[v_address1];
-
-                self.registers.v[0xF] = v_value1 >> 7;
+                let source_address = if self.quirks.shift_uses_vy { y as usize } else { v_address1 };
+                let v_value1 = self.registers.v[source_address];
 
                 self.registers.v[v_address1] = v_value1 << 1;
+                self.registers.v[0xF] = v_value1 >> 7;
                 Action::Continue
             },
-            (0x9, x, y, 0x0) => {
+            Instruction::SkipNeReg(x, y) => {
                 let v_address1 = x as usize;
                 let v_address2 = y as usize;
                 let v_value1 = self.registers.v[v_address1];
@@ -343,129 +767,170 @@ impl Cpu {
                     Action::Continue
                 }
             },
-            (0xA, n1, n2, n3) => {
-                let value = concat_nibbles_3(n1, n2, n3);
+            Instruction::LoadI(value) => {
                 self.registers.i = value;
                 Action::Continue
             },
-            (0xB, n1, n2, n3) => {
-                let address = concat_nibbles_3(n1, n2, n3);
-                let v_value = self.registers.v[0];
+            Instruction::JumpOffset(address) => {
+                let v_address = if self.quirks.jump_uses_vx { (address >> 8) as usize } else { 0 };
+                let v_value = self.registers.v[v_address];
                 Action::Jump(address + (v_value as u16))
             },
-            (0xC, x, k1, k2) => {
+            Instruction::Random(x, value) => {
                 let v_address1 = x as usize;
-                let value = concat_nibbles_2(k1, k2);
-                let mut rng = match OsRng::new() {
-                    Ok(g) => g,
-                    Err(e) => panic!("Error opening OS RNG: {}", e)
-                };
-                let random_number = rng.next_u32() as u8;
+                let random_number = self.rng.next_byte();
                 self.registers.v[v_address1] = random_number & value;
                 Action::Continue
             },
-            (0xD, x, y, n) => {
-                let v_address1 = x as usize;
-                let v_address2 = y as usize;
-                let sprite_len = n;
-
-                self.registers.v[0xF] = 0;
-
-                for row in 0..sprite_len {
-                    let sprite_row = self.memory[(self.registers.i + (row as u16)) as usize];
-                    let y_draw_position = (self.registers.v[v_address2] + row) % (DISPLAY_HEIGHT as u8);
-                    for column in 0..8 {
-                        let x_draw_position = (self.registers.v[v_address1] + column) % (DISPLAY_WIDTH as u8);
-                        let pixel = &mut self.video_memory[y_draw_position as usize][x_draw_position as usize];
-                        let sprite_pixel = ((sprite_row >> 7 - column) & 1) != 0;
-                        let pixel_erased = sprite_pixel & *pixel;
-                        *pixel ^= sprite_pixel;
-                        self.registers.v[0xF] |= pixel_erased as u8;
-                    }
-                }
-
-                Action::Continue
+            Instruction::Draw(x, y, 0) if self.variant.supports_super_chip() => {
+                self.draw_sprite(x, y, 16, 16)?
+            },
+            Instruction::Draw(x, y, n) => {
+                self.draw_sprite(x, y, 8, n as usize)?
             },
-            (0xE, x, 9, 0xE) => {
+            Instruction::SkipKey(x) => {
                 let v_address = x as usize;
-                let key = self.registers.v[v_address];
+                // Only the low nibble is wired to the keypad
+                let key = self.registers.v[v_address] & 0xF;
                 if self.keypad.key_is_pressed(key) {
                     Action::SkipNext
                 } else {
                     Action::Continue
                 }
             },
-            (0xE, x, 0xA, 0x1) => {
+            Instruction::SkipNotKey(x) => {
                 let v_address = x as usize;
-                let key = self.registers.v[v_address];
+                let key = self.registers.v[v_address] & 0xF;
                 if !self.keypad.key_is_pressed(key) {
                     Action::SkipNext
                 } else {
                     Action::Continue
                 }
             },
-            (0xF, x, 0x0, 0x7) => {
+            Instruction::LongLoadI => {
+                self.registers.i = self.get_opcode_at(self.program_counter.wrapping_add(PC_STEP))?;
+                Action::Jump(self.program_counter.wrapping_add(PC_STEP * 2))
+            },
+            Instruction::Plane(n) => {
+                self.selected_planes = n & 0x3;
+                Action::Continue
+            },
+            Instruction::Audio => {
+                let mut pattern = [0; 16];
+                let start = self.registers.i as usize;
+                for (offset, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.read_memory(start + offset)?;
+                }
+                self.audio_pattern = Some(pattern);
+                Action::Continue
+            },
+            Instruction::LoadDelay(x) => {
                 let v_address = x as usize;
                 self.registers.v[v_address] = self.registers.delay_timer;
                 Action::Continue
             },
-            (0xF, x, 0x0, 0xA) => {
+            Instruction::WaitKey(x) => {
                  Action::ContinueAfterKeypress(x)
             },
-            (0xF, x, 0x1, 0x5) => {
+            Instruction::SetDelay(x) => {
                 let v_address = x as usize;
                 let v_value = self.registers.v[v_address];
                 self.registers.delay_timer = v_value;
                 Action::Continue
             },
-            (0xF, x, 0x1, 0x8) => {
+            Instruction::SetSound(x) => {
                 let v_address = x as usize;
                 let v_value = self.registers.v[v_address];
                 self.registers.sound_timer = v_value;
 
                 Action::Continue
             },
-            (0xF, x, 0x1, 0xE) => {
+            Instruction::AddI(x) => {
                 let v_address = x as usize;
                 let v_value = self.registers.v[v_address];
-                self.registers.i += v_value as u16;
+                self.registers.i = self.registers.i.wrapping_add(v_value as u16);
 
                 Action::Continue
             },
-            (0xF, x, 0x2, 0x9) => {
+            Instruction::LoadFont(x) => {
                 let v_address = x as usize;
-                let v_value = self.registers.v[v_address];
+                let v_value = self.registers.v[v_address] & 0xF;
+
+                self.registers.i = (v_value as u16) * 5;
+                Action::Continue
+            },
+            Instruction::LoadBigFont(x) => {
+                let v_address = x as usize;
+                let v_value = self.registers.v[v_address] & 0xF;
 
-                self.registers.i = (v_value * 5) as u16;
+                self.registers.i = (BIG_FONT_START + (v_value as usize) * 10) as u16;
                 Action::Continue
             },
-            (0xF, x, 0x3, 0x3) => {
+            Instruction::Pitch(x) => {
+                let v_address = x as usize;
+                self.audio_pitch = self.registers.v[v_address];
+                Action::Continue
+            },
+            Instruction::Bcd(x) => {
                 let v_address = x as usize;
                 let v_value = self.registers.v[v_address];
 
-                self.memory[self.registers.i as usize] = v_value / 100;
-                self.memory[(self.registers.i + 1) as usize] = (v_value / 10) % 10;
-                self.memory[(self.registers.i + 2) as usize] = (v_value % 100) % 10;
+                let address = self.registers.i as usize;
+
+                self.write_memory(address, v_value / 100)?;
+                self.write_memory(address + 1, (v_value / 10) % 10)?;
+                self.write_memory(address + 2, (v_value % 100) % 10)?;
                 Action::Continue
             },
-            (0xF, x, 0x5, 0x5) => {
+            Instruction::Store(x) => {
                 let v_address = x as usize;
 
                 for i in 0..(v_address + 1)  {
-                    self.memory[(self.registers.i + (i as u16)) as usize] = self.registers.v[i as usize];
+                    let value = self.registers.v[i as usize];
+                    self.write_memory(self.registers.i as usize + i, value)?;
+                }
+
+                if self.quirks.load_store_increments_i {
+                    self.registers.i = self.registers.i.wrapping_add((v_address + 1) as u16);
                 }
 
                 Action::Continue
             },
-            (0xF, x, 0x6, 0x5) => {
+            Instruction::Load(x) => {
                 let v_address = x as usize;
 
                 for i in 0..(v_address + 1)  {
-                    self.registers.v[i as usize] = self.memory[(self.registers.i + (i as u16)) as usize];
+                    self.registers.v[i as usize] = self.read_memory(self.registers.i as usize + i)?;
+                }
+
+                if self.quirks.load_store_increments_i {
+                    self.registers.i = self.registers.i.wrapping_add((v_address + 1) as u16);
                 }
+
                 Action::Continue
             },
-            _ => Action::Nothing
-        }
+            Instruction::StoreFlags(x) => {
+                let v_address = x as usize;
+
+                self.rpl_flags[..(v_address + 1)].copy_from_slice(&self.registers.v[..(v_address + 1)]);
+                Action::Continue
+            },
+            Instruction::LoadFlags(x) => {
+                let v_address = x as usize;
+
+                self.registers.v[..(v_address + 1)].copy_from_slice(&self.rpl_flags[..(v_address + 1)]);
+                Action::Continue
+            },
+        };
+
+        Ok(action)
+    }
+}
+
+fn register_range (x: u8, y: u8) -> Vec<usize> {
+    if x <= y {
+        (x..(y + 1)).map(|v| v as usize).collect()
+    } else {
+        (y..(x + 1)).rev().map(|v| v as usize).collect()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,407 @@
+use std::fmt;
+use cpu::{Cpu, MemoryAccess};
+use error::CpuError;
+use instruction::Instruction;
+use machine::{Event, Machine};
+
+pub const DEFAULT_INSTRUCTION_LIMIT: u64 = 10_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    DelayTimer,
+    SoundTimer,
+}
+
+impl Register {
+    // Accepts V0-VF, I, DT and ST
+    pub fn parse (text: &str) -> Option<Register> {
+        let upper = text.trim().to_uppercase();
+        match upper.as_str() {
+            "I" => Some(Register::I),
+            "DT" => Some(Register::DelayTimer),
+            "ST" => Some(Register::SoundTimer),
+            _ if upper.len() == 2 && upper.starts_with('V') =>
+                u8::from_str_radix(&upper[1..], 16).ok().map(Register::V),
+            _ => None,
+        }
+    }
+
+    pub fn read (&self, cpu: &Cpu) -> u16 {
+        match *self {
+            Register::V(x) => cpu.registers.v[(x & 0xF) as usize] as u16,
+            Register::I => cpu.registers.i,
+            Register::DelayTimer => cpu.registers.delay_timer as u16,
+            Register::SoundTimer => cpu.registers.sound_timer as u16,
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::DelayTimer => write!(f, "DT"),
+            Register::SoundTimer => write!(f, "ST"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub comparison: Comparison,
+    pub value: u16,
+}
+
+impl Condition {
+    // Parses conditions such as `V3 == 0x10` or `I >= 0x300`
+    pub fn parse (text: &str) -> Option<Condition> {
+        let operators = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+
+        for &(operator, comparison) in operators.iter() {
+            if let Some(index) = text.find(operator) {
+                let register = Register::parse(&text[..index])?;
+                let value = parse_value(&text[index + operator.len()..])?;
+                return Some(Condition { register: register, comparison: comparison, value: value });
+            }
+        }
+        None
+    }
+
+    pub fn matches (&self, cpu: &Cpu) -> bool {
+        let current = self.register.read(cpu);
+        match self.comparison {
+            Comparison::Equal => current == self.value,
+            Comparison::NotEqual => current != self.value,
+            Comparison::Less => current < self.value,
+            Comparison::LessOrEqual => current <= self.value,
+            Comparison::Greater => current > self.value,
+            Comparison::GreaterOrEqual => current >= self.value,
+        }
+    }
+}
+
+fn parse_value (text: &str) -> Option<u16> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse::<u16>().ok(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    pub condition: Option<Condition>,
+}
+
+// Memory ranges are inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watchpoint {
+    MemoryRead { start: usize, end: usize },
+    MemoryWrite { start: usize, end: usize },
+    Register(Register),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Step,
+    Breakpoint { pc: u16 },
+    MemoryRead { pc: u16, addr: usize },
+    MemoryWrite { pc: u16, addr: usize, value: u8 },
+    RegisterChanged { pc: u16, register: Register, old: u16, new: u16 },
+    FrameComplete,
+    FrameReached { frame: u64 },
+    Exited,
+    InstructionLimit,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StopReason::Step => write!(f, "step"),
+            StopReason::Breakpoint { pc } =>
+                write!(f, "breakpoint at {:04X}", pc),
+            StopReason::MemoryRead { pc, addr } =>
+                write!(f, "read of {:04X} at {:04X}", addr, pc),
+            StopReason::MemoryWrite { pc, addr, value } =>
+                write!(f, "write of {:02X} to {:04X} at {:04X}", value, addr, pc),
+            StopReason::RegisterChanged { pc, register, old, new } =>
+                write!(f, "{} changed from {:X} to {:X} at {:04X}", register, old, new, pc),
+            StopReason::FrameComplete => write!(f, "frame complete"),
+            StopReason::FrameReached { frame } => write!(f, "reached frame {}", frame),
+            StopReason::Exited => write!(f, "program exited"),
+            StopReason::InstructionLimit => write!(f, "instruction limit reached"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Until {
+    Step,
+    Return { address: u16, depth: usize },
+    StackBelow(usize),
+    FrameComplete,
+    Frame(u64),
+}
+
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    instruction_limit: u64,
+    // Where the last run paused, a breakpoint there doesn't fire again until an instruction ran
+    stopped_at: Option<u16>,
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger {
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            instruction_limit: DEFAULT_INSTRUCTION_LIMIT,
+            stopped_at: None,
+        }
+    }
+
+    pub fn breakpoints (&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn watchpoints (&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    // Upper bound on instructions per run so step-over and step-out can't hang the host
+    pub fn set_instruction_limit (&mut self, instruction_limit: u64) {
+        self.instruction_limit = instruction_limit;
+    }
+
+    pub fn add_breakpoint (&mut self, address: u16) {
+        self.remove_breakpoint(address);
+        self.breakpoints.push(Breakpoint { address: address, condition: None });
+    }
+
+    pub fn add_conditional_breakpoint (&mut self, address: u16, condition: Condition) {
+        self.remove_breakpoint(address);
+        self.breakpoints.push(Breakpoint { address: address, condition: Some(condition) });
+    }
+
+    pub fn remove_breakpoint (&mut self, address: u16) {
+        self.breakpoints.retain(|breakpoint| breakpoint.address != address);
+    }
+
+    pub fn add_watchpoint (&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    pub fn remove_watchpoint (&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.retain(|existing| *existing != watchpoint);
+    }
+
+    pub fn clear (&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+    }
+
+    pub fn step (&mut self, machine: &mut Machine) -> Result<StopReason, CpuError> {
+        self.run(machine, Until::Step)
+    }
+
+    // Runs a 2nnn call to completion, other instructions are single stepped
+    pub fn step_over (&mut self, machine: &mut Machine) -> Result<StopReason, CpuError> {
+        let cpu = &machine.cpu;
+        let is_call = cpu.memory.get(cpu.program_counter as usize..cpu.program_counter as usize + 2)
+            .map(|bytes| ((bytes[0] as u16) << 8) | bytes[1] as u16)
+            .and_then(Instruction::decode)
+            .map(|instruction| matches!(instruction, Instruction::Call(_)))
+            .unwrap_or(false);
+
+        let until = if is_call {
+            Until::Return { address: cpu.program_counter.wrapping_add(2), depth: cpu.stack_pointer }
+        } else {
+            Until::Step
+        };
+        self.run(machine, until)
+    }
+
+    // Runs until the current subroutine returns to its caller
+    pub fn step_out (&mut self, machine: &mut Machine) -> Result<StopReason, CpuError> {
+        let until = match machine.cpu.stack_pointer {
+            0 => Until::Step,
+            depth => Until::StackBelow(depth),
+        };
+        self.run(machine, until)
+    }
+
+    // Runs the rest of the current frame, the equivalent of `Machine::run_frame`
+    pub fn run_frame (&mut self, machine: &mut Machine) -> Result<StopReason, CpuError> {
+        if machine.pending_event().is_none() {
+            machine.schedule_frame();
+        }
+        self.run(machine, Until::FrameComplete)
+    }
+
+    pub fn run_until_frame (&mut self, machine: &mut Machine, frame: u64) -> Result<StopReason, CpuError> {
+        if machine.frame_count() >= frame {
+            return Ok(StopReason::FrameReached { frame: machine.frame_count() });
+        }
+        self.run(machine, Until::Frame(frame))
+    }
+
+    fn run (&mut self, machine: &mut Machine, until: Until) -> Result<StopReason, CpuError> {
+        let tracked_memory = machine.cpu.memory_accesses.is_some();
+        let watches_memory = self.watchpoints.iter()
+            .any(|watchpoint| !matches!(*watchpoint, Watchpoint::Register(_)));
+        if watches_memory && !tracked_memory {
+            machine.cpu.set_memory_access_tracking(true);
+        }
+
+        let result = self.run_events(machine, until);
+
+        if watches_memory && !tracked_memory {
+            machine.cpu.set_memory_access_tracking(false);
+        }
+        // Frame boundaries aren't pauses, a breakpoint on the next frame's first instruction still fires
+        match result {
+            Ok(StopReason::FrameComplete) | Ok(StopReason::FrameReached { .. }) | Err(_) => {},
+            Ok(_) => self.stopped_at = Some(machine.cpu.program_counter),
+        }
+        result
+    }
+
+    fn run_events (&mut self, machine: &mut Machine, until: Until) -> Result<StopReason, CpuError> {
+        let mut executed = 0;
+
+        loop {
+            if machine.cpu.has_exited() {
+                return Ok(StopReason::Exited);
+            }
+
+            let event = match (machine.pending_event(), until) {
+                (Some(event), _) => event,
+                (None, Until::FrameComplete) => return Ok(StopReason::FrameComplete),
+                (None, Until::Frame(_)) => {
+                    machine.schedule_frame();
+                    continue;
+                },
+                (None, _) if machine.instructions_per_second() == 0 => return Ok(StopReason::InstructionLimit),
+                (None, _) => {
+                    machine.schedule_frame();
+                    continue;
+                },
+            };
+
+            if event == Event::TimerTick {
+                // Ticks change DT and ST too, they are reported at the instruction about to run
+                let registers_before = self.watched_registers(&machine.cpu);
+                machine.run_pending_event()?;
+                if let Some(reason) = self.check_registers(&machine.cpu, machine.cpu.program_counter, &registers_before) {
+                    return Ok(reason);
+                }
+                if let Until::Frame(frame) = until {
+                    if machine.frame_count() >= frame {
+                        return Ok(StopReason::FrameReached { frame: machine.frame_count() });
+                    }
+                }
+                continue;
+            }
+
+            // A breakpoint the last run paused on doesn't fire again, so the run can resume past it
+            if self.stopped_at != Some(machine.cpu.program_counter) && self.breakpoint_hit(&machine.cpu) {
+                return Ok(StopReason::Breakpoint { pc: machine.cpu.program_counter });
+            }
+            if executed >= self.instruction_limit {
+                return Ok(StopReason::InstructionLimit);
+            }
+
+            let pc = machine.cpu.program_counter;
+            let registers_before = self.watched_registers(&machine.cpu);
+            machine.run_pending_event()?;
+            executed += 1;
+            self.stopped_at = None;
+
+            if let Some(reason) = self.check_watchpoints(&machine.cpu, pc, &registers_before) {
+                return Ok(reason);
+            }
+
+            let cpu = &machine.cpu;
+            let done = match until {
+                Until::Step => true,
+                Until::Return { address, depth } => cpu.program_counter == address && cpu.stack_pointer == depth,
+                Until::StackBelow(depth) => cpu.stack_pointer < depth,
+                Until::FrameComplete | Until::Frame(_) => false,
+            };
+            if done {
+                return Ok(StopReason::Step);
+            }
+        }
+    }
+
+    fn breakpoint_hit (&self, cpu: &Cpu) -> bool {
+        self.breakpoints.iter().any(|breakpoint| {
+            breakpoint.address == cpu.program_counter &&
+                breakpoint.condition.map(|condition| condition.matches(cpu)).unwrap_or(true)
+        })
+    }
+
+    fn watched_registers (&self, cpu: &Cpu) -> Vec<(Register, u16)> {
+        self.watchpoints
+            .iter()
+            .filter_map(|watchpoint| match *watchpoint {
+                Watchpoint::Register(register) => Some((register, register.read(cpu))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn check_watchpoints (&self, cpu: &Cpu, pc: u16, registers_before: &[(Register, u16)]) -> Option<StopReason> {
+        for access in cpu.last_memory_accesses() {
+            for watchpoint in &self.watchpoints {
+                match (*watchpoint, *access) {
+                    (Watchpoint::MemoryWrite { start, end }, MemoryAccess::Write { addr, value }) if addr >= start && addr <= end =>
+                        return Some(StopReason::MemoryWrite { pc: pc, addr: addr, value: value }),
+                    (Watchpoint::MemoryRead { start, end }, MemoryAccess::Read { addr }) if addr >= start && addr <= end =>
+                        return Some(StopReason::MemoryRead { pc: pc, addr: addr }),
+                    _ => {},
+                }
+            }
+        }
+
+        self.check_registers(cpu, pc, registers_before)
+    }
+
+    fn check_registers (&self, cpu: &Cpu, pc: u16, registers_before: &[(Register, u16)]) -> Option<StopReason> {
+        registers_before.iter()
+            .map(|&(register, old)| (register, old, register.read(cpu)))
+            .find(|&(_, old, new)| old != new)
+            .map(|(register, old, new)| StopReason::RegisterChanged { pc: pc, register: register, old: old, new: new })
+    }
+}
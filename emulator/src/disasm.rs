@@ -0,0 +1,220 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+pub use instruction::Instruction;
+
+const PROGRAM_START: u16 = 0x200;
+const DATA_BYTES_PER_LINE: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Code(Instruction),
+    // F000 nnnn with its operand word
+    LongLoad(u16),
+    Data(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub address: u16,
+    pub label: Option<String>,
+    pub bytes: Vec<u8>,
+    pub item: Item,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Listing {
+    pub lines: Vec<Line>,
+    labels: HashMap<u16, String>,
+}
+
+impl Listing {
+    pub fn label_for (&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(|label| label.as_str())
+    }
+
+    // Listing without addresses and raw bytes, accepted by the assembler
+    pub fn to_source (&self) -> String {
+        let mut source = String::new();
+        for line in &self.lines {
+            if let Some(ref label) = line.label {
+                source.push_str(&format!("{}:\n", label));
+            }
+            source.push_str(&format!("    {}\n", self.format_item(&line.item)));
+        }
+        source
+    }
+
+    fn format_item (&self, item: &Item) -> String {
+        match *item {
+            Item::Code(instruction) => match instruction.target_address() {
+                Some(target) => match self.labels.get(&target) {
+                    Some(label) => instruction.format_with_target(label),
+                    None => instruction.to_string(),
+                },
+                None => instruction.to_string(),
+            },
+            Item::LongLoad(address) => match self.labels.get(&address) {
+                Some(label) => format!("LD I, LONG {}", label),
+                None => format!("LD I, LONG 0x{:04X}", address),
+            },
+            Item::Data(ref bytes) => {
+                let values = bytes
+                    .iter()
+                    .map(|byte| format!("0x{:02X}", byte))
+                    .collect::<Vec<String>>();
+                format!("DB {}", values.join(", "))
+            },
+        }
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            if let Some(ref label) = line.label {
+                writeln!(f, "{}:", label)?;
+            }
+            let hex = line.bytes
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect::<String>();
+            writeln!(f, "{:04X}  {:<16}  {}", line.address, hex, self.format_item(&line.item))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn decode (opcode: u16) -> Option<Instruction> {
+    Instruction::decode(opcode)
+}
+
+pub fn disassemble_instruction (opcode: u16) -> Option<String> {
+    Instruction::decode(opcode).map(|instruction| instruction.to_string())
+}
+
+// Disassembles a ROM loaded at 0x200. Code is found by following control flow
+// from the entry point, everything that is never reached is listed as data.
+pub fn disassemble (rom: &[u8]) -> Listing {
+    let end = PROGRAM_START as usize + rom.len();
+    let code_starts = find_code(rom);
+
+    let mut targets = BTreeSet::new();
+    for &address in &code_starts {
+        match decode_at(rom, address) {
+            Some(Item::Code(instruction)) => {
+                if let Some(target) = instruction.target_address() {
+                    targets.insert(target);
+                }
+            },
+            Some(Item::LongLoad(target)) => { targets.insert(target); },
+            _ => {},
+        }
+    }
+
+    let labels = targets
+        .into_iter()
+        .filter(|&target| target >= PROGRAM_START && (target as usize) < end)
+        .map(|target| (target, format!("L{:03X}", target)))
+        .collect::<HashMap<u16, String>>();
+
+    let mut lines = Vec::new();
+    let mut address = PROGRAM_START as usize;
+    while address < end {
+        let label = labels.get(&(address as u16)).cloned();
+
+        if code_starts.contains(&(address as u16)) {
+            if let Some(item) = decode_at(rom, address as u16) {
+                let size = item_size(&item);
+                let offset = address - PROGRAM_START as usize;
+                lines.push(Line {
+                    address: address as u16,
+                    label: label,
+                    bytes: rom[offset..offset + size].to_vec(),
+                    item: item,
+                });
+                address += size;
+                continue;
+            }
+        }
+
+        let start = address;
+        address += 1;
+        while address < end
+            && address - start < DATA_BYTES_PER_LINE
+            && !code_starts.contains(&(address as u16))
+            && !labels.contains_key(&(address as u16)) {
+            address += 1;
+        }
+        let bytes = rom[start - PROGRAM_START as usize..address - PROGRAM_START as usize].to_vec();
+        lines.push(Line {
+            address: start as u16,
+            label: label,
+            bytes: bytes.clone(),
+            item: Item::Data(bytes),
+        });
+    }
+
+    Listing { lines: lines, labels: labels }
+}
+
+fn read_word (rom: &[u8], address: u16) -> Option<u16> {
+    let offset = (address as usize).checked_sub(PROGRAM_START as usize)?;
+    if offset + 1 >= rom.len() {
+        return None;
+    }
+    Some(((rom[offset] as u16) << 8) | rom[offset + 1] as u16)
+}
+
+fn decode_at (rom: &[u8], address: u16) -> Option<Item> {
+    let instruction = Instruction::decode(read_word(rom, address)?)?;
+    if instruction == Instruction::LongLoadI {
+        return read_word(rom, address.wrapping_add(2)).map(Item::LongLoad);
+    }
+    Some(Item::Code(instruction))
+}
+
+fn item_size (item: &Item) -> usize {
+    match *item {
+        Item::Code(instruction) => instruction.size() as usize,
+        Item::LongLoad(_) => 4,
+        Item::Data(ref bytes) => bytes.len(),
+    }
+}
+
+fn find_code (rom: &[u8]) -> BTreeSet<u16> {
+    let mut code_starts = BTreeSet::new();
+    let mut pending = vec![PROGRAM_START];
+
+    while let Some(address) = pending.pop() {
+        if code_starts.contains(&address) {
+            continue;
+        }
+        let item = match decode_at(rom, address) {
+            Some(item) => item,
+            None => continue,
+        };
+        code_starts.insert(address);
+        let next = address.wrapping_add(item_size(&item) as u16);
+
+        match item {
+            Item::Code(Instruction::Jump(target)) => pending.push(target),
+            Item::Code(Instruction::JumpOffset(target)) => pending.push(target),
+            Item::Code(Instruction::Call(target)) => {
+                pending.push(target);
+                pending.push(next);
+            },
+            Item::Code(Instruction::Return) | Item::Code(Instruction::Exit) => {},
+            Item::Code(instruction) if instruction.is_skip() => {
+                pending.push(next);
+                let skipped = match decode_at(rom, next) {
+                    Some(ref skipped) => item_size(skipped) as u16,
+                    None => 2,
+                };
+                pending.push(next.wrapping_add(skipped));
+            },
+            _ => pending.push(next),
+        }
+    }
+
+    code_starts
+}
@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CpuError {
+    UnknownOpcode { pc: u16, opcode: u16 },
+    StackOverflow { pc: u16 },
+    StackUnderflow { pc: u16 },
+    MemoryOutOfBounds { pc: u16, addr: usize },
+}
+
+impl CpuError {
+    pub fn pc (&self) -> u16 {
+        match *self {
+            CpuError::UnknownOpcode { pc, .. } => pc,
+            CpuError::StackOverflow { pc } => pc,
+            CpuError::StackUnderflow { pc } => pc,
+            CpuError::MemoryOutOfBounds { pc, .. } => pc,
+        }
+    }
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CpuError::UnknownOpcode { pc, opcode } =>
+                write!(f, "unknown opcode {:04X} at {:04X}", opcode, pc),
+            CpuError::StackOverflow { pc } =>
+                write!(f, "stack overflow at {:04X}", pc),
+            CpuError::StackUnderflow { pc } =>
+                write!(f, "stack underflow at {:04X}", pc),
+            CpuError::MemoryOutOfBounds { pc, addr } =>
+                write!(f, "memory access out of bounds ({:X}) at {:04X}", addr, pc),
+        }
+    }
+}
+
+impl Error for CpuError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    ChecksumMismatch { expected: u32, actual: u32 },
+    Truncated,
+    InvalidData,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateError::InvalidMagic =>
+                write!(f, "not a save state"),
+            StateError::UnsupportedVersion(version) =>
+                write!(f, "unsupported save state version {}", version),
+            StateError::ChecksumMismatch { expected, actual } =>
+                write!(f, "save state checksum mismatch (expected {:08X}, got {:08X})", expected, actual),
+            StateError::Truncated =>
+                write!(f, "save state is truncated"),
+            StateError::InvalidData =>
+                write!(f, "save state contains invalid data"),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MovieError {
+    // The movie file itself is unreadable, reported with the save state error kinds
+    Format(StateError),
+    RomMismatch { expected: u32, actual: u32 },
+    Desync { frame: u32, expected: u32, actual: u32 },
+    Cpu(CpuError),
+}
+
+impl From<StateError> for MovieError {
+    fn from(error: StateError) -> MovieError {
+        MovieError::Format(error)
+    }
+}
+
+impl From<CpuError> for MovieError {
+    fn from(error: CpuError) -> MovieError {
+        MovieError::Cpu(error)
+    }
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MovieError::Format(StateError::InvalidMagic) =>
+                write!(f, "not a movie"),
+            MovieError::Format(StateError::UnsupportedVersion(version)) =>
+                write!(f, "unsupported movie version {}", version),
+            MovieError::Format(StateError::ChecksumMismatch { expected, actual }) =>
+                write!(f, "movie checksum mismatch (expected {:08X}, got {:08X})", expected, actual),
+            MovieError::Format(StateError::Truncated) =>
+                write!(f, "movie is truncated"),
+            MovieError::Format(StateError::InvalidData) =>
+                write!(f, "movie contains invalid data"),
+            MovieError::RomMismatch { expected, actual } =>
+                write!(f, "movie was recorded with another ROM (expected CRC {:08X}, got {:08X})", expected, actual),
+            MovieError::Desync { frame, expected, actual } =>
+                write!(f, "movie desynced at frame {} (expected state {:08X}, got {:08X})", frame, expected, actual),
+            MovieError::Cpu(error) =>
+                write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for MovieError {}
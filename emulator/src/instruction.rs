@@ -0,0 +1,298 @@
+use std::fmt;
+use ::nibbles::*;
+use variant::Variant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    ScrollDown(u8),
+    ClearScreen,
+    Return,
+    ScrollRight,
+    ScrollLeft,
+    Exit,
+    LowRes,
+    HighRes,
+    Jump(u16),
+    Call(u16),
+    SkipEqImm(u8, u8),
+    SkipNeImm(u8, u8),
+    SkipEqReg(u8, u8),
+    SaveRange(u8, u8),
+    LoadRange(u8, u8),
+    LoadImm(u8, u8),
+    AddImm(u8, u8),
+    LoadReg(u8, u8),
+    Or(u8, u8),
+    And(u8, u8),
+    Xor(u8, u8),
+    Add(u8, u8),
+    Sub(u8, u8),
+    ShiftRight(u8, u8),
+    SubN(u8, u8),
+    ShiftLeft(u8, u8),
+    SkipNeReg(u8, u8),
+    LoadI(u16),
+    JumpOffset(u16),
+    Random(u8, u8),
+    Draw(u8, u8, u8),
+    SkipKey(u8),
+    SkipNotKey(u8),
+    // F000 nnnn, the address is the word following the opcode
+    LongLoadI,
+    Plane(u8),
+    Audio,
+    LoadDelay(u8),
+    WaitKey(u8),
+    SetDelay(u8),
+    SetSound(u8),
+    AddI(u8),
+    LoadFont(u8),
+    LoadBigFont(u8),
+    Bcd(u8),
+    Pitch(u8),
+    Store(u8),
+    Load(u8),
+    StoreFlags(u8),
+    LoadFlags(u8),
+}
+
+impl Instruction {
+    pub fn decode (opcode: u16) -> Option<Instruction> {
+        let nibbles: (u8, u8, u8, u8) = (
+            get_nibble_1(opcode),
+            get_nibble_2(opcode),
+            get_nibble_3(opcode),
+            get_nibble_4(opcode)
+        );
+
+        let instruction = match nibbles {
+            (0x0, 0x0, 0xC, n) => Instruction::ScrollDown(n),
+            (0x0, 0x0, 0xE, 0x0) => Instruction::ClearScreen,
+            (0x0, 0x0, 0xE, 0xE) => Instruction::Return,
+            (0x0, 0x0, 0xF, 0xB) => Instruction::ScrollRight,
+            (0x0, 0x0, 0xF, 0xC) => Instruction::ScrollLeft,
+            (0x0, 0x0, 0xF, 0xD) => Instruction::Exit,
+            (0x0, 0x0, 0xF, 0xE) => Instruction::LowRes,
+            (0x0, 0x0, 0xF, 0xF) => Instruction::HighRes,
+            (0x1, n1, n2, n3) => Instruction::Jump(concat_nibbles_3(n1, n2, n3)),
+            (0x2, n1, n2, n3) => Instruction::Call(concat_nibbles_3(n1, n2, n3)),
+            (0x3, x, k1, k2) => Instruction::SkipEqImm(x, concat_nibbles_2(k1, k2)),
+            (0x4, x, k1, k2) => Instruction::SkipNeImm(x, concat_nibbles_2(k1, k2)),
+            (0x5, x, y, 0x0) => Instruction::SkipEqReg(x, y),
+            (0x5, x, y, 0x2) => Instruction::SaveRange(x, y),
+            (0x5, x, y, 0x3) => Instruction::LoadRange(x, y),
+            (0x6, x, k1, k2) => Instruction::LoadImm(x, concat_nibbles_2(k1, k2)),
+            (0x7, x, k1, k2) => Instruction::AddImm(x, concat_nibbles_2(k1, k2)),
+            (0x8, x, y, 0x0) => Instruction::LoadReg(x, y),
+            (0x8, x, y, 0x1) => Instruction::Or(x, y),
+            (0x8, x, y, 0x2) => Instruction::And(x, y),
+            (0x8, x, y, 0x3) => Instruction::Xor(x, y),
+            (0x8, x, y, 0x4) => Instruction::Add(x, y),
+            (0x8, x, y, 0x5) => Instruction::Sub(x, y),
+            (0x8, x, y, 0x6) => Instruction::ShiftRight(x, y),
+            (0x8, x, y, 0x7) => Instruction::SubN(x, y),
+            (0x8, x, y, 0xE) => Instruction::ShiftLeft(x, y),
+            (0x9, x, y, 0x0) => Instruction::SkipNeReg(x, y),
+            (0xA, n1, n2, n3) => Instruction::LoadI(concat_nibbles_3(n1, n2, n3)),
+            (0xB, n1, n2, n3) => Instruction::JumpOffset(concat_nibbles_3(n1, n2, n3)),
+            (0xC, x, k1, k2) => Instruction::Random(x, concat_nibbles_2(k1, k2)),
+            (0xD, x, y, n) => Instruction::Draw(x, y, n),
+            (0xE, x, 0x9, 0xE) => Instruction::SkipKey(x),
+            (0xE, x, 0xA, 0x1) => Instruction::SkipNotKey(x),
+            (0xF, 0x0, 0x0, 0x0) => Instruction::LongLoadI,
+            (0xF, n, 0x0, 0x1) => Instruction::Plane(n),
+            (0xF, 0x0, 0x0, 0x2) => Instruction::Audio,
+            (0xF, x, 0x0, 0x7) => Instruction::LoadDelay(x),
+            (0xF, x, 0x0, 0xA) => Instruction::WaitKey(x),
+            (0xF, x, 0x1, 0x5) => Instruction::SetDelay(x),
+            (0xF, x, 0x1, 0x8) => Instruction::SetSound(x),
+            (0xF, x, 0x1, 0xE) => Instruction::AddI(x),
+            (0xF, x, 0x2, 0x9) => Instruction::LoadFont(x),
+            (0xF, x, 0x3, 0x0) => Instruction::LoadBigFont(x),
+            (0xF, x, 0x3, 0x3) => Instruction::Bcd(x),
+            (0xF, x, 0x3, 0xA) => Instruction::Pitch(x),
+            (0xF, x, 0x5, 0x5) => Instruction::Store(x),
+            (0xF, x, 0x6, 0x5) => Instruction::Load(x),
+            (0xF, x, 0x7, 0x5) => Instruction::StoreFlags(x),
+            (0xF, x, 0x8, 0x5) => Instruction::LoadFlags(x),
+            _ => return None,
+        };
+
+        Some(instruction)
+    }
+
+    // LongLoadI encodes to F000 only, its address word follows it in memory
+    pub fn encode (&self) -> u16 {
+        let xy = |opcode: u16, x: u8, y: u8| opcode | ((x as u16 & 0xF) << 8) | ((y as u16 & 0xF) << 4);
+        let xkk = |opcode: u16, x: u8, kk: u8| opcode | ((x as u16 & 0xF) << 8) | kk as u16;
+        let fx = |x: u8, low: u16| 0xF000 | ((x as u16 & 0xF) << 8) | low;
+
+        match *self {
+            Instruction::ScrollDown(n) => 0x00C0 | (n as u16 & 0xF),
+            Instruction::ClearScreen => 0x00E0,
+            Instruction::Return => 0x00EE,
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::LowRes => 0x00FE,
+            Instruction::HighRes => 0x00FF,
+            Instruction::Jump(address) => 0x1000 | (address & 0xFFF),
+            Instruction::Call(address) => 0x2000 | (address & 0xFFF),
+            Instruction::SkipEqImm(x, kk) => xkk(0x3000, x, kk),
+            Instruction::SkipNeImm(x, kk) => xkk(0x4000, x, kk),
+            Instruction::SkipEqReg(x, y) => xy(0x5000, x, y),
+            Instruction::SaveRange(x, y) => xy(0x5002, x, y),
+            Instruction::LoadRange(x, y) => xy(0x5003, x, y),
+            Instruction::LoadImm(x, kk) => xkk(0x6000, x, kk),
+            Instruction::AddImm(x, kk) => xkk(0x7000, x, kk),
+            Instruction::LoadReg(x, y) => xy(0x8000, x, y),
+            Instruction::Or(x, y) => xy(0x8001, x, y),
+            Instruction::And(x, y) => xy(0x8002, x, y),
+            Instruction::Xor(x, y) => xy(0x8003, x, y),
+            Instruction::Add(x, y) => xy(0x8004, x, y),
+            Instruction::Sub(x, y) => xy(0x8005, x, y),
+            Instruction::ShiftRight(x, y) => xy(0x8006, x, y),
+            Instruction::SubN(x, y) => xy(0x8007, x, y),
+            Instruction::ShiftLeft(x, y) => xy(0x800E, x, y),
+            Instruction::SkipNeReg(x, y) => xy(0x9000, x, y),
+            Instruction::LoadI(address) => 0xA000 | (address & 0xFFF),
+            Instruction::JumpOffset(address) => 0xB000 | (address & 0xFFF),
+            Instruction::Random(x, kk) => xkk(0xC000, x, kk),
+            Instruction::Draw(x, y, n) => xy(0xD000, x, y) | (n as u16 & 0xF),
+            Instruction::SkipKey(x) => xkk(0xE000, x, 0x9E),
+            Instruction::SkipNotKey(x) => xkk(0xE000, x, 0xA1),
+            Instruction::LongLoadI => 0xF000,
+            Instruction::Plane(n) => fx(n, 0x01),
+            Instruction::Audio => 0xF002,
+            Instruction::LoadDelay(x) => fx(x, 0x07),
+            Instruction::WaitKey(x) => fx(x, 0x0A),
+            Instruction::SetDelay(x) => fx(x, 0x15),
+            Instruction::SetSound(x) => fx(x, 0x18),
+            Instruction::AddI(x) => fx(x, 0x1E),
+            Instruction::LoadFont(x) => fx(x, 0x29),
+            Instruction::LoadBigFont(x) => fx(x, 0x30),
+            Instruction::Bcd(x) => fx(x, 0x33),
+            Instruction::Pitch(x) => fx(x, 0x3A),
+            Instruction::Store(x) => fx(x, 0x55),
+            Instruction::Load(x) => fx(x, 0x65),
+            Instruction::StoreFlags(x) => fx(x, 0x75),
+            Instruction::LoadFlags(x) => fx(x, 0x85),
+        }
+    }
+
+    pub fn is_supported_by (&self, variant: Variant) -> bool {
+        match *self {
+            Instruction::ScrollDown(_) |
+            Instruction::ScrollRight |
+            Instruction::ScrollLeft |
+            Instruction::Exit |
+            Instruction::LowRes |
+            Instruction::HighRes |
+            Instruction::LoadBigFont(_) |
+            Instruction::StoreFlags(_) |
+            Instruction::LoadFlags(_) => variant.supports_super_chip(),
+            Instruction::SaveRange(..) |
+            Instruction::LoadRange(..) |
+            Instruction::LongLoadI |
+            Instruction::Plane(_) |
+            Instruction::Audio |
+            Instruction::Pitch(_) => variant.supports_xo_chip(),
+            _ => true,
+        }
+    }
+
+    // Size in bytes including any operand words
+    pub fn size (&self) -> u16 {
+        match *self {
+            Instruction::LongLoadI => 4,
+            _ => 2,
+        }
+    }
+
+    pub fn target_address (&self) -> Option<u16> {
+        match *self {
+            Instruction::Jump(address) |
+            Instruction::Call(address) |
+            Instruction::LoadI(address) |
+            Instruction::JumpOffset(address) => Some(address),
+            _ => None,
+        }
+    }
+
+    pub fn is_skip (&self) -> bool {
+        matches!(*self,
+            Instruction::SkipEqImm(..) |
+            Instruction::SkipNeImm(..) |
+            Instruction::SkipEqReg(..) |
+            Instruction::SkipNeReg(..) |
+            Instruction::SkipKey(..) |
+            Instruction::SkipNotKey(..))
+    }
+
+    // Formats the instruction with its address operand replaced by a label
+    pub fn format_with_target (&self, target: &str) -> String {
+        match *self {
+            Instruction::Jump(_) => format!("JP {}", target),
+            Instruction::Call(_) => format!("CALL {}", target),
+            Instruction::LoadI(_) => format!("LD I, {}", target),
+            Instruction::JumpOffset(_) => format!("JP V0, {}", target),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::ScrollDown(n) => write!(f, "SCD {}", n),
+            Instruction::ClearScreen => write!(f, "CLS"),
+            Instruction::Return => write!(f, "RET"),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::LowRes => write!(f, "LOW"),
+            Instruction::HighRes => write!(f, "HIGH"),
+            Instruction::Jump(address) => write!(f, "JP 0x{:03X}", address),
+            Instruction::Call(address) => write!(f, "CALL 0x{:03X}", address),
+            Instruction::SkipEqImm(x, kk) => write!(f, "SE V{:X}, 0x{:02X}", x, kk),
+            Instruction::SkipNeImm(x, kk) => write!(f, "SNE V{:X}, 0x{:02X}", x, kk),
+            Instruction::SkipEqReg(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::SaveRange(x, y) => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            Instruction::LoadRange(x, y) => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            Instruction::LoadImm(x, kk) => write!(f, "LD V{:X}, 0x{:02X}", x, kk),
+            Instruction::AddImm(x, kk) => write!(f, "ADD V{:X}, 0x{:02X}", x, kk),
+            Instruction::LoadReg(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::Add(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubN(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipNeReg(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LoadI(address) => write!(f, "LD I, 0x{:03X}", address),
+            Instruction::JumpOffset(address) => write!(f, "JP V0, 0x{:03X}", address),
+            Instruction::Random(x, kk) => write!(f, "RND V{:X}, 0x{:02X}", x, kk),
+            Instruction::Draw(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::SkipKey(x) => write!(f, "SKP V{:X}", x),
+            Instruction::SkipNotKey(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LongLoadI => write!(f, "LD I, LONG"),
+            Instruction::Plane(n) => write!(f, "PLANE {}", n),
+            Instruction::Audio => write!(f, "AUDIO"),
+            Instruction::LoadDelay(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelay(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSound(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddI(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::LoadFont(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::LoadBigFont(x) => write!(f, "LD HF, V{:X}", x),
+            Instruction::Bcd(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::Pitch(x) => write!(f, "PITCH V{:X}", x),
+            Instruction::Store(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::Load(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::StoreFlags(x) => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadFlags(x) => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
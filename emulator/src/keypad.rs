@@ -1,11 +1,25 @@
+use std::mem;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyChange {
+    pub key: u8,
+    pub pressed: bool,
+}
+
 pub struct Keypad {
-    keys: [bool; 16],
+    pub(crate) keys: [bool; 16],
+    just_pressed: [bool; 16],
+    just_released: [bool; 16],
+    changes: Option<Vec<KeyChange>>,
 }
 
 impl Keypad {
     pub fn new() -> Keypad {
       Keypad {
         keys: [false; 16],
+        just_pressed: [false; 16],
+        just_released: [false; 16],
+        changes: None,
       }
     }
 
@@ -19,19 +33,67 @@ impl Keypad {
         return None;
     }
 
+    // Lowest key pressed since the edges were last cleared, held or not
+    pub fn get_first_just_pressed_key (&self) -> Option<u8> {
+        (0..16).find(|&key| self.key_was_just_pressed(key))
+    }
+
     pub fn key_is_pressed (&mut self, key: u8) -> bool{
         self.keys[key as usize]
     }
 
+    pub fn key_was_just_pressed (&self, key: u8) -> bool {
+        self.just_pressed[key as usize]
+    }
+
+    pub fn key_was_just_released (&self, key: u8) -> bool {
+        self.just_released[key as usize]
+    }
+
+    // Edges pile up until cleared, `Machine` clears them after every frame
+    pub fn clear_edges (&mut self) {
+        self.just_pressed = [false; 16];
+        self.just_released = [false; 16];
+    }
+
     pub fn press_key (&mut self, key: u8) {
-        self.keys[key as usize] = true;
+        self.set_key(key, true);
     }
 
     pub fn release_key (&mut self, key: u8) {
-        self.keys[key as usize] = false;
+        self.set_key(key, false);
     }
 
     pub fn release_all_keys (&mut self) {
-        self.keys = [false; 16];
+        for key in 0..self.keys.len() {
+            self.set_key(key as u8, false);
+        }
+    }
+
+    // Keeps a log of every key that changed state, in order, for movie recording
+    pub fn set_change_tracking (&mut self, enabled: bool) {
+        self.changes = if enabled { Some(Vec::new()) } else { None };
+    }
+
+    pub fn take_changes (&mut self) -> Vec<KeyChange> {
+        match self.changes {
+            Some(ref mut changes) => mem::take(changes),
+            None => Vec::new(),
+        }
+    }
+
+    fn set_key (&mut self, key: u8, pressed: bool) {
+        if self.keys[key as usize] == pressed {
+            return;
+        }
+        self.keys[key as usize] = pressed;
+        if pressed {
+            self.just_pressed[key as usize] = true;
+        } else {
+            self.just_released[key as usize] = true;
+        }
+        if let Some(ref mut changes) = self.changes {
+            changes.push(KeyChange { key: key, pressed: pressed });
+        }
     }
 }
\ No newline at end of file
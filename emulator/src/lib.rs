@@ -1,6 +1,20 @@
 extern crate rand;
 
+pub mod checksum;
 pub mod cpu;
+pub mod debugger;
+pub mod disasm;
+pub mod error;
+pub mod instruction;
 pub mod keypad;
+pub mod machine;
+pub mod movie;
+pub mod quirks;
+pub mod random;
+pub mod rewind;
+pub mod savestate;
+pub mod snapshot;
+pub mod trace;
+pub mod variant;
 mod nibbles;
 mod sprites;
\ No newline at end of file
@@ -0,0 +1,152 @@
+use std::time::Duration;
+use cpu::Cpu;
+use error::CpuError;
+
+pub const DEFAULT_INSTRUCTIONS_PER_SECOND: u32 = 480;
+pub const DEFAULT_TIMER_FREQUENCY: u32 = 60;
+// Longest stretch `run_for` catches up on, a frontend that stalled for longer drops the rest
+pub const MAX_RUN_DURATION: Duration = Duration::from_secs(1);
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Instruction,
+    TimerTick,
+}
+
+pub struct Machine {
+    pub cpu: Cpu,
+    instructions_per_second: u32,
+    timer_frequency: u32,
+
+    // Elapsed time scaled by the respective frequency, one event is due per NANOS_PER_SECOND
+    instruction_budget: u64,
+    timer_budget: u64,
+    frame_remainder: u64,
+    frame_count: u64,
+}
+
+impl Machine {
+    pub fn new(cpu: Cpu) -> Machine {
+        Machine {
+            cpu: cpu,
+            instructions_per_second: DEFAULT_INSTRUCTIONS_PER_SECOND,
+            timer_frequency: DEFAULT_TIMER_FREQUENCY,
+            instruction_budget: 0,
+            timer_budget: 0,
+            frame_remainder: 0,
+            frame_count: 0,
+        }
+    }
+
+    pub fn instructions_per_second (&self) -> u32 {
+        self.instructions_per_second
+    }
+
+    pub fn set_instructions_per_second (&mut self, instructions_per_second: u32) {
+        self.instructions_per_second = instructions_per_second;
+        self.instruction_budget = 0;
+        self.frame_remainder = 0;
+    }
+
+    pub fn timer_frequency (&self) -> u32 {
+        self.timer_frequency
+    }
+
+    pub fn set_timer_frequency (&mut self, timer_frequency: u32) {
+        self.timer_frequency = timer_frequency.max(1);
+        self.timer_budget = 0;
+        self.frame_remainder = 0;
+    }
+
+    // Number of timer ticks since the last reset
+    pub fn frame_count (&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn reset (&mut self) {
+        self.cpu.reset();
+        self.instruction_budget = 0;
+        self.timer_budget = 0;
+        self.frame_remainder = 0;
+        self.frame_count = 0;
+    }
+
+    pub fn run_for (&mut self, duration: Duration) -> Result<(), CpuError> {
+        let duration = duration.min(MAX_RUN_DURATION);
+        let nanos = duration.as_secs() * NANOS_PER_SECOND + duration.subsec_nanos() as u64;
+
+        self.instruction_budget = self.instruction_budget.saturating_add(nanos * self.instructions_per_second as u64);
+        self.timer_budget = self.timer_budget.saturating_add(nanos * self.timer_frequency as u64);
+
+        self.run_budget()
+    }
+
+    pub fn run_frame (&mut self) -> Result<(), CpuError> {
+        self.schedule_frame();
+        self.run_budget()
+    }
+
+    // Adds one timer period worth of budget without running it, see `pending_event`
+    pub fn schedule_frame (&mut self) {
+        // Carry the division remainder so a second's worth of frames runs exactly the configured rate
+        let scaled_budget = NANOS_PER_SECOND * self.instructions_per_second as u64 + self.frame_remainder;
+        self.instruction_budget += scaled_budget / self.timer_frequency as u64;
+        self.frame_remainder = scaled_budget % self.timer_frequency as u64;
+        self.timer_budget += NANOS_PER_SECOND;
+    }
+
+    // The event `run_pending_event` would run next, None once the budget is spent
+    pub fn pending_event (&self) -> Option<Event> {
+        let instruction_due = self.instruction_budget >= NANOS_PER_SECOND;
+        let timer_due = self.timer_budget >= NANOS_PER_SECOND;
+
+        if instruction_due && timer_due {
+            // Run whichever event became due first
+            let instruction_overdue = (self.instruction_budget - NANOS_PER_SECOND).saturating_mul(self.timer_frequency as u64);
+            let timer_overdue = (self.timer_budget - NANOS_PER_SECOND).saturating_mul(self.instructions_per_second as u64);
+
+            if timer_overdue >= instruction_overdue {
+                Some(Event::TimerTick)
+            } else {
+                Some(Event::Instruction)
+            }
+        } else if timer_due {
+            Some(Event::TimerTick)
+        } else if instruction_due {
+            Some(Event::Instruction)
+        } else {
+            None
+        }
+    }
+
+    pub fn run_pending_event (&mut self) -> Result<Option<Event>, CpuError> {
+        let event = self.pending_event();
+        match event {
+            Some(Event::TimerTick) => self.tick_timers(),
+            Some(Event::Instruction) => self.execute_instruction()?,
+            None => {},
+        }
+        // The frame is over once its budget is spent, key edges only last that long
+        if event.is_some() && self.pending_event().is_none() {
+            self.cpu.keypad.clear_edges();
+        }
+        Ok(event)
+    }
+
+    fn run_budget (&mut self) -> Result<(), CpuError> {
+        while self.run_pending_event()?.is_some() {}
+        Ok(())
+    }
+
+    fn tick_timers (&mut self) {
+        self.timer_budget -= NANOS_PER_SECOND;
+        self.frame_count += 1;
+        self.cpu.tick_timers();
+    }
+
+    fn execute_instruction (&mut self) -> Result<(), CpuError> {
+        self.instruction_budget -= NANOS_PER_SECOND;
+        self.cpu.execute_next_op_code()
+    }
+}
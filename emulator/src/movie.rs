@@ -0,0 +1,290 @@
+// Movie format, all integers little endian:
+//
+//   magic              4 bytes  "C8MV"
+//   version            u16      MOVIE_VERSION
+//   variant            u8       as in save states
+//   quirks             u8       Quirks::to_bits
+//   instructions/s     u32
+//   timer frequency    u32
+//   seed               u64      SeededRandom seed the recording started from
+//   rom                u32      CRC-32 of the ROM
+//   checksum interval  u32      frames between state checksums
+//   frames             u32      length of the movie
+//   event count        u32
+//   events             frame u32, key u8 with bit 7 set for a press
+//   checksum count     u32
+//   checksums          frame u32, CRC-32 of the save state after that frame
+//   checksum           u32      CRC-32 of every preceding byte
+
+use checksum::crc32;
+use error::{CpuError, MovieError, StateError};
+use keypad::KeyChange;
+use machine::Machine;
+use quirks::Quirks;
+use random::SeededRandom;
+use savestate::{variant_from_byte, variant_to_byte, StateReader, StateWriter};
+use variant::Variant;
+
+pub const MOVIE_MAGIC: &[u8; 4] = b"C8MV";
+pub const MOVIE_VERSION: u16 = 1;
+pub const DEFAULT_CHECKSUM_INTERVAL: u32 = 60;
+
+const KEY_PRESSED: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MovieHeader {
+    pub variant: Variant,
+    pub quirks: Quirks,
+    pub instructions_per_second: u32,
+    pub timer_frequency: u32,
+    pub seed: u64,
+    pub rom_checksum: u32,
+    pub checksum_interval: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MovieEvent {
+    pub frame: u32,
+    pub change: KeyChange,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    pub header: MovieHeader,
+    pub frames: u32,
+    pub events: Vec<MovieEvent>,
+    pub checksums: Vec<(u32, u32)>,
+}
+
+impl Movie {
+    pub fn to_bytes (&self) -> Vec<u8> {
+        let mut writer = StateWriter { bytes: Vec::new() };
+        let header = &self.header;
+
+        writer.write_bytes(MOVIE_MAGIC);
+        writer.write_u16(MOVIE_VERSION);
+        writer.write_u8(variant_to_byte(header.variant));
+        writer.write_u8(header.quirks.to_bits());
+        writer.write_u32(header.instructions_per_second);
+        writer.write_u32(header.timer_frequency);
+        writer.write_u64(header.seed);
+        writer.write_u32(header.rom_checksum);
+        writer.write_u32(header.checksum_interval);
+        writer.write_u32(self.frames);
+
+        writer.write_u32(self.events.len() as u32);
+        for event in &self.events {
+            writer.write_u32(event.frame);
+            let pressed = if event.change.pressed { KEY_PRESSED } else { 0 };
+            writer.write_u8(pressed | (event.change.key & 0xF));
+        }
+
+        writer.write_u32(self.checksums.len() as u32);
+        for &(frame, checksum) in &self.checksums {
+            writer.write_u32(frame);
+            writer.write_u32(checksum);
+        }
+
+        let checksum = crc32(&writer.bytes);
+        writer.write_u32(checksum);
+
+        writer.bytes
+    }
+
+    pub fn from_bytes (bytes: &[u8]) -> Result<Movie, MovieError> {
+        if bytes.len() < MOVIE_MAGIC.len() || &bytes[..MOVIE_MAGIC.len()] != MOVIE_MAGIC {
+            return Err(StateError::InvalidMagic.into());
+        }
+        if bytes.len() < MOVIE_MAGIC.len() + 2 + 4 {
+            return Err(StateError::Truncated.into());
+        }
+
+        let (body, checksum_bytes) = bytes.split_at(bytes.len() - 4);
+        let mut reader = StateReader { bytes: body, position: MOVIE_MAGIC.len() };
+
+        let version = reader.read_u16()?;
+        if version != MOVIE_VERSION {
+            return Err(StateError::UnsupportedVersion(version).into());
+        }
+
+        let expected = StateReader { bytes: checksum_bytes, position: 0 }.read_u32()?;
+        let actual = crc32(body);
+        if expected != actual {
+            return Err(StateError::ChecksumMismatch { expected: expected, actual: actual }.into());
+        }
+
+        let header = MovieHeader {
+            variant: variant_from_byte(reader.read_u8()?)?,
+            quirks: Quirks::from_bits(reader.read_u8()?),
+            instructions_per_second: reader.read_u32()?,
+            timer_frequency: reader.read_u32()?,
+            seed: reader.read_u64()?,
+            rom_checksum: reader.read_u32()?,
+            checksum_interval: reader.read_u32()?,
+        };
+        let frames = reader.read_u32()?;
+
+        let event_count = reader.read_u32()? as usize;
+        let mut events = Vec::new();
+        for _ in 0..event_count {
+            let frame = reader.read_u32()?;
+            let key = reader.read_u8()?;
+            if key & !(KEY_PRESSED | 0xF) != 0 {
+                return Err(StateError::InvalidData.into());
+            }
+            events.push(MovieEvent {
+                frame: frame,
+                change: KeyChange { key: key & 0xF, pressed: key & KEY_PRESSED != 0 },
+            });
+        }
+
+        let checksum_count = reader.read_u32()? as usize;
+        let mut checksums = Vec::new();
+        for _ in 0..checksum_count {
+            checksums.push((reader.read_u32()?, reader.read_u32()?));
+        }
+
+        if reader.position != body.len() {
+            return Err(StateError::InvalidData.into());
+        }
+
+        Ok(Movie { header: header, frames: frames, events: events, checksums: checksums })
+    }
+}
+
+fn state_checksum (machine: &Machine) -> u32 {
+    // Leave out the trailing CRC, including it would give the same residue for every state
+    let state = machine.cpu.save_state();
+    crc32(&state[..state.len() - 4])
+}
+
+// Restarts the machine the way the movie header describes so every run starts identically
+fn restart (machine: &mut Machine, header: &MovieHeader, rom: &[u8]) {
+    machine.cpu.variant = header.variant;
+    machine.cpu.quirks = header.quirks;
+    machine.cpu.set_random_source(Box::new(SeededRandom::new(header.seed)));
+    machine.set_instructions_per_second(header.instructions_per_second);
+    machine.set_timer_frequency(header.timer_frequency);
+    machine.reset();
+    // RPL flags outlive a reset and the header doesn't record them
+    machine.cpu.rpl_flags = [0; 16];
+    machine.cpu.keypad.release_all_keys();
+    machine.cpu.keypad.clear_edges();
+    machine.cpu.keypad.take_changes();
+    machine.cpu.load_program(rom);
+}
+
+// Records key changes made between frames. The machine has to be run through `run_frame`
+// so every change is tagged with the frame it took effect in
+pub struct MovieRecorder {
+    movie: Movie,
+}
+
+impl MovieRecorder {
+    // Restarts the machine with its current variant, quirks and speed
+    pub fn start (machine: &mut Machine, rom: &[u8], seed: u64, checksum_interval: u32) -> MovieRecorder {
+        let header = MovieHeader {
+            variant: machine.cpu.variant,
+            quirks: machine.cpu.quirks,
+            instructions_per_second: machine.instructions_per_second(),
+            timer_frequency: machine.timer_frequency(),
+            seed: seed,
+            rom_checksum: crc32(rom),
+            checksum_interval: checksum_interval.max(1),
+        };
+        restart(machine, &header, rom);
+        machine.cpu.keypad.set_change_tracking(true);
+
+        MovieRecorder {
+            movie: Movie { header: header, frames: 0, events: Vec::new(), checksums: Vec::new() },
+        }
+    }
+
+    pub fn run_frame (&mut self, machine: &mut Machine) -> Result<(), CpuError> {
+        let frame = self.movie.frames;
+        for change in machine.cpu.keypad.take_changes() {
+            self.movie.events.push(MovieEvent { frame: frame, change: change });
+        }
+
+        // A failing frame still counts, so playing the movie back reproduces the failure
+        let result = machine.run_frame();
+        self.movie.frames += 1;
+        result?;
+
+        if self.movie.frames.is_multiple_of(self.movie.header.checksum_interval) {
+            self.movie.checksums.push((self.movie.frames, state_checksum(machine)));
+        }
+        Ok(())
+    }
+
+    // Key changes after the last frame never took effect and are dropped
+    pub fn finish (self, machine: &mut Machine) -> Movie {
+        machine.cpu.keypad.set_change_tracking(false);
+        self.movie
+    }
+}
+
+pub struct MoviePlayer {
+    movie: Movie,
+    frame: u32,
+    next_event: usize,
+    next_checksum: usize,
+}
+
+impl MoviePlayer {
+    pub fn start (movie: Movie, machine: &mut Machine, rom: &[u8]) -> Result<MoviePlayer, MovieError> {
+        let actual = crc32(rom);
+        if actual != movie.header.rom_checksum {
+            return Err(MovieError::RomMismatch { expected: movie.header.rom_checksum, actual: actual });
+        }
+        restart(machine, &movie.header, rom);
+
+        Ok(MoviePlayer { movie: movie, frame: 0, next_event: 0, next_checksum: 0 })
+    }
+
+    pub fn frame (&self) -> u32 {
+        self.frame
+    }
+
+    pub fn is_finished (&self) -> bool {
+        self.frame >= self.movie.frames
+    }
+
+    // Replays the next frame and checks it against the recorded state checksum, if any
+    pub fn run_frame (&mut self, machine: &mut Machine) -> Result<(), MovieError> {
+        while let Some(event) = self.movie.events.get(self.next_event) {
+            if event.frame > self.frame {
+                break;
+            }
+            if event.change.pressed {
+                machine.cpu.keypad.press_key(event.change.key);
+            } else {
+                machine.cpu.keypad.release_key(event.change.key);
+            }
+            self.next_event += 1;
+        }
+
+        machine.run_frame()?;
+        self.frame += 1;
+
+        while let Some(&(frame, expected)) = self.movie.checksums.get(self.next_checksum) {
+            if frame > self.frame {
+                break;
+            }
+            self.next_checksum += 1;
+            let actual = state_checksum(machine);
+            if frame == self.frame && actual != expected {
+                return Err(MovieError::Desync { frame: frame, expected: expected, actual: actual });
+            }
+        }
+        Ok(())
+    }
+
+    // Plays the remaining frames, stopping at the first desync
+    pub fn play_to_end (&mut self, machine: &mut Machine) -> Result<(), MovieError> {
+        while !self.is_finished() {
+            self.run_frame(machine)?;
+        }
+        Ok(())
+    }
+}
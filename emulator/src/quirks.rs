@@ -0,0 +1,70 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quirks {
+    // 8xy6/8xyE shift Vy into Vx instead of shifting Vx in place
+    pub shift_uses_vy: bool,
+    // Fx55/Fx65 leave I pointing past the last register transferred
+    pub load_store_increments_i: bool,
+    // Bnnn is read as Bxnn and jumps to xnn + Vx instead of nnn + V0
+    pub jump_uses_vx: bool,
+    // Dxyn clips sprites at the screen edges instead of wrapping them
+    pub clip_sprites: bool,
+    // 8xy1/8xy2/8xy3 reset VF to 0
+    pub logic_resets_vf: bool,
+    // Fx0A takes the lowest held key at once instead of waiting for a key to be pressed and released
+    pub key_wait_on_press: bool,
+}
+
+impl Quirks {
+    pub fn cosmac_vip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            clip_sprites: true,
+            logic_resets_vf: true,
+            key_wait_on_press: false,
+        }
+    }
+
+    pub fn schip() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_uses_vx: true,
+            clip_sprites: true,
+            logic_resets_vf: false,
+            key_wait_on_press: false,
+        }
+    }
+
+    pub fn xo_chip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            clip_sprites: false,
+            logic_resets_vf: false,
+            key_wait_on_press: false,
+        }
+    }
+
+    pub fn to_bits(&self) -> u8 {
+        (self.shift_uses_vy as u8) |
+            ((self.load_store_increments_i as u8) << 1) |
+            ((self.jump_uses_vx as u8) << 2) |
+            ((self.clip_sprites as u8) << 3) |
+            ((self.logic_resets_vf as u8) << 4) |
+            ((self.key_wait_on_press as u8) << 5)
+    }
+
+    pub fn from_bits(bits: u8) -> Quirks {
+        Quirks {
+            shift_uses_vy: bits & 1 != 0,
+            load_store_increments_i: bits & (1 << 1) != 0,
+            jump_uses_vx: bits & (1 << 2) != 0,
+            clip_sprites: bits & (1 << 3) != 0,
+            logic_resets_vf: bits & (1 << 4) != 0,
+            key_wait_on_press: bits & (1 << 5) != 0,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use rand::rngs::OsRng;
+use rand::{Error, RngCore};
+
+pub const DEFAULT_SEED: u64 = 0x853C_49E6_748F_EA9B;
+
+pub trait RandomSource {
+    fn next_byte(&mut self) -> u8;
+
+    // Sources that can be captured return their internal state so save states replay exactly
+    fn state(&self) -> Option<u64> {
+        None
+    }
+}
+
+// xorshift64*, small and fast enough to run on every Cxkk while staying reproducible
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> SeededRandom {
+        // A zero state would make xorshift produce zeros forever
+        let state = if seed == 0 { DEFAULT_SEED } else { seed };
+        SeededRandom { state: state }
+    }
+
+}
+
+impl Default for SeededRandom {
+    fn default() -> SeededRandom {
+        SeededRandom::new(DEFAULT_SEED)
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_byte(&mut self) -> u8 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+
+        (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn state(&self) -> Option<u64> {
+        Some(self.state)
+    }
+}
+
+pub struct OsRandom {
+    rng: OsRng,
+}
+
+impl OsRandom {
+    pub fn new() -> Result<OsRandom, Error> {
+        Ok(OsRandom { rng: OsRng::new()? })
+    }
+}
+
+impl RandomSource for OsRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.rng.next_u32() as u8
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::VecDeque;
+use cpu::Cpu;
+use error::StateError;
+
+pub const DEFAULT_REWIND_CAPACITY: usize = 600;
+pub const DEFAULT_REWIND_INTERVAL: u32 = 2;
+// Every n:th snapshot is stored in full so restoring never replays too many deltas
+const KEYFRAME_INTERVAL: usize = 30;
+
+enum Snapshot {
+    Full(Vec<u8>),
+    // XOR against the previous snapshot, run length encoded
+    Delta(Vec<u8>),
+}
+
+pub struct Rewind {
+    capacity: usize,
+    interval: u32,
+    frames_since_capture: u32,
+    deltas_since_keyframe: usize,
+    snapshots: VecDeque<Snapshot>,
+    latest_state: Option<Vec<u8>>,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, interval: u32) -> Rewind {
+        Rewind {
+            capacity: capacity.max(1),
+            interval: interval.max(1),
+            frames_since_capture: 0,
+            deltas_since_keyframe: 0,
+            snapshots: VecDeque::new(),
+            latest_state: None,
+        }
+    }
+
+    pub fn len (&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty (&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn interval (&self) -> u32 {
+        self.interval
+    }
+
+    pub fn size_in_bytes (&self) -> usize {
+        self.snapshots.iter()
+            .map(|snapshot| match *snapshot {
+                Snapshot::Full(ref bytes) => bytes.len(),
+                Snapshot::Delta(ref bytes) => bytes.len(),
+            })
+            .sum()
+    }
+
+    pub fn clear (&mut self) {
+        self.snapshots.clear();
+        self.latest_state = None;
+        self.frames_since_capture = 0;
+        self.deltas_since_keyframe = 0;
+    }
+
+    pub fn record_frame (&mut self, cpu: &Cpu) {
+        if !self.snapshots.is_empty() && self.frames_since_capture + 1 < self.interval {
+            self.frames_since_capture += 1;
+            return;
+        }
+
+        self.capture(cpu);
+    }
+
+    pub fn capture (&mut self, cpu: &Cpu) {
+        let state = cpu.save_state();
+
+        let snapshot = match self.latest_state {
+            Some(ref previous) if previous.len() == state.len() && self.deltas_since_keyframe + 1 < KEYFRAME_INTERVAL => {
+                self.deltas_since_keyframe += 1;
+                Snapshot::Delta(encode_delta(previous, &state))
+            },
+            _ => {
+                self.deltas_since_keyframe = 0;
+                Snapshot::Full(state.clone())
+            },
+        };
+
+        self.snapshots.push_back(snapshot);
+        self.latest_state = Some(state);
+        self.frames_since_capture = 0;
+
+        while self.snapshots.len() > self.capacity {
+            self.drop_oldest();
+        }
+    }
+
+    // Restores the newest snapshot that is at least `frames` old, returns how many frames were rewound
+    pub fn rewind (&mut self, cpu: &mut Cpu, frames: u32) -> Result<u32, StateError> {
+        if self.snapshots.is_empty() {
+            return Ok(0);
+        }
+
+        let mut age = self.frames_since_capture;
+        let mut index = self.snapshots.len() - 1;
+        while age < frames && index > 0 {
+            index -= 1;
+            age += self.interval;
+        }
+
+        let state = self.reconstruct(index);
+        cpu.load_state(&state)?;
+
+        self.snapshots.truncate(index + 1);
+        self.latest_state = Some(state);
+        self.frames_since_capture = 0;
+        self.deltas_since_keyframe = (0..(index + 1)).rev()
+            .take_while(|i| matches!(self.snapshots[*i], Snapshot::Delta(_)))
+            .count();
+
+        Ok(age)
+    }
+
+    fn reconstruct (&self, index: usize) -> Vec<u8> {
+        let keyframe = (0..(index + 1)).rev()
+            .find(|i| matches!(self.snapshots[*i], Snapshot::Full(_)))
+            .expect("oldest rewind snapshot is always stored in full");
+
+        let mut state = match self.snapshots[keyframe] {
+            Snapshot::Full(ref bytes) => bytes.clone(),
+            Snapshot::Delta(_) => unreachable!(),
+        };
+        for i in (keyframe + 1)..(index + 1) {
+            if let Snapshot::Delta(ref delta) = self.snapshots[i] {
+                apply_delta(&mut state, delta);
+            }
+        }
+
+        state
+    }
+
+    fn drop_oldest (&mut self) {
+        let needs_keyframe = matches!(self.snapshots.get(1), Some(&Snapshot::Delta(_)));
+
+        if needs_keyframe {
+            let state = self.reconstruct(1);
+            self.snapshots[1] = Snapshot::Full(state);
+        }
+        self.snapshots.pop_front();
+    }
+}
+
+impl Default for Rewind {
+    fn default() -> Rewind {
+        Rewind::new(DEFAULT_REWIND_CAPACITY, DEFAULT_REWIND_INTERVAL)
+    }
+}
+
+fn write_varint (bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint (bytes: &[u8], position: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*position];
+        *position += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+// Encodes the XOR of two equally sized states as (unchanged run, changed run, changed bytes) triples
+fn encode_delta (previous: &[u8], current: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    let mut position = 0;
+
+    while position < current.len() {
+        let unchanged_start = position;
+        while position < current.len() && previous[position] == current[position] {
+            position += 1;
+        }
+        let changed_start = position;
+        while position < current.len() && previous[position] != current[position] {
+            position += 1;
+        }
+
+        write_varint(&mut delta, changed_start - unchanged_start);
+        write_varint(&mut delta, position - changed_start);
+        for i in changed_start..position {
+            delta.push(previous[i] ^ current[i]);
+        }
+    }
+
+    delta
+}
+
+fn apply_delta (state: &mut [u8], delta: &[u8]) {
+    let mut state_position = 0;
+    let mut delta_position = 0;
+
+    while delta_position < delta.len() {
+        state_position += read_varint(delta, &mut delta_position);
+        let changed = read_varint(delta, &mut delta_position);
+        for _ in 0..changed {
+            state[state_position] ^= delta[delta_position];
+            state_position += 1;
+            delta_position += 1;
+        }
+    }
+}
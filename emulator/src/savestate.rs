@@ -0,0 +1,322 @@
+// Save state format, all integers little endian:
+//
+//   magic            4 bytes  "C8ST"
+//   version          u16      STATE_VERSION, version 1 states still load
+//   variant          u8       0 = CHIP-8, 1 = SUPER-CHIP, 2 = XO-CHIP
+//   quirks           u8       Quirks::to_bits
+//   program counter  u16
+//   i                u16
+//   v                16 bytes
+//   delay timer      u8
+//   sound timer      u8
+//   stack pointer    u8
+//   stack            16 x u16
+//   rpl flags        16 bytes
+//   cpu flags        u8       bit 0 hires, 1 exited, 2 awaiting key, 3 sound reported, 4 audio pattern set,
+//                             5 awaited key pressed (since version 2)
+//   await key        u8       register awaiting a key in the low nibble, 0 unless bit 2 is set,
+//                             and the key waiting to be released in the high nibble if bit 5 is set
+//                             (since version 2)
+//   selected planes  u8
+//   audio pattern    16 bytes zeroed unless bit 4 is set
+//   audio pitch      u8
+//   keypad           u16      bit n set while key n is held
+//   rng present      u8       1 when the random source state follows
+//   rng state        u64
+//   memory length    u32
+//   memory           memory length bytes
+//   display width    u16
+//   display height   u16
+//   planes           2 x width x height bits, row major, packed MSB first
+//   checksum         u32      CRC-32 of every preceding byte
+
+use cpu::Cpu;
+use checksum::crc32;
+use error::StateError;
+use quirks::Quirks;
+use random::SeededRandom;
+use variant::Variant;
+
+pub const STATE_MAGIC: &[u8; 4] = b"C8ST";
+pub const STATE_VERSION: u16 = 2;
+// Before Fx0A waited for a release, a version 1 await just takes the next key pressed
+const STATE_VERSION_1: u16 = 1;
+
+const FLAG_HIRES: u8 = 1 << 0;
+const FLAG_EXITED: u8 = 1 << 1;
+const FLAG_AWAITING_KEY: u8 = 1 << 2;
+const FLAG_SOUND_REPORTED: u8 = 1 << 3;
+const FLAG_AUDIO_PATTERN: u8 = 1 << 4;
+const FLAG_AWAITED_KEY: u8 = 1 << 5;
+
+pub(crate) struct StateWriter {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl StateWriter {
+    pub(crate) fn write_u8 (&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub(crate) fn write_u16 (&mut self, value: u16) {
+        self.bytes.push(value as u8);
+        self.bytes.push((value >> 8) as u8);
+    }
+
+    pub(crate) fn write_u32 (&mut self, value: u32) {
+        self.write_u16(value as u16);
+        self.write_u16((value >> 16) as u16);
+    }
+
+    pub(crate) fn write_u64 (&mut self, value: u64) {
+        self.write_u32(value as u32);
+        self.write_u32((value >> 32) as u32);
+    }
+
+    pub(crate) fn write_bytes (&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn write_bits (&mut self, bits: &[bool]) {
+        for chunk in bits.chunks(8) {
+            let byte = chunk.iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, bit)| byte | ((*bit as u8) << (7 - i)));
+            self.write_u8(byte);
+        }
+    }
+}
+
+pub(crate) struct StateReader<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub(crate) fn read_bytes (&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.position + len > self.bytes.len() {
+            return Err(StateError::Truncated);
+        }
+
+        let bytes = &self.bytes[self.position..(self.position + len)];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_u8 (&mut self) -> Result<u8, StateError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub(crate) fn read_u16 (&mut self) -> Result<u16, StateError> {
+        let bytes = self.read_bytes(2)?;
+        Ok((bytes[0] as u16) | ((bytes[1] as u16) << 8))
+    }
+
+    pub(crate) fn read_u32 (&mut self) -> Result<u32, StateError> {
+        let low = self.read_u16()? as u32;
+        let high = self.read_u16()? as u32;
+        Ok(low | (high << 16))
+    }
+
+    pub(crate) fn read_u64 (&mut self) -> Result<u64, StateError> {
+        let low = self.read_u32()? as u64;
+        let high = self.read_u32()? as u64;
+        Ok(low | (high << 32))
+    }
+
+    pub(crate) fn read_bits (&mut self, len: usize) -> Result<Vec<bool>, StateError> {
+        let bytes = self.read_bytes(len.div_ceil(8))?;
+        Ok((0..len).map(|i| (bytes[i / 8] >> (7 - i % 8)) & 1 != 0).collect())
+    }
+}
+
+pub(crate) fn variant_to_byte (variant: Variant) -> u8 {
+    match variant {
+        Variant::Chip8 => 0,
+        Variant::SuperChip => 1,
+        Variant::XoChip => 2,
+    }
+}
+
+pub(crate) fn variant_from_byte (byte: u8) -> Result<Variant, StateError> {
+    match byte {
+        0 => Ok(Variant::Chip8),
+        1 => Ok(Variant::SuperChip),
+        2 => Ok(Variant::XoChip),
+        _ => Err(StateError::InvalidData),
+    }
+}
+
+impl Cpu {
+    pub fn save_state (&self) -> Vec<u8> {
+        let mut writer = StateWriter { bytes: Vec::new() };
+
+        writer.write_bytes(STATE_MAGIC);
+        writer.write_u16(STATE_VERSION);
+        writer.write_u8(variant_to_byte(self.variant));
+        writer.write_u8(self.quirks.to_bits());
+
+        writer.write_u16(self.program_counter);
+        writer.write_u16(self.registers.i);
+        writer.write_bytes(&self.registers.v);
+        writer.write_u8(self.registers.delay_timer);
+        writer.write_u8(self.registers.sound_timer);
+        writer.write_u8(self.stack_pointer as u8);
+        for address in self.stack.iter() {
+            writer.write_u16(*address);
+        }
+        writer.write_bytes(&self.rpl_flags);
+
+        let mut flags = 0;
+        if self.hires { flags |= FLAG_HIRES; }
+        if self.exited { flags |= FLAG_EXITED; }
+        if self.await_key.is_some() { flags |= FLAG_AWAITING_KEY; }
+        if self.reported_sound_active { flags |= FLAG_SOUND_REPORTED; }
+        if self.audio_pattern.is_some() { flags |= FLAG_AUDIO_PATTERN; }
+        if self.awaited_key.is_some() { flags |= FLAG_AWAITED_KEY; }
+        writer.write_u8(flags);
+        writer.write_u8(self.await_key.unwrap_or(0) | (self.awaited_key.unwrap_or(0) << 4));
+        writer.write_u8(self.selected_planes);
+        writer.write_bytes(&self.audio_pattern.unwrap_or([0; 16]));
+        writer.write_u8(self.audio_pitch);
+
+        let keypad = self.keypad.keys.iter()
+            .enumerate()
+            .fold(0u16, |mask, (key, pressed)| mask | ((*pressed as u16) << key));
+        writer.write_u16(keypad);
+
+        match self.rng.state() {
+            Some(state) => {
+                writer.write_u8(1);
+                writer.write_u64(state);
+            },
+            None => {
+                writer.write_u8(0);
+                writer.write_u64(0);
+            },
+        }
+
+        writer.write_u32(self.memory.len() as u32);
+        writer.write_bytes(&self.memory);
+
+        writer.write_u16(self.display_width() as u16);
+        writer.write_u16(self.display_height() as u16);
+        for plane in [&self.video_memory, &self.video_memory_plane2].iter() {
+            let bits = plane.iter().flat_map(|row| row.iter().cloned()).collect::<Vec<bool>>();
+            writer.write_bits(&bits);
+        }
+
+        let checksum = crc32(&writer.bytes);
+        writer.write_u32(checksum);
+
+        writer.bytes
+    }
+
+    pub fn load_state (&mut self, bytes: &[u8]) -> Result<(), StateError> {
+        if bytes.len() < STATE_MAGIC.len() || &bytes[..STATE_MAGIC.len()] != STATE_MAGIC {
+            return Err(StateError::InvalidMagic);
+        }
+        if bytes.len() < STATE_MAGIC.len() + 2 + 4 {
+            return Err(StateError::Truncated);
+        }
+
+        let (body, checksum_bytes) = bytes.split_at(bytes.len() - 4);
+        let mut reader = StateReader { bytes: bytes, position: STATE_MAGIC.len() };
+
+        let version = reader.read_u16()?;
+        if version != STATE_VERSION && version != STATE_VERSION_1 {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let expected = StateReader { bytes: checksum_bytes, position: 0 }.read_u32()?;
+        let actual = crc32(body);
+        if expected != actual {
+            return Err(StateError::ChecksumMismatch { expected: expected, actual: actual });
+        }
+
+        // Parse everything before touching the cpu so a bad state leaves it intact
+        let variant = variant_from_byte(reader.read_u8()?)?;
+        let quirks = Quirks::from_bits(reader.read_u8()?);
+
+        let program_counter = reader.read_u16()?;
+        let i = reader.read_u16()?;
+        let mut v = [0; 16];
+        v.copy_from_slice(reader.read_bytes(16)?);
+        let delay_timer = reader.read_u8()?;
+        let sound_timer = reader.read_u8()?;
+        let stack_pointer = reader.read_u8()? as usize;
+        let mut stack = [0; 16];
+        for address in stack.iter_mut() {
+            *address = reader.read_u16()?;
+        }
+        if stack_pointer > stack.len() {
+            return Err(StateError::InvalidData);
+        }
+        let mut rpl_flags = [0; 16];
+        rpl_flags.copy_from_slice(reader.read_bytes(16)?);
+
+        let flags = reader.read_u8()?;
+        let await_key = reader.read_u8()?;
+        let selected_planes = reader.read_u8()?;
+        let mut audio_pattern = [0; 16];
+        audio_pattern.copy_from_slice(reader.read_bytes(16)?);
+        let audio_pitch = reader.read_u8()?;
+        let keypad = reader.read_u16()?;
+        let has_rng_state = reader.read_u8()? != 0;
+        let rng_state = reader.read_u64()?;
+
+        let memory_len = reader.read_u32()? as usize;
+        if memory_len != variant.memory_size() {
+            return Err(StateError::InvalidData);
+        }
+        let memory = reader.read_bytes(memory_len)?.to_vec();
+
+        let width = reader.read_u16()? as usize;
+        let height = reader.read_u16()? as usize;
+        let hires = flags & FLAG_HIRES != 0;
+        let expected_size = if hires {
+            (::cpu::HIRES_DISPLAY_WIDTH, ::cpu::HIRES_DISPLAY_HEIGHT)
+        } else {
+            (::cpu::LORES_DISPLAY_WIDTH, ::cpu::LORES_DISPLAY_HEIGHT)
+        };
+        if (width, height) != expected_size {
+            return Err(StateError::InvalidData);
+        }
+        let plane1 = reader.read_bits(width * height)?;
+        let plane2 = reader.read_bits(width * height)?;
+
+        if reader.position != body.len() {
+            return Err(StateError::InvalidData);
+        }
+
+        self.variant = variant;
+        self.quirks = quirks;
+        self.program_counter = program_counter;
+        self.registers.i = i;
+        self.registers.v = v;
+        self.registers.delay_timer = delay_timer;
+        self.registers.sound_timer = sound_timer;
+        self.stack = stack;
+        self.stack_pointer = stack_pointer;
+        self.rpl_flags = rpl_flags;
+        self.hires = hires;
+        self.exited = flags & FLAG_EXITED != 0;
+        self.await_key = if flags & FLAG_AWAITING_KEY != 0 { Some(await_key & 0xF) } else { None };
+        self.awaited_key = if version != STATE_VERSION_1 && flags & FLAG_AWAITED_KEY != 0 { Some(await_key >> 4) } else { None };
+        self.reported_sound_active = flags & FLAG_SOUND_REPORTED != 0;
+        self.audio_pattern = if flags & FLAG_AUDIO_PATTERN != 0 { Some(audio_pattern) } else { None };
+        self.selected_planes = selected_planes & 0x3;
+        self.audio_pitch = audio_pitch;
+        for (key, pressed) in self.keypad.keys.iter_mut().enumerate() {
+            *pressed = keypad & (1 << key) != 0;
+        }
+        if has_rng_state {
+            self.rng = Box::new(SeededRandom::new(rng_state));
+        }
+        self.memory = memory;
+        self.video_memory = plane1.chunks(width).map(|row| row.to_vec()).collect();
+        self.video_memory_plane2 = plane2.chunks(width).map(|row| row.to_vec()).collect();
+
+        Ok(())
+    }
+}
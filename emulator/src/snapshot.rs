@@ -0,0 +1,145 @@
+// Snapshot files hold the display at chosen frames as ASCII art, see `to_ascii`:
+//
+//   # frame 60
+//   ....##..
+//   ........
+//   # frame 120
+//   ...
+//
+// The files themselves are read and blessed by the tests, see tests/snapshot_file.rs
+
+use cpu::Cpu;
+use error::CpuError;
+use machine::Machine;
+use movie::MovieEvent;
+
+// Background, plane 1, plane 2 and both planes
+pub const ASCII_PIXELS: [char; 4] = ['.', '#', 'o', '@'];
+
+const FRAME_HEADER: &str = "# frame ";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub frame: u32,
+    pub display: String,
+}
+
+// Runs `machine` until the last frame in `frames`, applying each event before the frame it is
+// tagged with, and takes a snapshot once each of `frames` has run
+pub fn capture (machine: &mut Machine, events: &[MovieEvent], frames: &[u32]) -> Result<Vec<Snapshot>, CpuError> {
+    let mut snapshots = Vec::new();
+    let last = frames.iter().cloned().max().unwrap_or(0);
+    let mut next_event = 0;
+
+    for frame in 0..last {
+        while let Some(event) = events.get(next_event).filter(|event| event.frame <= frame) {
+            next_event += 1;
+            if event.change.pressed {
+                machine.cpu.keypad.press_key(event.change.key);
+            } else {
+                machine.cpu.keypad.release_key(event.change.key);
+            }
+        }
+        machine.run_frame()?;
+        if frames.contains(&(frame + 1)) {
+            snapshots.push(Snapshot { frame: frame + 1, display: to_ascii(&machine.cpu) });
+        }
+    }
+    Ok(snapshots)
+}
+
+// One line per display row, see ASCII_PIXELS
+pub fn to_ascii (cpu: &Cpu) -> String {
+    let mut ascii = String::new();
+    for y in 0..cpu.display_height() {
+        for x in 0..cpu.display_width() {
+            ascii.push(ASCII_PIXELS[(cpu.pixel(x, y) & 0x3) as usize]);
+        }
+        ascii.push('\n');
+    }
+    ascii
+}
+
+pub fn to_text (snapshots: &[Snapshot]) -> String {
+    let mut text = String::new();
+    for snapshot in snapshots {
+        text.push_str(&format!("{}{}\n", FRAME_HEADER, snapshot.frame));
+        text.push_str(&snapshot.display);
+    }
+    text
+}
+
+pub fn parse (text: &str) -> Result<Vec<Snapshot>, String> {
+    let mut snapshots: Vec<Snapshot> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if let Some(frame) = line.strip_prefix(FRAME_HEADER) {
+            let frame = frame.trim().parse::<u32>()
+                .map_err(|_| format!("line {}: invalid frame number", number + 1))?;
+            snapshots.push(Snapshot { frame: frame, display: String::new() });
+        } else {
+            match snapshots.last_mut() {
+                Some(snapshot) => {
+                    snapshot.display.push_str(line);
+                    snapshot.display.push('\n');
+                },
+                None => return Err(format!("line {}: expected '{}<n>'", number + 1, FRAME_HEADER)),
+            }
+        }
+    }
+    Ok(snapshots)
+}
+
+// Expected and actual rows next to each other, rows that differ are marked with `>`
+pub fn side_by_side_diff (expected: &str, actual: &str) -> String {
+    let expected_rows = expected.lines().collect::<Vec<&str>>();
+    let actual_rows = actual.lines().collect::<Vec<&str>>();
+    let width = expected_rows.iter().map(|row| row.len()).max().unwrap_or(0).max("expected".len());
+
+    let mut diff = format!("  {:width$} | actual\n", "expected", width = width);
+    for index in 0..expected_rows.len().max(actual_rows.len()) {
+        let expected_row = expected_rows.get(index).cloned().unwrap_or("");
+        let actual_row = actual_rows.get(index).cloned().unwrap_or("");
+        let marker = if expected_row == actual_row { ' ' } else { '>' };
+        diff.push_str(&format!("{} {:width$} | {}\n", marker, expected_row, actual_row, width = width));
+    }
+    diff
+}
+
+fn differing_pixels (expected: &str, actual: &str) -> usize {
+    let expected_rows = expected.lines().map(|row| row.as_bytes()).collect::<Vec<&[u8]>>();
+    let actual_rows = actual.lines().map(|row| row.as_bytes()).collect::<Vec<&[u8]>>();
+
+    (0..expected_rows.len().max(actual_rows.len()))
+        .map(|index| {
+            let expected_row = expected_rows.get(index).cloned().unwrap_or(&[]);
+            let actual_row = actual_rows.get(index).cloned().unwrap_or(&[]);
+            (0..expected_row.len().max(actual_row.len()))
+                .filter(|&x| expected_row.get(x) != actual_row.get(x))
+                .count()
+        })
+        .sum()
+}
+
+// Describes every difference between two sets of snapshots, None when they match
+pub fn compare (expected: &[Snapshot], actual: &[Snapshot]) -> Option<String> {
+    let mut report = String::new();
+    for snapshot in actual {
+        match expected.iter().find(|expected| expected.frame == snapshot.frame) {
+            Some(expected) if expected.display == snapshot.display => {},
+            Some(expected) => {
+                report.push_str(&format!(
+                    "frame {}: {} pixels differ\n{}",
+                    snapshot.frame,
+                    differing_pixels(&expected.display, &snapshot.display),
+                    side_by_side_diff(&expected.display, &snapshot.display),
+                ));
+            },
+            None => report.push_str(&format!("frame {}: no snapshot recorded\n", snapshot.frame)),
+        }
+    }
+    for expected in expected.iter().filter(|expected| !actual.iter().any(|actual| actual.frame == expected.frame)) {
+        report.push_str(&format!("frame {}: snapshot recorded but not captured\n", expected.frame));
+    }
+
+    if report.is_empty() { None } else { Some(report) }
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Write;
+use cpu::Cpu;
+use debugger::Register;
+use instruction::Instruction;
+
+pub const TRACED_REGISTERS: [Register; 19] = [
+    Register::V(0x0), Register::V(0x1), Register::V(0x2), Register::V(0x3),
+    Register::V(0x4), Register::V(0x5), Register::V(0x6), Register::V(0x7),
+    Register::V(0x8), Register::V(0x9), Register::V(0xA), Register::V(0xB),
+    Register::V(0xC), Register::V(0xD), Register::V(0xE), Register::V(0xF),
+    Register::I, Register::DelayTimer, Register::SoundTimer,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegisterChange {
+    pub register: Register,
+    pub old: u16,
+    pub new: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    pub opcode: u16,
+    pub instruction: Option<Instruction>,
+    pub register_changes: Vec<RegisterChange>,
+    pub memory_writes: Vec<(usize, u8)>,
+}
+
+// One line per instruction: `PC OPCODE` followed by the new register values and
+// memory writes, with the mnemonic last so traces from other emulators diff cleanly
+// once the comments are stripped, e.g. `0204 F055 I=0301 [0300]=42 ; LD [I], V0`
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04X} {:04X}", self.pc, self.opcode)?;
+        for change in &self.register_changes {
+            match change.register {
+                Register::V(_) | Register::DelayTimer | Register::SoundTimer =>
+                    write!(f, " {}={:02X}", change.register, change.new)?,
+                Register::I =>
+                    write!(f, " {}={:04X}", change.register, change.new)?,
+            }
+        }
+        for &(address, value) in &self.memory_writes {
+            write!(f, " [{:04X}]={:02X}", address, value)?;
+        }
+        if let Some(instruction) = self.instruction {
+            write!(f, " ; {}", instruction)?;
+        }
+        Ok(())
+    }
+}
+
+// Keeps the most recent entries, dropping the oldest once full
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceBuffer {
+    capacity: usize,
+    entries: VecDeque<TraceEntry>,
+}
+
+impl TraceBuffer {
+    pub fn new (capacity: usize) -> TraceBuffer {
+        TraceBuffer {
+            capacity: capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity (&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len (&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty (&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries (&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter()
+    }
+
+    pub fn push (&mut self, entry: TraceEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn clear (&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl fmt::Display for TraceBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+pub enum TraceSink {
+    Buffer(TraceBuffer),
+    Writer(Box<dyn Write>),
+    Callback(Box<dyn FnMut(&TraceEntry)>),
+}
+
+impl TraceSink {
+    pub fn record (&mut self, entry: TraceEntry) {
+        match *self {
+            TraceSink::Buffer(ref mut buffer) => buffer.push(entry),
+            // Tracing must not change how a ROM runs, so write errors are dropped
+            TraceSink::Writer(ref mut writer) => { let _ = writeln!(writer, "{}", entry); },
+            TraceSink::Callback(ref mut callback) => callback(&entry),
+        }
+    }
+}
+
+pub(crate) fn snapshot_registers (cpu: &Cpu) -> [u16; 19] {
+    let mut values = [0; 19];
+    for (value, register) in values.iter_mut().zip(TRACED_REGISTERS.iter()) {
+        *value = register.read(cpu);
+    }
+    values
+}
+
+pub(crate) fn register_changes (before: &[u16; 19], after: &[u16; 19]) -> Vec<RegisterChange> {
+    TRACED_REGISTERS
+        .iter()
+        .zip(before.iter().zip(after.iter()))
+        .filter(|&(_, (old, new))| old != new)
+        .map(|(&register, (&old, &new))| RegisterChange { register: register, old: old, new: new })
+        .collect()
+}
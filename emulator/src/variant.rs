@@ -0,0 +1,38 @@
+use quirks::Quirks;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Variant {
+    #[default]
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Variant {
+    pub fn supports_super_chip(&self) -> bool {
+        match *self {
+            Variant::Chip8 => false,
+            Variant::SuperChip => true,
+            Variant::XoChip => true,
+        }
+    }
+
+    pub fn supports_xo_chip(&self) -> bool {
+        matches!(*self, Variant::XoChip)
+    }
+
+    pub fn memory_size(&self) -> usize {
+        match *self {
+            Variant::XoChip => 0x10000,
+            _ => 0x1000,
+        }
+    }
+
+    pub fn default_quirks(&self) -> Quirks {
+        match *self {
+            Variant::Chip8 => Quirks::cosmac_vip(),
+            Variant::SuperChip => Quirks::schip(),
+            Variant::XoChip => Quirks::xo_chip(),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+extern crate chip8_emulator;
+
+mod cpu_fixture;
+use chip8_emulator::cpu::*;
+use chip8_emulator::error::CpuError;
+use cpu_fixture::CpuFixture;
+
+// Registers as (index, value) pairs. Registers left out start at 0 and must not change
+type Registers = &'static [(usize, u8)];
+
+struct RegisterCase {
+    name: &'static str,
+    opcode: u16,
+    before: Registers,
+    after: Registers,
+}
+
+const REGISTER_CASES: &[RegisterCase] = &[
+    RegisterCase { name: "6xkk loads", opcode: 0x6A42, before: &[(0xA, 0x11)], after: &[(0xA, 0x42)] },
+    RegisterCase { name: "6xkk loads VF", opcode: 0x6F42, before: &[], after: &[(0xF, 0x42)] },
+    RegisterCase { name: "7xkk adds", opcode: 0x7A10, before: &[(0xA, 0x20)], after: &[(0xA, 0x30)] },
+    RegisterCase { name: "7xkk wraps without carry", opcode: 0x7AFF, before: &[(0xA, 0x02), (0xF, 0x55)], after: &[(0xA, 0x01)] },
+    RegisterCase { name: "7xkk adds to VF", opcode: 0x7F01, before: &[(0xF, 0xFF)], after: &[(0xF, 0x00)] },
+
+    RegisterCase { name: "8xy0 copies", opcode: 0x8AB0, before: &[(0xA, 0x01), (0xB, 0x02)], after: &[(0xA, 0x02)] },
+    RegisterCase { name: "8xy0 copies into VF", opcode: 0x8F00, before: &[(0x0, 0x07), (0xF, 0x01)], after: &[(0xF, 0x07)] },
+    RegisterCase { name: "8xy1 ors", opcode: 0x8AB1, before: &[(0xA, 0x44), (0xB, 0x22), (0xF, 0x12)], after: &[(0xA, 0x66)] },
+    RegisterCase { name: "8xy1 ors into VF", opcode: 0x8F01, before: &[(0x0, 0x0F), (0xF, 0xF0)], after: &[(0xF, 0xFF)] },
+    RegisterCase { name: "8xy2 ands", opcode: 0x8AB2, before: &[(0xA, 0x6C), (0xB, 0x3A), (0xF, 0x12)], after: &[(0xA, 0x28)] },
+    RegisterCase { name: "8xy3 xors", opcode: 0x8AB3, before: &[(0xA, 0x6C), (0xB, 0x3A), (0xF, 0x12)], after: &[(0xA, 0x56)] },
+    RegisterCase { name: "8xy3 xors a register with itself", opcode: 0x8AA3, before: &[(0xA, 0x6C)], after: &[(0xA, 0x00)] },
+
+    RegisterCase { name: "8xy4 adds", opcode: 0x8AB4, before: &[(0xA, 0x10), (0xB, 0x20), (0xF, 0x12)], after: &[(0xA, 0x30), (0xF, 0)] },
+    RegisterCase { name: "8xy4 reaches 0xFF without carry", opcode: 0x8AB4, before: &[(0xA, 0xF0), (0xB, 0x0F)], after: &[(0xA, 0xFF)] },
+    RegisterCase { name: "8xy4 wraps with carry", opcode: 0x8AB4, before: &[(0xA, 0xFF), (0xB, 0x01)], after: &[(0xA, 0x00), (0xF, 1)] },
+    RegisterCase { name: "8xy4 adds a register to itself", opcode: 0x8AA4, before: &[(0xA, 0x80)], after: &[(0xA, 0x00), (0xF, 1)] },
+    RegisterCase { name: "8xy4 flag wins over VF result", opcode: 0x8F04, before: &[(0x0, 0x02), (0xF, 0xFF)], after: &[(0xF, 1)] },
+    RegisterCase { name: "8xy4 clears VF result without carry", opcode: 0x8F04, before: &[(0x0, 0x02), (0xF, 0x01)], after: &[(0xF, 0)] },
+    RegisterCase { name: "8xy4 reads VF as operand", opcode: 0x80F4, before: &[(0x0, 0x01), (0xF, 0xFF)], after: &[(0x0, 0x00), (0xF, 1)] },
+
+    RegisterCase { name: "8xy5 subtracts", opcode: 0x8AB5, before: &[(0xA, 0x30), (0xB, 0x10)], after: &[(0xA, 0x20), (0xF, 1)] },
+    RegisterCase { name: "8xy5 equal operands don't borrow", opcode: 0x8AB5, before: &[(0xA, 0x42), (0xB, 0x42)], after: &[(0xA, 0x00), (0xF, 1)] },
+    RegisterCase { name: "8xy5 wraps with borrow", opcode: 0x8AB5, before: &[(0xA, 0x10), (0xB, 0x30), (0xF, 0x12)], after: &[(0xA, 0xE0), (0xF, 0)] },
+    RegisterCase { name: "8xy5 flag wins over VF result", opcode: 0x8F05, before: &[(0x0, 0x10), (0xF, 0x30)], after: &[(0xF, 1)] },
+    RegisterCase { name: "8xy5 borrow into VF", opcode: 0x8F05, before: &[(0x0, 0x30), (0xF, 0x10)], after: &[(0xF, 0)] },
+    RegisterCase { name: "8xy5 reads VF as operand", opcode: 0x80F5, before: &[(0x0, 0x10), (0xF, 0x30)], after: &[(0x0, 0xE0), (0xF, 0)] },
+
+    RegisterCase { name: "8xy6 shifts right", opcode: 0x8A06, before: &[(0xA, 0x05)], after: &[(0xA, 0x02), (0xF, 1)] },
+    RegisterCase { name: "8xy6 clears flag", opcode: 0x8A06, before: &[(0xA, 0x04), (0xF, 0x12)], after: &[(0xA, 0x02), (0xF, 0)] },
+    RegisterCase { name: "8xy6 flag wins over VF result", opcode: 0x8F06, before: &[(0xF, 0x04)], after: &[(0xF, 0)] },
+    RegisterCase { name: "8xy6 sets flag in VF", opcode: 0x8F06, before: &[(0xF, 0x05)], after: &[(0xF, 1)] },
+
+    RegisterCase { name: "8xy7 subtracts", opcode: 0x8AB7, before: &[(0xA, 0x10), (0xB, 0x30)], after: &[(0xA, 0x20), (0xF, 1)] },
+    RegisterCase { name: "8xy7 equal operands don't borrow", opcode: 0x8AB7, before: &[(0xA, 0x42), (0xB, 0x42)], after: &[(0xA, 0x00), (0xF, 1)] },
+    RegisterCase { name: "8xy7 wraps with borrow", opcode: 0x8AB7, before: &[(0xA, 0x30), (0xB, 0x10), (0xF, 0x12)], after: &[(0xA, 0xE0), (0xF, 0)] },
+    RegisterCase { name: "8xy7 flag wins over VF result", opcode: 0x8F07, before: &[(0x0, 0x30), (0xF, 0x10)], after: &[(0xF, 1)] },
+    RegisterCase { name: "8xy7 reads VF as operand", opcode: 0x80F7, before: &[(0x0, 0x30), (0xF, 0x10)], after: &[(0x0, 0xE0), (0xF, 0)] },
+
+    RegisterCase { name: "8xyE shifts left", opcode: 0x8A0E, before: &[(0xA, 0x81)], after: &[(0xA, 0x02), (0xF, 1)] },
+    RegisterCase { name: "8xyE clears flag", opcode: 0x8A0E, before: &[(0xA, 0x41), (0xF, 0x12)], after: &[(0xA, 0x82), (0xF, 0)] },
+    RegisterCase { name: "8xyE flag wins over VF result", opcode: 0x8F0E, before: &[(0xF, 0x40)], after: &[(0xF, 0)] },
+    RegisterCase { name: "8xyE sets flag in VF", opcode: 0x8F0E, before: &[(0xF, 0x80)], after: &[(0xF, 1)] },
+
+    RegisterCase { name: "Cxkk with a zero mask", opcode: 0xCA00, before: &[(0xA, 0x42)], after: &[(0xA, 0x00)] },
+    RegisterCase { name: "Fx07 reads the delay timer", opcode: 0xF307, before: &[], after: &[(0x3, 0x00)] },
+];
+
+struct SkipCase {
+    name: &'static str,
+    opcode: u16,
+    before: Registers,
+    keys: &'static [u8],
+    skips: bool,
+}
+
+const SKIP_CASES: &[SkipCase] = &[
+    SkipCase { name: "3xkk equal", opcode: 0x3A42, before: &[(0xA, 0x42)], keys: &[], skips: true },
+    SkipCase { name: "3xkk not equal", opcode: 0x3A42, before: &[(0xA, 0x41)], keys: &[], skips: false },
+    SkipCase { name: "4xkk equal", opcode: 0x4A42, before: &[(0xA, 0x42)], keys: &[], skips: false },
+    SkipCase { name: "4xkk not equal", opcode: 0x4A42, before: &[(0xA, 0x41)], keys: &[], skips: true },
+    SkipCase { name: "5xy0 equal", opcode: 0x5AB0, before: &[(0xA, 0x42), (0xB, 0x42)], keys: &[], skips: true },
+    SkipCase { name: "5xy0 not equal", opcode: 0x5AB0, before: &[(0xA, 0x42)], keys: &[], skips: false },
+    SkipCase { name: "9xy0 equal", opcode: 0x9AB0, before: &[(0xA, 0x42), (0xB, 0x42)], keys: &[], skips: false },
+    SkipCase { name: "9xy0 not equal", opcode: 0x9AB0, before: &[(0xA, 0x42)], keys: &[], skips: true },
+    SkipCase { name: "Ex9E pressed", opcode: 0xEA9E, before: &[(0xA, 0x5)], keys: &[0x5], skips: true },
+    SkipCase { name: "Ex9E other key pressed", opcode: 0xEA9E, before: &[(0xA, 0x5)], keys: &[0x6], skips: false },
+    SkipCase { name: "Ex9E uses the low nibble", opcode: 0xEA9E, before: &[(0xA, 0x15)], keys: &[0x5], skips: true },
+    SkipCase { name: "ExA1 pressed", opcode: 0xEAA1, before: &[(0xA, 0x5)], keys: &[0x5], skips: false },
+    SkipCase { name: "ExA1 not pressed", opcode: 0xEAA1, before: &[(0xA, 0x5)], keys: &[], skips: true },
+    SkipCase { name: "ExA1 uses the low nibble", opcode: 0xEAA1, before: &[(0xA, 0xF5)], keys: &[0x5], skips: false },
+];
+
+struct IndexCase {
+    name: &'static str,
+    opcode: u16,
+    before: Registers,
+    i_before: u16,
+    i_after: u16,
+}
+
+const INDEX_CASES: &[IndexCase] = &[
+    IndexCase { name: "Annn loads", opcode: 0xA123, before: &[], i_before: 0x456, i_after: 0x123 },
+    IndexCase { name: "Fx1E adds", opcode: 0xF11E, before: &[(0x1, 0xAA)], i_before: 0x15, i_after: 0xBF },
+    IndexCase { name: "Fx1E leaves VF alone past 0xFFF", opcode: 0xF11E, before: &[(0x1, 0x02), (0xF, 0x12)], i_before: 0xFFF, i_after: 0x1001 },
+    IndexCase { name: "Fx1E wraps", opcode: 0xF11E, before: &[(0x1, 0x03)], i_before: 0xFFFF, i_after: 0x0002 },
+    IndexCase { name: "Fx29 points at a glyph", opcode: 0xF129, before: &[(0x1, 0xA)], i_before: 0, i_after: 50 },
+    IndexCase { name: "Fx29 last glyph", opcode: 0xF129, before: &[(0x1, 0xF)], i_before: 0, i_after: 75 },
+    IndexCase { name: "Fx29 uses the low nibble", opcode: 0xF129, before: &[(0x1, 0x34)], i_before: 0, i_after: 20 },
+    IndexCase { name: "Fx29 large values", opcode: 0xF129, before: &[(0x1, 0xFF)], i_before: 0, i_after: 75 },
+];
+
+fn registers (values: Registers) -> [u8; 16] {
+    let mut registers = [0; 16];
+    for &(index, value) in values {
+        registers[index] = value;
+    }
+    registers
+}
+
+fn run (opcode: u16, before: Registers) -> CpuFixture {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[opcode]);
+    fixture.cpu.registers.v = registers(before);
+    fixture
+}
+
+#[test]
+fn test_register_cases() {
+    for case in REGISTER_CASES {
+        let mut fixture = run(case.opcode, case.before);
+        fixture.cpu.execute_next_op_code().unwrap();
+
+        let mut expected = registers(case.before);
+        for &(index, value) in case.after {
+            expected[index] = value;
+        }
+        assert_eq!(fixture.cpu.registers.v, expected, "{} ({:04X})", case.name, case.opcode);
+        assert_eq!(fixture.cpu.program_counter, 0x202, "{} ({:04X})", case.name, case.opcode);
+    }
+}
+
+#[test]
+fn test_skip_cases() {
+    for case in SKIP_CASES {
+        let mut fixture = run(case.opcode, case.before);
+        for &key in case.keys {
+            fixture.cpu.keypad.press_key(key);
+        }
+        fixture.cpu.execute_next_op_code().unwrap();
+
+        let expected = if case.skips { 0x204 } else { 0x202 };
+        assert_eq!(fixture.cpu.program_counter, expected, "{} ({:04X})", case.name, case.opcode);
+        assert_eq!(fixture.cpu.registers.v, registers(case.before), "{} ({:04X})", case.name, case.opcode);
+    }
+}
+
+#[test]
+fn test_index_cases() {
+    for case in INDEX_CASES {
+        let mut fixture = run(case.opcode, case.before);
+        fixture.cpu.registers.i = case.i_before;
+        fixture.cpu.execute_next_op_code().unwrap();
+
+        assert_eq!(fixture.cpu.registers.i, case.i_after, "{} ({:04X})", case.name, case.opcode);
+        assert_eq!(fixture.cpu.registers.v, registers(case.before), "{} ({:04X})", case.name, case.opcode);
+    }
+}
+
+#[test]
+fn test_bcd_cases() {
+    for &(value, digits) in &[(0, [0, 0, 0]), (9, [0, 0, 9]), (10, [0, 1, 0]), (100, [1, 0, 0]), (255, [2, 5, 5])] {
+        let mut fixture = run(0xF533, &[]);
+        fixture.cpu.registers.v[5] = value;
+        fixture.cpu.registers.i = 0x600;
+        fixture.cpu.execute_next_op_code().unwrap();
+
+        assert_eq!(&fixture.cpu.memory[0x600..0x603], &digits, "BCD of {}", value);
+        assert_eq!(fixture.cpu.registers.i, 0x600);
+    }
+}
+
+#[test]
+fn test_store_and_load_cases() {
+    // Fx55 then Fx65 round trips V0 through Vx, VF included
+    let mut fixture = run(0xFF55, &[(0x0, 0x10), (0x7, 0x17), (0xF, 0x1F)]);
+    fixture.cpu.registers.i = 0x600;
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.memory[0x600], 0x10);
+    assert_eq!(fixture.cpu.memory[0x607], 0x17);
+    assert_eq!(fixture.cpu.memory[0x60F], 0x1F);
+    assert_eq!(fixture.cpu.memory[0x610], 0x00);
+
+    let mut fixture = run(0xF265, &[(0x3, 0x33)]);
+    fixture.cpu.registers.i = 0x600;
+    fixture.cpu.memory[0x600..0x604].copy_from_slice(&[0xA0, 0xA1, 0xA2, 0xA3]);
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(&fixture.cpu.registers.v[..4], &[0xA0, 0xA1, 0xA2, 0x33]);
+
+    // Storing past the end of memory is an error rather than a wrap
+    let mut fixture = run(0xF155, &[]);
+    fixture.cpu.registers.i = 0xFFF;
+    assert_eq!(
+        fixture.cpu.execute_next_op_code(),
+        Err(CpuError::MemoryOutOfBounds { pc: 0x200, addr: 0x1000 })
+    );
+}
+
+#[test]
+fn test_flow_cases() {
+    let mut fixture = run(0x1345, &[]);
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, 0x345, "1nnn");
+
+    let mut fixture = run(0xB300, &[(0x0, 0x45), (0x3, 0x99)]);
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, 0x345, "Bnnn adds V0");
+
+    let mut fixture = run(0xBFFF, &[(0x0, 0x03)]);
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, 0x002, "Bnnn wraps at the end of memory");
+
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0x2206, 0x0000, 0x0000, 0x00EE]);
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, 0x206, "2nnn");
+    assert_eq!(fixture.cpu.stack_pointer, 1, "2nnn");
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, 0x202, "00EE returns past the call");
+    assert_eq!(fixture.cpu.stack_pointer, 0, "00EE");
+}
+
+#[test]
+fn test_timer_cases() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xF015, 0xF118, 0xF207]);
+    fixture.cpu.registers.v[0] = 0x20;
+    fixture.cpu.registers.v[1] = 0x30;
+    for _ in 0..3 {
+        fixture.cpu.execute_next_op_code().unwrap();
+    }
+    assert_eq!(fixture.cpu.registers.delay_timer, 0x20, "Fx15");
+    assert_eq!(fixture.cpu.registers.sound_timer, 0x30, "Fx18");
+    assert_eq!(fixture.cpu.registers.v[2], 0x20, "Fx07");
+
+    fixture.cpu.tick_timers();
+    assert_eq!(fixture.cpu.registers.delay_timer, 0x1F);
+    assert_eq!(fixture.cpu.registers.sound_timer, 0x2F);
+}
+
+#[test]
+fn test_draw_cases() {
+    // Drawing the same sprite twice erases it and reports the collision, with VF as a coordinate
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xDEF1, 0xDEF1]);
+    fixture.cpu.registers.i = 0x600;
+    fixture.cpu.memory[0x600] = 0xC0;
+    fixture.cpu.registers.v[0xE] = 2;
+    fixture.cpu.registers.v[0xF] = 3;
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert!(fixture.cpu.video_memory[3][2] && fixture.cpu.video_memory[3][3], "Dxyn draws");
+    assert_eq!(fixture.cpu.registers.v[0xF], 0, "Dxyn without collision");
+
+    fixture.cpu.registers.v[0xF] = 3;
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert!(!fixture.cpu.video_memory[3][2] && !fixture.cpu.video_memory[3][3], "Dxyn erases");
+    assert_eq!(fixture.cpu.registers.v[0xF], 1, "Dxyn with collision");
+
+    let mut fixture = run(0x00E0, &[]);
+    fixture.cpu.video_memory[5][5] = true;
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert!(fixture.cpu.video_memory.iter().all(|row| row.iter().all(|&pixel| !pixel)), "00E0");
+    assert_eq!(fixture.cpu.program_counter, 0x200 + PC_STEP);
+}
@@ -1,7 +1,11 @@
+#![allow(dead_code)]
+
 extern crate chip8_emulator;
 
 use chip8_emulator::cpu::*;
 use chip8_emulator::keypad::*;
+use chip8_emulator::quirks::*;
+use chip8_emulator::variant::*;
 
 pub struct CpuFixture {
     pub cpu: Cpu,
@@ -9,8 +13,16 @@ pub struct CpuFixture {
 
 impl CpuFixture {
     pub fn new() -> CpuFixture {
+        CpuFixture::with_quirks(Quirks::default())
+    }
+
+    pub fn with_quirks(quirks: Quirks) -> CpuFixture {
+        CpuFixture::with_variant(Variant::Chip8, quirks)
+    }
+
+    pub fn with_variant(variant: Variant, quirks: Quirks) -> CpuFixture {
         let keypad = Keypad::new();
-        let cpu = Cpu::new(keypad);
+        let cpu = Cpu::new(keypad, variant, quirks);
         CpuFixture {cpu}
     }
 
@@ -2,6 +2,12 @@ extern crate chip8_emulator;
 
 mod cpu_fixture;
 use chip8_emulator::cpu::*;
+use chip8_emulator::error::*;
+use chip8_emulator::instruction::*;
+use chip8_emulator::keypad::*;
+use chip8_emulator::quirks::*;
+use chip8_emulator::random::*;
+use chip8_emulator::variant::*;
 use cpu_fixture::CpuFixture;
 
 #[test]
@@ -12,7 +18,7 @@ fn test_opcode_00e0() {
     fixture.cpu.video_memory[10][2] = true;
 
     fixture.load_op_codes(&[0x00E0]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.video_memory[5][5], false);
     assert_eq!(fixture.cpu.video_memory[10][2], false);
@@ -25,7 +31,7 @@ fn test_opcode_00ee() {
     fixture.cpu.stack[0] = 0x123;
 
     fixture.load_op_codes(&[0x00EE]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.stack_pointer, 0);
     assert_eq!(fixture.cpu.program_counter, 0x123);
@@ -35,7 +41,7 @@ fn test_opcode_00ee() {
 fn test_opcode_1nnn() {
     let mut fixture = CpuFixture::new();
     fixture.load_op_codes(&[0x1123]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.program_counter, 0x123);
 }
@@ -47,7 +53,7 @@ fn test_opcode_2nnn() {
     let current_pc = fixture.cpu.program_counter;
 
     fixture.load_op_codes(&[0x2100]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.stack_pointer, current_sp + 1);
     assert_eq!(fixture.cpu.stack[current_sp], current_pc + PC_STEP);
@@ -62,10 +68,10 @@ fn test_opcode_3xkk() {
 
     fixture.load_op_codes(&[0x3A22, 0x3A44]);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + 6);
 }
 
@@ -77,10 +83,10 @@ fn test_opcode_4xkk() {
 
     fixture.load_op_codes(&[0x4A44, 0x4A22]);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + 6);
 }
 
@@ -94,10 +100,10 @@ fn test_opcode_5xy0() {
 
     fixture.load_op_codes(&[0x5AB0, 0x5AC0]);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP * 3);
 }
 
@@ -106,7 +112,7 @@ fn test_opcode_6xkk() {
     let mut fixture = CpuFixture::new();
 
     fixture.load_op_codes(&[0x6A22]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xA], 0x22);
 }
@@ -117,7 +123,7 @@ fn test_opcode_7xkk() {
     fixture.cpu.registers.v[0xA] = 0x44;
 
     fixture.load_op_codes(&[0x7A44]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xA], 0x88);
 }
@@ -129,7 +135,7 @@ fn test_opcode_8xy0() {
     fixture.cpu.registers.v[0xB] = 0x22;
 
     fixture.load_op_codes(&[0x8AB0]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xA], 0x22);
 }
@@ -141,7 +147,7 @@ fn test_opcode_8xy1() {
     fixture.cpu.registers.v[0xB] = 0x22;
 
     fixture.load_op_codes(&[0x8AB1]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xA], 0x44 | 0x22);
 }
@@ -153,7 +159,7 @@ fn test_opcode_8xy2() {
     fixture.cpu.registers.v[0xB] = 0x22;
 
     fixture.load_op_codes(&[0x8AB2]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xA], 0x44 & 0x22);
 }
@@ -165,7 +171,7 @@ fn test_opcode_8xy3() {
     fixture.cpu.registers.v[0xB] = 0x22;
 
     fixture.load_op_codes(&[0x8AB3]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xA], 0x44 ^ 0x22);
 }
@@ -182,12 +188,12 @@ fn test_opcode_8xy4() {
     let ab_addition_result: u16 = fixture.cpu.registers.v[0xA] as u16 + fixture.cpu.registers.v[0xB] as u16;
     let ab_addition_result_lowest_8bits: u16 = ab_addition_result & 0xFF;
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.registers.v[0xA] as u16, ab_addition_result_lowest_8bits);
     assert_eq!(fixture.cpu.registers.v[0xF], 1);
 
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.registers.v[0xC], 0x10 + 0x20);
     assert_eq!(fixture.cpu.registers.v[0xF], 0);
 }
@@ -199,16 +205,16 @@ fn test_opcode_8xy5() {
     fixture.cpu.registers.v[0xB] = 0x64;
     fixture.load_op_codes(&[0x8AB5, 0x8BA5]);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.registers.v[0xF], 1);
     assert_eq!(fixture.cpu.registers.v[0xA], 155);
 
     fixture.cpu.registers.v[0xA] = 0xFF;
     fixture.cpu.registers.v[0xB] = 0x64;
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.registers.v[0xF], 0);
-    assert_eq!(fixture.cpu.registers.v[0xB], 0);
+    assert_eq!(fixture.cpu.registers.v[0xB], 0x65);
 }
 
 #[test]
@@ -217,13 +223,13 @@ fn test_opcode_8xy6() {
     fixture.cpu.registers.v[0xA] = 0x65;
     fixture.load_op_codes(&[0x8AB6, 0x8AB6]);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.registers.v[0xF], 1);
     assert_eq!(fixture.cpu.registers.v[0xA], 0x65 >> 1);
 
     fixture.cpu.registers.v[0xA] = 0x64;
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.registers.v[0xF], 0);
     assert_eq!(fixture.cpu.registers.v[0xA], 0x64 >> 1);
 }
@@ -235,17 +241,17 @@ fn test_opcode_8xy7() {
     fixture.cpu.registers.v[0xB] = 0x64;
     fixture.load_op_codes(&[0x8BA7, 0x8AB7]);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xF], 1);
     assert_eq!(fixture.cpu.registers.v[0xB], 155);
 
     fixture.cpu.registers.v[0xA] = 0xFF;
     fixture.cpu.registers.v[0xB] = 0x64;
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xF], 0);
-    assert_eq!(fixture.cpu.registers.v[0xA], 0);
+    assert_eq!(fixture.cpu.registers.v[0xA], 0x65);
 }
 
 #[test]
@@ -254,13 +260,13 @@ fn test_opcode_8xye() {
     fixture.cpu.registers.v[0xA] = 0xFF;
 
     fixture.load_op_codes(&[0x8ABE, 0x8ABE]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xF], 1);
     assert_eq!(fixture.cpu.registers.v[0xA], 0xFF << 1);
     fixture.cpu.registers.v[0xA] = 0x64;
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.registers.v[0xF], 0);
     assert_eq!(fixture.cpu.registers.v[0xA], 0x64 << 1);
 }
@@ -274,10 +280,10 @@ fn test_opcode_9xy0() {
     fixture.cpu.registers.v[0xB] = 0x22;
     fixture.cpu.registers.v[0xC] = 0x44;
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter,  initial_pc + (PC_STEP * 3));
 }
 
@@ -286,7 +292,7 @@ fn test_opcode_annn() {
     let mut fixture = CpuFixture::new();
 
     fixture.load_op_codes(&[0xA123]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.i, 0x123);
 }
@@ -297,7 +303,7 @@ fn test_opcode_bnnn() {
 
     fixture.cpu.registers.v[0] = 5;
     fixture.load_op_codes(&[0xB123]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.program_counter, 0x123 + (fixture.cpu.registers.v[0] as u16));
 }
@@ -306,13 +312,12 @@ fn test_opcode_bnnn() {
 fn test_opcode_cxkk() {
     let mut fixture = CpuFixture::new();
 
-    fixture.load_op_codes(&[0xC000]);
-    fixture.cpu.execute_next_op_code();
+    fixture.load_op_codes(&[0xC000, 0xC0AA]);
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0], 0);
 
-    fixture.load_op_codes(&[0xC0AA]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0], fixture.cpu.registers.v[0] & 0xAA);
 }
@@ -328,7 +333,7 @@ fn test_opcode_dxyn_x() {
 
 
     fixture.cpu.memory[0x600] = 0b10101010;
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xF], 0);
     assert_eq!(fixture.cpu.video_memory[10][5], true);
@@ -336,7 +341,7 @@ fn test_opcode_dxyn_x() {
     assert_eq!(fixture.cpu.video_memory[10][7], true);
 
     fixture.cpu.memory[0x600] = 0b01111111;
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0xF], 1);
     assert_eq!(fixture.cpu.video_memory[10][5], true);
@@ -346,7 +351,7 @@ fn test_opcode_dxyn_x() {
     fixture.cpu.registers.v[1] = 62;
     fixture.cpu.registers.v[2] = 20;
     fixture.cpu.memory[0x600] = 0b10101010;
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.video_memory[20][62], true);
     assert_eq!(fixture.cpu.video_memory[20][63], false);
@@ -372,7 +377,7 @@ fn test_opcode_dxyn_y() {
     fixture.cpu.memory[0x602] = 0b10101010;
     fixture.cpu.memory[0x603] = 0b10101010;
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.video_memory[10][5], true);
     assert_eq!(fixture.cpu.video_memory[11][5], false);
@@ -387,12 +392,12 @@ fn test_opcode_ex9e() {
     let initial_pc = fixture.cpu.program_counter;
     fixture.load_op_codes(&[0xEA9E, 0xEA9E]);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
 
     fixture.cpu.keypad.press_key(0x5);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + (PC_STEP * 3));
 }
 
@@ -404,12 +409,12 @@ fn test_opcode_exa1() {
     fixture.load_op_codes(&[0xEAA1, 0xEAA1]);
 
     fixture.cpu.keypad.press_key(0x5);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
     fixture.cpu.keypad.release_key(0x5);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + (PC_STEP * 3));
 }
 
@@ -419,7 +424,7 @@ fn test_opcode_fx07() {
     fixture.cpu.registers.delay_timer = 0xFF;
 
     fixture.load_op_codes(&[0xF107]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[1], fixture.cpu.registers.delay_timer);
 }
@@ -430,15 +435,82 @@ fn test_opcode_fx0a() {
     let initial_pc = fixture.cpu.program_counter;
     fixture.load_op_codes(&[0xF50A, 0x00E0, 0x00E0]);
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
 
     fixture.cpu.keypad.press_key(0xA);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.keypad.press_key(0x3);
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
+
+    // Only the lowest key pressed first counts, and only once released
+    fixture.cpu.keypad.release_key(0xA);
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
+
+    fixture.cpu.keypad.release_key(0x3);
+    fixture.cpu.execute_next_op_code().unwrap();
     assert_eq!(fixture.cpu.program_counter, initial_pc + (PC_STEP * 2));
 
+    assert_eq!(fixture.cpu.registers.v[0x5], 0x3);
+}
+
+#[test]
+fn test_opcode_fx0a_ignores_held_key() {
+    let mut fixture = CpuFixture::new();
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0xF50A, 0x00E0]);
+
+    // Held since an earlier frame, so it has to be released and pressed again
+    fixture.cpu.keypad.press_key(0x7);
+    fixture.cpu.keypad.clear_edges();
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.keypad.release_key(0x7);
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
+
+    fixture.cpu.keypad.press_key(0x7);
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.keypad.release_key(0x7);
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, initial_pc + (PC_STEP * 2));
+    assert_eq!(fixture.cpu.registers.v[0x5], 0x7);
+}
+
+#[test]
+fn test_quirk_key_wait_on_press() {
+    let mut fixture = CpuFixture::with_quirks(Quirks { key_wait_on_press: true, ..Quirks::default() });
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0xF50A, 0x00E0, 0x00E0]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.keypad.press_key(0xA);
+    fixture.cpu.keypad.press_key(0x3);
+    fixture.cpu.execute_next_op_code().unwrap();
 
-    assert_eq!(fixture.cpu.registers.v[0x5], 0xA);
+    assert_eq!(fixture.cpu.program_counter, initial_pc + (PC_STEP * 2));
+    assert_eq!(fixture.cpu.registers.v[0x5], 0x3);
+}
+
+#[test]
+fn test_keypad_edges() {
+    let mut keypad = Keypad::new();
+    keypad.press_key(0x2);
+    keypad.press_key(0x9);
+    keypad.release_key(0x9);
+    keypad.release_key(0x4);
+
+    assert!(keypad.key_was_just_pressed(0x2));
+    assert!(keypad.key_was_just_pressed(0x9));
+    assert!(keypad.key_was_just_released(0x9));
+    assert!(!keypad.key_was_just_released(0x4));
+    assert_eq!(keypad.get_first_just_pressed_key(), Some(0x2));
+
+    keypad.clear_edges();
+
+    assert!(keypad.key_is_pressed(0x2));
+    assert!(!keypad.key_was_just_pressed(0x2));
+    assert_eq!(keypad.get_first_just_pressed_key(), None);
 }
 
 #[test]
@@ -447,7 +519,7 @@ fn test_opcode_fx15() {
     fixture.cpu.registers.v[1] = 0xFF;
 
     fixture.load_op_codes(&[0xF115]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.delay_timer, 0xFF);
 }
@@ -458,7 +530,7 @@ fn test_opcode_fx18() {
     fixture.cpu.registers.v[1] = 0xFF;
 
     fixture.load_op_codes(&[0xF118]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.sound_timer, 0xFF);
 }
@@ -470,7 +542,7 @@ fn test_opcode_fx1e() {
     fixture.cpu.registers.v[1] = 0xAA;
 
     fixture.load_op_codes(&[0xF11E]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.i, 0x15 + 0xAA);
 }
@@ -482,7 +554,7 @@ fn test_opcode_fx33() {
     fixture.cpu.registers.v[5] = 239;
 
     fixture.load_op_codes(&[0xF533]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.memory[0x602], 2);
     assert_eq!(fixture.cpu.memory[0x603], 3);
@@ -498,7 +570,7 @@ fn test_opcode_fx55() {
     fixture.cpu.registers.v[2] = 0xA3;
 
     fixture.load_op_codes(&[0xFF55]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.memory[0x605], 0xA1);
     assert_eq!(fixture.cpu.memory[0x606], 0xA2);
@@ -515,7 +587,7 @@ fn test_opcode_fx65() {
     fixture.cpu.memory[0x606] = 0xA2;
     fixture.cpu.memory[0x607] = 0xA3;
 
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.v[0], 0xA1);
     assert_eq!(fixture.cpu.registers.v[1], 0xA2);
@@ -529,7 +601,7 @@ fn test_opcode_fx29() {
     fixture.cpu.registers.v[0x1] = 0xA;
 
     fixture.load_op_codes(&[0xF129]);
-    fixture.cpu.execute_next_op_code();
+    fixture.cpu.execute_next_op_code().unwrap();
 
     assert_eq!(fixture.cpu.registers.i, 50);
 }
@@ -545,4 +617,610 @@ fn test_load_program_with_overflow() {
 
     fixture.cpu.load_program(&program);
     assert_eq!(*fixture.cpu.memory.last().unwrap(), 8);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_quirk_shift_uses_vy() {
+    let mut fixture = CpuFixture::with_quirks(Quirks { shift_uses_vy: true, ..Quirks::default() });
+    fixture.cpu.registers.v[0xA] = 0x00;
+    fixture.cpu.registers.v[0xB] = 0x65;
+    fixture.load_op_codes(&[0x8AB6, 0x8ABE]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.registers.v[0xF], 1);
+    assert_eq!(fixture.cpu.registers.v[0xA], 0x65 >> 1);
+    assert_eq!(fixture.cpu.registers.v[0xB], 0x65);
+
+    fixture.cpu.registers.v[0xB] = 0x81;
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.registers.v[0xF], 1);
+    assert_eq!(fixture.cpu.registers.v[0xA], 0x02);
+    assert_eq!(fixture.cpu.registers.v[0xB], 0x81);
+}
+
+#[test]
+fn test_quirk_load_store_increments_i() {
+    let mut fixture = CpuFixture::with_quirks(Quirks { load_store_increments_i: true, ..Quirks::default() });
+    fixture.cpu.registers.i = 0x605;
+    fixture.cpu.registers.v[0] = 0xA1;
+    fixture.cpu.registers.v[1] = 0xA2;
+    fixture.cpu.registers.v[2] = 0xA3;
+    fixture.load_op_codes(&[0xF255, 0xF165]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.memory[0x607], 0xA3);
+    assert_eq!(fixture.cpu.registers.i, 0x608);
+
+    fixture.cpu.registers.i = 0x605;
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.registers.i, 0x607);
+}
+
+#[test]
+fn test_quirk_load_store_leaves_i() {
+    let mut fixture = CpuFixture::new();
+    fixture.cpu.registers.i = 0x605;
+    fixture.load_op_codes(&[0xF255, 0xF265]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.registers.i, 0x605);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.registers.i, 0x605);
+}
+
+#[test]
+fn test_quirk_jump_uses_vx() {
+    let mut fixture = CpuFixture::with_quirks(Quirks { jump_uses_vx: true, ..Quirks::default() });
+    fixture.cpu.registers.v[0] = 5;
+    fixture.cpu.registers.v[1] = 7;
+    fixture.load_op_codes(&[0xB123]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, 0x123 + 7);
+}
+
+#[test]
+fn test_quirk_clip_sprites() {
+    let mut fixture = CpuFixture::with_quirks(Quirks { clip_sprites: true, ..Quirks::default() });
+    fixture.cpu.registers.i = 0x600;
+    fixture.cpu.registers.v[1] = 62;
+    fixture.cpu.registers.v[2] = 31;
+    fixture.load_op_codes(&[0xD122]);
+    fixture.cpu.memory[0x600] = 0xFF;
+    fixture.cpu.memory[0x601] = 0xFF;
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.video_memory[31][62], true);
+    assert_eq!(fixture.cpu.video_memory[31][63], true);
+    assert_eq!(fixture.cpu.video_memory[31][0], false);
+    assert_eq!(fixture.cpu.video_memory[0][62], false);
+    assert_eq!(fixture.cpu.video_memory[0][0], false);
+}
+
+#[test]
+fn test_quirk_clip_sprites_wraps_origin() {
+    let mut fixture = CpuFixture::with_quirks(Quirks { clip_sprites: true, ..Quirks::default() });
+    fixture.cpu.registers.i = 0x600;
+    fixture.cpu.registers.v[1] = 64 + 3;
+    fixture.cpu.registers.v[2] = 32 + 4;
+    fixture.load_op_codes(&[0xD121]);
+    fixture.cpu.memory[0x600] = 0x80;
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.video_memory[4][3], true);
+}
+
+#[test]
+fn test_quirk_logic_resets_vf() {
+    let mut fixture = CpuFixture::with_quirks(Quirks { logic_resets_vf: true, ..Quirks::default() });
+    fixture.load_op_codes(&[0x8AB1, 0x8AB2, 0x8AB3]);
+
+    for _ in 0..3 {
+        fixture.cpu.registers.v[0xF] = 0x12;
+        fixture.cpu.execute_next_op_code().unwrap();
+        assert_eq!(fixture.cpu.registers.v[0xF], 0);
+    }
+}
+
+#[test]
+fn test_quirk_presets() {
+    assert_eq!(Quirks::default(), Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        jump_uses_vx: false,
+        clip_sprites: false,
+        logic_resets_vf: false,
+        key_wait_on_press: false,
+    });
+    assert!(Quirks::cosmac_vip().shift_uses_vy);
+    assert!(Quirks::cosmac_vip().logic_resets_vf);
+    assert!(Quirks::schip().jump_uses_vx);
+    assert!(!Quirks::schip().load_store_increments_i);
+    assert!(Quirks::xo_chip().load_store_increments_i);
+    assert!(!Quirks::xo_chip().clip_sprites);
+}
+
+fn schip_fixture() -> CpuFixture {
+    CpuFixture::with_variant(Variant::SuperChip, Quirks::schip())
+}
+
+#[test]
+fn test_opcode_00fe_00ff() {
+    let mut fixture = schip_fixture();
+    fixture.load_op_codes(&[0x00FF, 0x00FE]);
+    assert_eq!(fixture.cpu.display_width(), 64);
+    assert_eq!(fixture.cpu.display_height(), 32);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert!(fixture.cpu.is_hires());
+    assert_eq!(fixture.cpu.display_width(), 128);
+    assert_eq!(fixture.cpu.display_height(), 64);
+    assert_eq!(fixture.cpu.video_memory.len(), 64);
+    assert_eq!(fixture.cpu.video_memory[0].len(), 128);
+
+    fixture.cpu.video_memory[63][127] = true;
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert!(!fixture.cpu.is_hires());
+    assert_eq!(fixture.cpu.video_memory.len(), 32);
+    assert_eq!(fixture.cpu.video_memory[0].len(), 64);
+    assert!(fixture.cpu.video_memory.iter().all(|row| row.iter().all(|pixel| !pixel)));
+}
+
+#[test]
+fn test_opcode_00fe_00ff_ignored_on_chip8() {
+    let mut fixture = CpuFixture::new();
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0x00FF]);
+
+    let result = fixture.cpu.execute_next_op_code();
+    assert_eq!(result, Err(CpuError::UnknownOpcode { pc: initial_pc, opcode: 0x00FF }));
+    assert!(!fixture.cpu.is_hires());
+    assert_eq!(fixture.cpu.program_counter, initial_pc);
+}
+
+#[test]
+fn test_opcode_00cn() {
+    let mut fixture = schip_fixture();
+    fixture.cpu.video_memory[0][5] = true;
+    fixture.cpu.video_memory[30][5] = true;
+    fixture.load_op_codes(&[0x00C3]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.video_memory[0][5], false);
+    assert_eq!(fixture.cpu.video_memory[3][5], true);
+    assert_eq!(fixture.cpu.video_memory[30][5], false);
+    assert_eq!(fixture.cpu.video_memory[31][5], false);
+}
+
+#[test]
+fn test_opcode_00fb() {
+    let mut fixture = schip_fixture();
+    fixture.cpu.video_memory[2][0] = true;
+    fixture.cpu.video_memory[2][62] = true;
+    fixture.load_op_codes(&[0x00FB]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.video_memory[2][0], false);
+    assert_eq!(fixture.cpu.video_memory[2][2], false);
+    assert_eq!(fixture.cpu.video_memory[2][4], true);
+    assert_eq!(fixture.cpu.video_memory[2][62], false);
+}
+
+#[test]
+fn test_opcode_00fc() {
+    let mut fixture = schip_fixture();
+    fixture.cpu.video_memory[2][1] = true;
+    fixture.cpu.video_memory[2][10] = true;
+    fixture.load_op_codes(&[0x00FC]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.video_memory[2][6], true);
+    assert_eq!(fixture.cpu.video_memory[2][10], false);
+    assert_eq!(fixture.cpu.video_memory[2][61], false);
+    assert_eq!(fixture.cpu.video_memory[2][1], false);
+}
+
+#[test]
+fn test_opcode_00fd() {
+    let mut fixture = schip_fixture();
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0x00FD, 0x6A22]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert!(fixture.cpu.has_exited());
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.program_counter, initial_pc);
+    assert_eq!(fixture.cpu.registers.v[0xA], 0);
+
+    fixture.cpu.reset();
+    assert!(!fixture.cpu.has_exited());
+}
+
+#[test]
+fn test_opcode_dxy0() {
+    let mut fixture = schip_fixture();
+    fixture.cpu.registers.i = 0x600;
+    fixture.cpu.registers.v[1] = 10;
+    fixture.cpu.registers.v[2] = 4;
+    fixture.load_op_codes(&[0x00FF, 0xD120]);
+    for row in 0..16 {
+        fixture.cpu.memory[0x600 + row * 2] = 0b10000000;
+        fixture.cpu.memory[0x600 + row * 2 + 1] = 0b00000001;
+    }
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.registers.v[0xF], 0);
+    assert_eq!(fixture.cpu.video_memory[4][10], true);
+    assert_eq!(fixture.cpu.video_memory[4][25], true);
+    assert_eq!(fixture.cpu.video_memory[19][10], true);
+    assert_eq!(fixture.cpu.video_memory[19][25], true);
+    assert_eq!(fixture.cpu.video_memory[19][11], false);
+    assert_eq!(fixture.cpu.video_memory[20][10], false);
+}
+
+#[test]
+fn test_opcode_fx30() {
+    let mut fixture = schip_fixture();
+    fixture.cpu.registers.v[3] = 0x2;
+    fixture.load_op_codes(&[0xF330]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    let i = fixture.cpu.registers.i as usize;
+    assert_eq!(&fixture.cpu.memory[i..i + 10], &[0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF]);
+}
+
+#[test]
+fn test_opcode_fx75_fx85() {
+    let mut fixture = schip_fixture();
+    fixture.cpu.registers.v[0] = 0xA1;
+    fixture.cpu.registers.v[1] = 0xA2;
+    fixture.cpu.registers.v[2] = 0xA3;
+    fixture.load_op_codes(&[0xF175]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.rpl_flags[0], 0xA1);
+    assert_eq!(fixture.cpu.rpl_flags[1], 0xA2);
+    assert_eq!(fixture.cpu.rpl_flags[2], 0);
+
+    fixture.cpu.reset();
+    fixture.load_op_codes(&[0xF285]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.registers.v[0], 0xA1);
+    assert_eq!(fixture.cpu.registers.v[1], 0xA2);
+    assert_eq!(fixture.cpu.registers.v[2], 0);
+}
+
+fn xo_chip_fixture() -> CpuFixture {
+    CpuFixture::with_variant(Variant::XoChip, Quirks::xo_chip())
+}
+
+#[test]
+fn test_xo_chip_memory_size() {
+    let fixture = xo_chip_fixture();
+    assert_eq!(fixture.cpu.memory.len(), 0x10000);
+    assert_eq!(CpuFixture::new().cpu.memory.len(), 0x1000);
+}
+
+#[test]
+fn test_opcode_f000_nnnn() {
+    let mut fixture = xo_chip_fixture();
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0xF000, 0xABCD]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.registers.i, 0xABCD);
+    assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP * 2);
+}
+
+#[test]
+fn test_skip_over_f000_nnnn() {
+    let mut fixture = xo_chip_fixture();
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0x3000, 0xF000, 0xABCD]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP * 3);
+}
+
+#[test]
+fn test_opcode_5xy2() {
+    let mut fixture = xo_chip_fixture();
+    fixture.cpu.registers.i = 0x605;
+    fixture.cpu.registers.v[2] = 0xA2;
+    fixture.cpu.registers.v[3] = 0xA3;
+    fixture.cpu.registers.v[4] = 0xA4;
+    fixture.load_op_codes(&[0x5242, 0x5422]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(&fixture.cpu.memory[0x605..0x608], &[0xA2, 0xA3, 0xA4]);
+    assert_eq!(fixture.cpu.registers.i, 0x605);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(&fixture.cpu.memory[0x605..0x608], &[0xA4, 0xA3, 0xA2]);
+}
+
+#[test]
+fn test_opcode_5xy3() {
+    let mut fixture = xo_chip_fixture();
+    fixture.cpu.registers.i = 0x605;
+    fixture.load_op_codes(&[0x5133]);
+    fixture.cpu.memory[0x605] = 0xA1;
+    fixture.cpu.memory[0x606] = 0xA2;
+    fixture.cpu.memory[0x607] = 0xA3;
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.registers.v[0], 0);
+    assert_eq!(fixture.cpu.registers.v[1], 0xA1);
+    assert_eq!(fixture.cpu.registers.v[2], 0xA2);
+    assert_eq!(fixture.cpu.registers.v[3], 0xA3);
+    assert_eq!(fixture.cpu.registers.i, 0x605);
+}
+
+#[test]
+fn test_opcode_fn01() {
+    let mut fixture = xo_chip_fixture();
+    fixture.cpu.registers.i = 0x600;
+    fixture.cpu.registers.v[1] = 4;
+    fixture.cpu.registers.v[2] = 6;
+    fixture.load_op_codes(&[0xF301, 0xD121, 0xF201, 0x00E0]);
+    fixture.cpu.memory[0x600] = 0b10000000;
+    fixture.cpu.memory[0x601] = 0b01000000;
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.selected_planes(), 3);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.video_memory[6][4], true);
+    assert_eq!(fixture.cpu.video_memory_plane2[6][4], false);
+    assert_eq!(fixture.cpu.video_memory_plane2[6][5], true);
+    assert_eq!(fixture.cpu.pixel(4, 6), 1);
+    assert_eq!(fixture.cpu.pixel(5, 6), 2);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert_eq!(fixture.cpu.video_memory[6][4], true);
+    assert_eq!(fixture.cpu.video_memory_plane2[6][5], false);
+}
+
+#[test]
+fn test_opcode_f002() {
+    let mut fixture = xo_chip_fixture();
+    fixture.cpu.registers.i = 0x600;
+    fixture.load_op_codes(&[0xF002]);
+    for i in 0..16 {
+        fixture.cpu.memory[0x600 + i] = i as u8;
+    }
+    assert_eq!(fixture.cpu.audio_pattern(), None);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.audio_pattern(), Some([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]));
+}
+
+#[test]
+fn test_opcode_fx3a() {
+    let mut fixture = xo_chip_fixture();
+    fixture.cpu.registers.v[4] = 112;
+    fixture.load_op_codes(&[0xF43A]);
+    assert_eq!(fixture.cpu.audio_playback_rate(), 4000.0);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.audio_pitch(), 112);
+    assert_eq!(fixture.cpu.audio_playback_rate(), 8000.0);
+}
+
+#[test]
+fn test_unknown_opcode() {
+    let mut fixture = CpuFixture::new();
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0x6A22, 0x5AB1]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    let result = fixture.cpu.execute_next_op_code();
+
+    assert_eq!(result, Err(CpuError::UnknownOpcode { pc: initial_pc + PC_STEP, opcode: 0x5AB1 }));
+    assert_eq!(fixture.cpu.program_counter, initial_pc + PC_STEP);
+}
+
+#[test]
+fn test_decode_op_code() {
+    let fixture = CpuFixture::new();
+
+    assert_eq!(fixture.cpu.decode_op_code(0xD125), Ok(Instruction::Draw(0x1, 0x2, 5)));
+    assert_eq!(fixture.cpu.decode_op_code(0x8AB4), Ok(Instruction::Add(0xA, 0xB)));
+}
+
+#[test]
+fn test_decode_op_code_rejects_unsupported_variant_instructions() {
+    let chip8 = CpuFixture::new();
+    let schip = schip_fixture();
+    let pc = chip8.cpu.program_counter;
+
+    assert_eq!(chip8.cpu.decode_op_code(0x00FF), Err(CpuError::UnknownOpcode { pc: pc, opcode: 0x00FF }));
+    assert_eq!(schip.cpu.decode_op_code(0x00FF), Ok(Instruction::HighRes));
+    assert_eq!(schip.cpu.decode_op_code(0xF201), Err(CpuError::UnknownOpcode { pc: pc, opcode: 0xF201 }));
+    assert_eq!(chip8.cpu.decode_op_code(0x0123), Err(CpuError::UnknownOpcode { pc: pc, opcode: 0x0123 }));
+}
+
+#[test]
+fn test_stack_overflow() {
+    let mut fixture = CpuFixture::new();
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0x2200]);
+
+    for _ in 0..16 {
+        fixture.cpu.execute_next_op_code().unwrap();
+    }
+    let result = fixture.cpu.execute_next_op_code();
+
+    assert_eq!(result, Err(CpuError::StackOverflow { pc: initial_pc }));
+    assert_eq!(fixture.cpu.stack_pointer, 16);
+}
+
+#[test]
+fn test_stack_underflow() {
+    let mut fixture = CpuFixture::new();
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0x00EE]);
+
+    let result = fixture.cpu.execute_next_op_code();
+
+    assert_eq!(result, Err(CpuError::StackUnderflow { pc: initial_pc }));
+    assert_eq!(fixture.cpu.stack_pointer, 0);
+}
+
+#[test]
+fn test_memory_out_of_bounds() {
+    let mut fixture = CpuFixture::new();
+    let initial_pc = fixture.cpu.program_counter;
+    fixture.load_op_codes(&[0xD015, 0xF033, 0xF255]);
+
+    fixture.cpu.registers.i = 0xFFE;
+    let result = fixture.cpu.execute_next_op_code();
+    assert_eq!(result, Err(CpuError::MemoryOutOfBounds { pc: initial_pc, addr: 0x1000 }));
+
+    fixture.cpu.program_counter += PC_STEP;
+    let result = fixture.cpu.execute_next_op_code();
+    assert_eq!(result, Err(CpuError::MemoryOutOfBounds { pc: initial_pc + PC_STEP, addr: 0x1000 }));
+
+    fixture.cpu.program_counter += PC_STEP;
+    let result = fixture.cpu.execute_next_op_code();
+    assert_eq!(result, Err(CpuError::MemoryOutOfBounds { pc: initial_pc + PC_STEP * 2, addr: 0x1000 }));
+}
+
+#[test]
+fn test_program_counter_out_of_bounds() {
+    let mut fixture = CpuFixture::new();
+    fixture.cpu.program_counter = 0xFFF;
+
+    let result = fixture.cpu.execute_next_op_code();
+
+    assert_eq!(result, Err(CpuError::MemoryOutOfBounds { pc: 0xFFF, addr: 0x1000 }));
+}
+
+#[test]
+fn test_execute_cycle_returns_error() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0x6A22, 0x00EE]);
+
+    let result = fixture.cpu.execute_cycle();
+
+    assert_eq!(result, Err(CpuError::StackUnderflow { pc: 0x202 }));
+    assert_eq!(fixture.cpu.registers.v[0xA], 0x22);
+}
+
+#[test]
+fn test_timers_tick_once_per_cycle() {
+    let mut fixture = CpuFixture::new();
+    fixture.cpu.registers.delay_timer = 5;
+    fixture.cpu.registers.sound_timer = 3;
+    fixture.load_op_codes(&[0x1200]);
+
+    fixture.cpu.execute_cycle().unwrap();
+    assert_eq!(fixture.cpu.registers.delay_timer, 4);
+    assert_eq!(fixture.cpu.registers.sound_timer, 2);
+
+    for _ in 0..5 {
+        fixture.cpu.execute_cycle().unwrap();
+    }
+    assert_eq!(fixture.cpu.registers.delay_timer, 0);
+    assert_eq!(fixture.cpu.registers.sound_timer, 0);
+}
+
+#[test]
+fn test_is_sound_active() {
+    let mut fixture = CpuFixture::new();
+    fixture.cpu.registers.v[1] = 2;
+    fixture.load_op_codes(&[0xF118, 0x1202]);
+    assert!(!fixture.cpu.is_sound_active());
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert!(fixture.cpu.is_sound_active());
+
+    fixture.cpu.execute_cycle().unwrap();
+    assert!(fixture.cpu.is_sound_active());
+
+    fixture.cpu.execute_cycle().unwrap();
+    assert!(!fixture.cpu.is_sound_active());
+}
+
+#[test]
+fn test_poll_audio_event() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0x1200]);
+    assert_eq!(fixture.cpu.poll_audio_event(), None);
+
+    fixture.cpu.registers.sound_timer = 1;
+    assert_eq!(fixture.cpu.poll_audio_event(), Some(AudioEvent::Started));
+    assert_eq!(fixture.cpu.poll_audio_event(), None);
+
+    fixture.cpu.execute_cycle().unwrap();
+    assert_eq!(fixture.cpu.poll_audio_event(), Some(AudioEvent::Stopped));
+    assert_eq!(fixture.cpu.poll_audio_event(), None);
+}
+
+struct FixedRandom(u8);
+
+impl RandomSource for FixedRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.0
+    }
+}
+
+#[test]
+fn test_opcode_cxkk_uses_random_source() {
+    let mut fixture = CpuFixture::new();
+    fixture.cpu.set_random_source(Box::new(FixedRandom(0b1011_0110)));
+    fixture.load_op_codes(&[0xC30F, 0xC4F0]);
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(fixture.cpu.registers.v[3], 0b0000_0110);
+    assert_eq!(fixture.cpu.registers.v[4], 0b1011_0000);
+}
+
+#[test]
+fn test_opcode_cxkk_is_reproducible() {
+    let run = |seed: u64| {
+        let mut fixture = CpuFixture::new();
+        fixture.cpu.set_random_source(Box::new(SeededRandom::new(seed)));
+        fixture.load_op_codes(&[0xC0FF, 0xC1FF, 0xC2FF, 0xC3FF]);
+        for _ in 0..4 {
+            fixture.cpu.execute_next_op_code().unwrap();
+        }
+        fixture.cpu.registers.v
+    };
+
+    assert_eq!(run(42), run(42));
+    assert_ne!(run(42), run(43));
+}
+
+#[test]
+fn test_seeded_random_default_and_zero_seed() {
+    let mut default = SeededRandom::default();
+    let mut zero = SeededRandom::new(0);
+
+    assert_eq!(default.state(), Some(DEFAULT_SEED));
+    let bytes: Vec<u8> = (0..8).map(|_| default.next_byte()).collect();
+    assert_eq!(bytes, (0..8).map(|_| zero.next_byte()).collect::<Vec<u8>>());
+    assert!(bytes.iter().any(|b| *b != bytes[0]));
+}
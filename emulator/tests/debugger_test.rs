@@ -0,0 +1,217 @@
+extern crate chip8_emulator;
+
+mod cpu_fixture;
+use chip8_emulator::debugger::*;
+use chip8_emulator::machine::*;
+use cpu_fixture::CpuFixture;
+
+fn machine_with(op_codes: &[u16]) -> Machine {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(op_codes);
+    Machine::new(fixture.cpu)
+}
+
+// 0x200: V0 += 1, call 0x208, jump 0x200
+// 0x208: V1 += 1, V2 := V1, return
+fn subroutine_machine() -> Machine {
+    machine_with(&[0x7001, 0x2208, 0x1200, 0x0000, 0x7101, 0x8210, 0x00EE])
+}
+
+#[test]
+fn test_step_executes_one_instruction() {
+    let mut machine = subroutine_machine();
+    let mut debugger = Debugger::new();
+
+    let reason = debugger.step(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::Step);
+    assert_eq!(machine.cpu.program_counter, 0x202);
+    assert_eq!(machine.cpu.registers.v[0], 1);
+}
+
+#[test]
+fn test_breakpoint_stops_before_instruction() {
+    let mut machine = subroutine_machine();
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x20A);
+
+    let reason = debugger.run_frame(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::Breakpoint { pc: 0x20A });
+    assert_eq!(machine.cpu.registers.v[1], 1);
+    assert_eq!(machine.cpu.registers.v[2], 0);
+}
+
+#[test]
+fn test_resume_from_breakpoint() {
+    let mut machine = subroutine_machine();
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x200);
+
+    debugger.step(&mut machine).unwrap();
+    let reason = debugger.run_frame(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::Breakpoint { pc: 0x200 });
+    assert_eq!(machine.cpu.registers.v[0], 1);
+}
+
+#[test]
+fn test_breakpoint_on_first_instruction_of_frame() {
+    // Eight instructions fill the first frame, 0x210 starts the second
+    let mut machine = machine_with(&[0x7001; 8].iter().cloned().chain(vec![0x7101, 0x1212]).collect::<Vec<u16>>());
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x210);
+
+    assert_eq!(debugger.run_frame(&mut machine).unwrap(), StopReason::FrameComplete);
+    assert_eq!(machine.cpu.program_counter, 0x210);
+
+    let reason = debugger.run_frame(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::Breakpoint { pc: 0x210 });
+    assert_eq!(machine.cpu.registers.v[1], 0);
+
+    assert_eq!(debugger.run_frame(&mut machine).unwrap(), StopReason::FrameComplete);
+    assert_eq!(machine.cpu.registers.v[1], 1);
+}
+
+#[test]
+fn test_conditional_breakpoint() {
+    let mut machine = subroutine_machine();
+    let mut debugger = Debugger::new();
+    debugger.add_conditional_breakpoint(0x200, Condition::parse("V0 == 0x3").unwrap());
+
+    let reason = debugger.run_until_frame(&mut machine, 10).unwrap();
+
+    assert_eq!(reason, StopReason::Breakpoint { pc: 0x200 });
+    assert_eq!(machine.cpu.registers.v[0], 3);
+}
+
+#[test]
+fn test_condition_parse() {
+    assert_eq!(Condition::parse("V3 == 0x10"), Some(Condition {
+        register: Register::V(3),
+        comparison: Comparison::Equal,
+        value: 0x10,
+    }));
+    assert_eq!(Condition::parse("i>=768"), Some(Condition {
+        register: Register::I,
+        comparison: Comparison::GreaterOrEqual,
+        value: 768,
+    }));
+    assert_eq!(Condition::parse("VG == 1"), None);
+    assert_eq!(Condition::parse("V1 = 1"), None);
+}
+
+#[test]
+fn test_memory_write_watchpoint() {
+    // I := 0x300, V0 := 0x42, store V0, jump self
+    let mut machine = machine_with(&[0xA300, 0x6042, 0xF055, 0x1206]);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::MemoryWrite { start: 0x300, end: 0x30F });
+
+    let reason = debugger.run_frame(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::MemoryWrite { pc: 0x204, addr: 0x300, value: 0x42 });
+    assert!(machine.cpu.last_memory_accesses().is_empty());
+}
+
+#[test]
+fn test_memory_read_watchpoint() {
+    // I := 0x300, draw 1 row, jump self
+    let mut machine = machine_with(&[0xA300, 0xD011, 0x1204]);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::MemoryRead { start: 0x300, end: 0x300 });
+
+    let reason = debugger.run_frame(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::MemoryRead { pc: 0x202, addr: 0x300 });
+}
+
+#[test]
+fn test_register_watchpoint() {
+    let mut machine = subroutine_machine();
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::Register(Register::V(2)));
+
+    let reason = debugger.run_frame(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::RegisterChanged { pc: 0x20A, register: Register::V(2), old: 0, new: 1 });
+}
+
+#[test]
+fn test_register_watchpoint_sees_timer_ticks() {
+    // DT := 2, then spin
+    let mut machine = machine_with(&[0x6002, 0xF015, 0x1204]);
+    let mut debugger = Debugger::new();
+    debugger.add_watchpoint(Watchpoint::Register(Register::DelayTimer));
+
+    let reason = debugger.run_frame(&mut machine).unwrap();
+    assert_eq!(reason, StopReason::RegisterChanged { pc: 0x202, register: Register::DelayTimer, old: 0, new: 2 });
+
+    let reason = debugger.run_frame(&mut machine).unwrap();
+    assert_eq!(reason, StopReason::RegisterChanged { pc: 0x204, register: Register::DelayTimer, old: 2, new: 1 });
+}
+
+#[test]
+fn test_step_over_runs_call_to_completion() {
+    let mut machine = subroutine_machine();
+    let mut debugger = Debugger::new();
+    debugger.step(&mut machine).unwrap();
+
+    let reason = debugger.step_over(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::Step);
+    assert_eq!(machine.cpu.program_counter, 0x204);
+    assert_eq!(machine.cpu.registers.v[2], 1);
+}
+
+#[test]
+fn test_step_over_stops_at_breakpoint_inside_call() {
+    let mut machine = subroutine_machine();
+    let mut debugger = Debugger::new();
+    debugger.add_breakpoint(0x20A);
+    debugger.step(&mut machine).unwrap();
+
+    let reason = debugger.step_over(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::Breakpoint { pc: 0x20A });
+}
+
+#[test]
+fn test_step_out_returns_to_caller() {
+    let mut machine = subroutine_machine();
+    let mut debugger = Debugger::new();
+    debugger.step(&mut machine).unwrap();
+    debugger.step(&mut machine).unwrap();
+    assert_eq!(machine.cpu.program_counter, 0x208);
+
+    let reason = debugger.step_out(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::Step);
+    assert_eq!(machine.cpu.program_counter, 0x204);
+    assert_eq!(machine.cpu.stack_pointer, 0);
+}
+
+#[test]
+fn test_run_until_frame() {
+    let mut machine = subroutine_machine();
+    let mut debugger = Debugger::new();
+
+    let reason = debugger.run_until_frame(&mut machine, 5).unwrap();
+
+    assert_eq!(reason, StopReason::FrameReached { frame: 5 });
+    assert_eq!(machine.frame_count(), 5);
+}
+
+#[test]
+fn test_instruction_limit() {
+    // Infinite recursion-free loop inside a subroutine never returns
+    let mut machine = machine_with(&[0x2204, 0x0000, 0x1204]);
+    let mut debugger = Debugger::new();
+    debugger.set_instruction_limit(100);
+    debugger.step(&mut machine).unwrap();
+
+    let reason = debugger.step_out(&mut machine).unwrap();
+
+    assert_eq!(reason, StopReason::InstructionLimit);
+}
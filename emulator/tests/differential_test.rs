@@ -0,0 +1,325 @@
+extern crate chip8_emulator;
+extern crate proptest;
+
+mod reference_interpreter;
+use std::fs;
+use std::path::PathBuf;
+use proptest::prelude::*;
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::instruction::Instruction;
+use chip8_emulator::keypad::{KeyChange, Keypad};
+use chip8_emulator::movie::MovieEvent;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::random::SeededRandom;
+use chip8_emulator::variant::Variant;
+use reference_interpreter::{Reference, Step};
+
+// Cpu and the reference interpreter run the same CHIP-8 program in lockstep, eight instructions
+// per frame with the timers ticking before each frame like Cpu::execute_cycle, and their state
+// is compared after every instruction
+const STEPS_PER_FRAME: usize = 8;
+const SEED: u64 = 7;
+
+#[derive(Clone, PartialEq, Eq)]
+struct State {
+    v: [u8; 16],
+    i: u16,
+    pc: u16,
+    stack: Vec<u16>,
+    delay_timer: u8,
+    sound_timer: u8,
+    memory: Vec<u8>,
+    display: Vec<Vec<bool>>,
+}
+
+impl State {
+    fn of_cpu (cpu: &Cpu) -> State {
+        State {
+            v: cpu.registers.v,
+            i: cpu.registers.i,
+            pc: cpu.program_counter,
+            stack: cpu.stack[..cpu.stack_pointer].to_vec(),
+            delay_timer: cpu.registers.delay_timer,
+            sound_timer: cpu.registers.sound_timer,
+            memory: cpu.memory.clone(),
+            display: cpu.video_memory.clone(),
+        }
+    }
+
+    fn of_reference (reference: &Reference) -> State {
+        State {
+            v: reference.v,
+            i: reference.i,
+            pc: reference.pc,
+            stack: reference.stack.clone(),
+            delay_timer: reference.delay_timer,
+            sound_timer: reference.sound_timer,
+            memory: reference.memory.clone(),
+            display: reference.display.iter().map(|row| row.to_vec()).collect(),
+        }
+    }
+
+    fn opcode (&self) -> Option<u16> {
+        let pc = self.pc as usize;
+        match (self.memory.get(pc), self.memory.get(pc + 1)) {
+            (Some(&high), Some(&low)) => Some(((high as u16) << 8) | low as u16),
+            _ => None,
+        }
+    }
+}
+
+// Every register, stack, memory and display change from `before` to `after`, one per line
+fn delta (before: &State, after: &State) -> String {
+    let mut lines = Vec::new();
+    for (index, (old, new)) in before.v.iter().zip(after.v.iter()).enumerate() {
+        if old != new {
+            lines.push(format!("V{:X}: {:#04X} -> {:#04X}", index, old, new));
+        }
+    }
+    if before.i != after.i {
+        lines.push(format!("I: {:#06X} -> {:#06X}", before.i, after.i));
+    }
+    if before.pc != after.pc {
+        lines.push(format!("PC: {:#06X} -> {:#06X}", before.pc, after.pc));
+    }
+    if before.stack != after.stack {
+        lines.push(format!("stack: {:X?} -> {:X?}", before.stack, after.stack));
+    }
+    if before.delay_timer != after.delay_timer {
+        lines.push(format!("DT: {} -> {}", before.delay_timer, after.delay_timer));
+    }
+    if before.sound_timer != after.sound_timer {
+        lines.push(format!("ST: {} -> {}", before.sound_timer, after.sound_timer));
+    }
+    for (address, (old, new)) in before.memory.iter().zip(after.memory.iter()).enumerate() {
+        if old != new {
+            lines.push(format!("[{:#06X}]: {:#04X} -> {:#04X}", address, old, new));
+        }
+    }
+    let flipped = before.display.iter().flat_map(|row| row.iter())
+        .zip(after.display.iter().flat_map(|row| row.iter()))
+        .filter(|&(old, new)| old != new)
+        .count();
+    if flipped > 0 {
+        lines.push(format!("display: {} pixels flipped", flipped));
+    }
+
+    if lines.is_empty() {
+        return "  nothing\n".to_string();
+    }
+    lines.iter().map(|line| format!("  {}\n", line)).collect()
+}
+
+fn outcome (result: &Result<(), String>) -> String {
+    match *result {
+        Ok(()) => "ok".to_string(),
+        Err(ref error) => format!("failed, {}", error),
+    }
+}
+
+fn pair (rom: &[u8], quirks: Quirks) -> (Cpu, Reference) {
+    let mut cpu = Cpu::new(Keypad::new(), Variant::Chip8, quirks);
+    cpu.set_random_source(Box::new(SeededRandom::new(SEED)));
+    cpu.load_program(rom);
+    let reference = Reference::new(cpu.memory.clone(), quirks, SEED);
+    (cpu, reference)
+}
+
+// Runs both for `frames` frames, applying each event before the frame it is tagged with, and
+// describes the first instruction after which they disagree along with what each of them
+// changed. Running stops without a report at the first error both agree on
+fn first_divergence (cpu: &mut Cpu, reference: &mut Reference, events: &[MovieEvent], frames: u32) -> Option<String> {
+    let mut cpu_before = State::of_cpu(cpu);
+    let mut reference_before = State::of_reference(reference);
+    let mut next_event = 0;
+    // Where Cpu's program counter stopped when the current Fx0A wait began
+    let mut waiting_pc = None;
+
+    for frame in 0..frames {
+        while let Some(event) = events.get(next_event).filter(|event| event.frame <= frame) {
+            next_event += 1;
+            if event.change.pressed {
+                cpu.keypad.press_key(event.change.key);
+                reference.press_key(event.change.key);
+            } else {
+                cpu.keypad.release_key(event.change.key);
+                reference.release_key(event.change.key);
+            }
+        }
+        cpu.tick_timers();
+        reference.tick_timers();
+        // Timers aren't an instruction's doing, leave them out of the deltas
+        cpu_before.delay_timer = cpu.registers.delay_timer;
+        cpu_before.sound_timer = cpu.registers.sound_timer;
+        reference_before.delay_timer = reference.delay_timer;
+        reference_before.sound_timer = reference.sound_timer;
+
+        for step in 0..STEPS_PER_FRAME {
+            let cpu_result = cpu.execute_next_op_code().map_err(|e| e.to_string());
+            // Key edges only count for the instruction right after them, on both sides
+            cpu.keypad.clear_edges();
+            // Cpu moves past Fx0A as soon as it starts waiting, and runs the instruction after
+            // it in the same step that takes the key. While the reference waits Cpu has to be
+            // waiting on the same register without moving, the states are compared once it's over
+            let mut reference_result = reference.step();
+            if reference_result == Ok(Step::KeyTaken) {
+                reference_result = reference.step();
+            }
+            let mut note = None;
+            if reference_result == Ok(Step::Waiting) && cpu_result.is_ok() {
+                let register = reference.key_wait_register().unwrap();
+                if cpu.key_wait_register() != Some(register) {
+                    note = Some(format!("reference is waiting for a key in V{:X}, cpu isn't", register));
+                } else if *waiting_pc.get_or_insert(cpu.program_counter) != cpu.program_counter {
+                    note = Some(format!("cpu moved while waiting for a key in V{:X}", register));
+                } else {
+                    continue;
+                }
+            }
+            waiting_pc = None;
+            let reference_result = reference_result.map(|_| ());
+
+            let cpu_after = State::of_cpu(cpu);
+            let reference_after = State::of_reference(reference);
+            if note.is_some() || cpu_result.is_ok() != reference_result.is_ok() || cpu_after != reference_after {
+                let instruction = reference_before.opcode()
+                    .map(|opcode| match Instruction::decode(opcode) {
+                        Some(instruction) => format!("{:04X} {}", opcode, instruction),
+                        None => format!("{:04X}", opcode),
+                    })
+                    .unwrap_or_else(|| "past the end of memory".to_string());
+                return Some(format!(
+                    "frame {}, step {}: diverged at {:#06X} {}\n{}cpu {}, changed:\n{}reference {}, changed:\n{}",
+                    frame, step, reference_before.pc, instruction,
+                    note.map(|note| format!("{}\n", note)).unwrap_or_default(),
+                    outcome(&cpu_result), delta(&cpu_before, &cpu_after),
+                    outcome(&reference_result), delta(&reference_before, &reference_after),
+                ));
+            }
+            if cpu_result.is_err() {
+                return None;
+            }
+            cpu_before = cpu_after;
+            reference_before = reference_after;
+        }
+    }
+    None
+}
+
+fn crate_path (path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(path)
+}
+
+const fn press (frame: u32, key: u8) -> MovieEvent {
+    MovieEvent { frame: frame, change: KeyChange { key: key, pressed: true } }
+}
+
+const fn release (frame: u32, key: u8) -> MovieEvent {
+    MovieEvent { frame: frame, change: KeyChange { key: key, pressed: false } }
+}
+
+const ROMS: &[(&str, &[MovieEvent])] = &[
+    ("tests/roms/flags.ch8", &[]),
+    ("tests/roms/display.ch8", &[]),
+    ("../web/roms/BRIX.ch8", &[press(120, 0x4), release(150, 0x4), press(200, 0x6), release(260, 0x6)]),
+    ("../web/roms/PONG2.ch8", &[press(60, 0x1), release(90, 0x1), press(100, 0xC), release(140, 0xC)]),
+    ("../web/roms/TETRIS.ch8", &[press(100, 0x6), release(110, 0x6), press(150, 0x4), release(160, 0x4)]),
+    ("../web/roms/UFO.ch8", &[press(100, 0x5), release(105, 0x5), press(200, 0x4), release(203, 0x4)]),
+];
+
+#[test]
+fn test_roms_match_reference() {
+    for &(path, events) in ROMS {
+        let rom = fs::read(crate_path(path)).unwrap();
+        for &quirks in &[Quirks::cosmac_vip(), Quirks::schip(), Quirks::default()] {
+            let (mut cpu, mut reference) = pair(&rom, quirks);
+            if let Some(report) = first_divergence(&mut cpu, &mut reference, events, 600) {
+                panic!("{} with {:?}\n{}", path, quirks, report);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_key_wait_matches_reference() {
+    // Wait for a key, then count up in V1 until the next wait
+    let rom = [0xF0, 0x0A, 0x71, 0x01, 0xF2, 0x0A, 0x12, 0x02];
+    let events = [press(2, 0x3), release(4, 0x3), press(5, 0x9), press(6, 0x1), release(7, 0x9), release(9, 0x1)];
+    for &on_press in &[false, true] {
+        let quirks = Quirks { key_wait_on_press: on_press, ..Quirks::cosmac_vip() };
+        let (mut cpu, mut reference) = pair(&rom, quirks);
+
+        assert_eq!(first_divergence(&mut cpu, &mut reference, &events, 12), None);
+        assert_eq!(cpu.registers.v[0], 0x3);
+        assert_eq!(reference.v[2], cpu.registers.v[2]);
+    }
+}
+
+#[test]
+fn test_divergence_report() {
+    // LD V1, 0x81 ; SHR V1, V2 ; with shift_uses_vy on the reference side only
+    let (mut cpu, _) = pair(&[0x61, 0x81, 0x81, 0x26], Quirks::default());
+    let (_, mut reference) = pair(&[0x61, 0x81, 0x81, 0x26], Quirks { shift_uses_vy: true, ..Quirks::default() });
+
+    let report = first_divergence(&mut cpu, &mut reference, &[], 1).unwrap();
+
+    assert_eq!(report, "frame 0, step 1: diverged at 0x0202 8126 SHR V1, V2\n\
+        cpu ok, changed:\n  V1: 0x81 -> 0x40\n  VF: 0x00 -> 0x01\n  PC: 0x0202 -> 0x0204\n\
+        reference ok, changed:\n  V1: 0x81 -> 0x00\n  PC: 0x0202 -> 0x0204\n");
+}
+
+#[test]
+fn test_divergence_while_waiting_for_key() {
+    // LD V0, K ; spin, with a key held from the start. Cpu takes it at once, the reference waits
+    let rom = [0xF0, 0x0A, 0x12, 0x02];
+    let (mut cpu, _) = pair(&rom, Quirks { key_wait_on_press: true, ..Quirks::default() });
+    let (_, mut reference) = pair(&rom, Quirks::default());
+
+    let report = first_divergence(&mut cpu, &mut reference, &[press(0, 0x5)], 1).unwrap();
+
+    assert!(report.starts_with("frame 0, step 1: diverged at 0x0200 F00A LD V0, K\n\
+        reference is waiting for a key in V0, cpu isn't\n"), "{}", report);
+    assert!(report.contains("cpu ok, changed:\n  V0: 0x00 -> 0x05\n"), "{}", report);
+}
+
+fn chip8_opcode () -> impl Strategy<Value = u16> {
+    any::<u16>().prop_filter("not a CHIP-8 instruction", |&opcode| {
+        Instruction::decode(opcode).is_some_and(|instruction| instruction.is_supported_by(Variant::Chip8))
+    })
+}
+
+fn key_events () -> impl Strategy<Value = Vec<MovieEvent>> {
+    prop::collection::vec((0..120u32, 0..16u8, any::<bool>()), 0..24).prop_map(|mut events| {
+        events.sort();
+        events.into_iter().map(|(frame, key, pressed)| MovieEvent { frame: frame, change: KeyChange { key: key, pressed: pressed } }).collect()
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn test_random_programs_match_reference (
+        opcodes in prop::collection::vec(chip8_opcode(), 1..96),
+        quirks in any::<u8>().prop_map(Quirks::from_bits),
+        events in key_events(),
+    ) {
+        let rom = opcodes.iter().flat_map(|&opcode| vec![(opcode >> 8) as u8, opcode as u8]).collect::<Vec<u8>>();
+        let (mut cpu, mut reference) = pair(&rom, quirks);
+        if let Some(report) = first_divergence(&mut cpu, &mut reference, &events, 120) {
+            return Err(TestCaseError::fail(report));
+        }
+    }
+
+    #[test]
+    fn test_random_bytes_match_reference (
+        rom in prop::collection::vec(any::<u8>(), 0..256),
+        quirks in any::<u8>().prop_map(Quirks::from_bits),
+        events in key_events(),
+    ) {
+        let (mut cpu, mut reference) = pair(&rom, quirks);
+        if let Some(report) = first_divergence(&mut cpu, &mut reference, &events, 120) {
+            return Err(TestCaseError::fail(report));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+extern crate chip8_emulator;
+
+use chip8_emulator::disasm::*;
+
+fn rom(op_codes: &[u16]) -> Vec<u8> {
+    op_codes
+        .iter()
+        .flat_map(|op_code| vec![(op_code >> 8) as u8, *op_code as u8])
+        .collect()
+}
+
+#[test]
+fn test_decode_structured_instruction() {
+    assert_eq!(decode(0x6120), Some(Instruction::LoadImm(0x1, 0x20)));
+    assert_eq!(decode(0xD015), Some(Instruction::Draw(0x0, 0x1, 5)));
+    assert_eq!(decode(0x2345), Some(Instruction::Call(0x345)));
+    assert_eq!(decode(0xF000), Some(Instruction::LongLoadI));
+    assert_eq!(decode(0x5121), None);
+    assert_eq!(decode(0x0123), None);
+}
+
+#[test]
+fn test_format_mnemonics() {
+    let cases = [
+        (0x00E0, "CLS"),
+        (0x00EE, "RET"),
+        (0x00C4, "SCD 4"),
+        (0x00FF, "HIGH"),
+        (0x1228, "JP 0x228"),
+        (0x3A0F, "SE VA, 0x0F"),
+        (0x5AB0, "SE VA, VB"),
+        (0x5123, "LOAD V1, V2"),
+        (0x6120, "LD V1, 0x20"),
+        (0x8126, "SHR V1, V2"),
+        (0xA300, "LD I, 0x300"),
+        (0xB400, "JP V0, 0x400"),
+        (0xC3FF, "RND V3, 0xFF"),
+        (0xD015, "DRW V0, V1, 5"),
+        (0xE39E, "SKP V3"),
+        (0xF201, "PLANE 2"),
+        (0xF40A, "LD V4, K"),
+        (0xF529, "LD F, V5"),
+        (0xF655, "LD [I], V6"),
+        (0xF785, "LD V7, R"),
+    ];
+
+    for &(op_code, expected) in cases.iter() {
+        assert_eq!(disassemble_instruction(op_code).unwrap(), expected, "{:04X}", op_code);
+    }
+}
+
+#[test]
+fn test_listing_labels_jump_and_call_targets() {
+    let listing = disassemble(&rom(&[0x2206, 0x1202, 0x0000, 0x6001, 0x00EE]));
+
+    assert_eq!(listing.label_for(0x206), Some("L206"));
+    assert_eq!(listing.label_for(0x202), Some("L202"));
+    assert_eq!(listing.to_string(),
+        "0200  2206              CALL L206\n\
+         L202:\n\
+         0202  1202              JP L202\n\
+         0204  0000              DB 0x00, 0x00\n\
+         L206:\n\
+         0206  6001              LD V0, 0x01\n\
+         0208  00EE              RET\n");
+}
+
+#[test]
+fn test_listing_detects_sprite_data() {
+    let mut bytes = rom(&[0xA206, 0xD015, 0x1204]);
+    bytes.extend_from_slice(&[0xF0, 0x90, 0x90, 0x90, 0xF0]);
+    let listing = disassemble(&bytes);
+
+    let data = listing.lines.last().unwrap();
+    assert_eq!(data.address, 0x206);
+    assert_eq!(data.label, Some("L206".to_string()));
+    assert_eq!(data.item, Item::Data(vec![0xF0, 0x90, 0x90, 0x90, 0xF0]));
+    assert!(listing.to_string().contains("LD I, L206"));
+}
+
+#[test]
+fn test_listing_follows_both_skip_branches() {
+    let listing = disassemble(&rom(&[0x3000, 0x1208, 0x00FD, 0x0102, 0x6105, 0x00FD]));
+
+    let code = listing.lines
+        .iter()
+        .filter(|line| !matches!(line.item, Item::Data(_)))
+        .count();
+    assert_eq!(code, 5);
+}
+
+#[test]
+fn test_listing_long_load_operand() {
+    let listing = disassemble(&rom(&[0xF000, 0x0206, 0x00FD, 0x0102]));
+
+    assert_eq!(listing.lines[0].item, Item::LongLoad(0x206));
+    assert_eq!(listing.lines[0].bytes.len(), 4);
+    assert!(listing.to_string().contains("LD I, LONG L206"));
+}
+
+#[test]
+fn test_encode_round_trips_every_opcode() {
+    for opcode in 0..=0xFFFFu16 {
+        if let Some(instruction) = decode(opcode) {
+            assert_eq!(instruction.encode(), opcode, "{}", instruction);
+        }
+    }
+}
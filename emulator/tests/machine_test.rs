@@ -0,0 +1,140 @@
+extern crate chip8_emulator;
+
+mod cpu_fixture;
+use std::time::Duration;
+use chip8_emulator::machine::*;
+use cpu_fixture::CpuFixture;
+
+fn counting_machine() -> Machine {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0x7001, 0x1200]);
+    Machine::new(fixture.cpu)
+}
+
+fn executed_instructions(machine: &Machine) -> u32 {
+    let loops = machine.cpu.registers.v[0] as u32;
+    let in_loop = (machine.cpu.program_counter == 0x202) as u32;
+    loops * 2 - in_loop
+}
+
+#[test]
+fn test_default_speed() {
+    let machine = counting_machine();
+
+    assert_eq!(machine.instructions_per_second(), DEFAULT_INSTRUCTIONS_PER_SECOND);
+    assert_eq!(machine.timer_frequency(), DEFAULT_TIMER_FREQUENCY);
+}
+
+#[test]
+fn test_run_for_executes_instructions_per_second() {
+    let mut machine = counting_machine();
+    machine.set_instructions_per_second(200);
+
+    machine.run_for(Duration::from_millis(500)).unwrap();
+
+    assert_eq!(executed_instructions(&machine), 100);
+}
+
+#[test]
+fn test_run_for_accumulates_fractional_cycles() {
+    let mut machine = counting_machine();
+    machine.set_instructions_per_second(500);
+
+    for _ in 0..100 {
+        machine.run_for(Duration::from_micros(1500)).unwrap();
+    }
+
+    assert_eq!(executed_instructions(&machine), 75);
+}
+
+#[test]
+fn test_run_for_ticks_timers() {
+    let mut machine = counting_machine();
+    machine.cpu.registers.delay_timer = 100;
+    machine.cpu.registers.sound_timer = 30;
+
+    machine.run_for(Duration::from_millis(500)).unwrap();
+
+    assert_eq!(machine.cpu.registers.delay_timer, 70);
+    assert_eq!(machine.cpu.registers.sound_timer, 0);
+}
+
+#[test]
+fn test_timer_frequency() {
+    let mut machine = counting_machine();
+    machine.set_timer_frequency(120);
+    machine.cpu.registers.delay_timer = 200;
+
+    machine.run_for(Duration::from_secs(1)).unwrap();
+
+    assert_eq!(machine.cpu.registers.delay_timer, 80);
+}
+
+#[test]
+fn test_run_for_caps_long_durations() {
+    let mut machine = counting_machine();
+    machine.cpu.registers.delay_timer = 100;
+
+    machine.run_for(Duration::from_secs(3600)).unwrap();
+
+    assert_eq!(executed_instructions(&machine), DEFAULT_INSTRUCTIONS_PER_SECOND);
+    assert_eq!(machine.cpu.registers.delay_timer, 40);
+}
+
+#[test]
+fn test_pending_event_with_large_budgets() {
+    let mut machine = counting_machine();
+    machine.set_instructions_per_second(u32::MAX);
+    machine.set_timer_frequency(1 << 16);
+
+    for _ in 0..10 {
+        machine.schedule_frame();
+    }
+
+    assert_eq!(machine.pending_event(), Some(Event::TimerTick));
+}
+
+#[test]
+fn test_run_frame() {
+    let mut machine = counting_machine();
+    machine.set_instructions_per_second(300);
+    machine.cpu.registers.delay_timer = 10;
+
+    for _ in 0..60 {
+        machine.run_frame().unwrap();
+    }
+
+    assert_eq!(executed_instructions(&machine), 300);
+    assert_eq!(machine.cpu.registers.delay_timer, 0);
+}
+
+#[test]
+fn test_run_for_interleaves_timers_and_instructions() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xF015, 0xF107, 0xF207]);
+    fixture.cpu.registers.v[0] = 10;
+    let mut machine = Machine::new(fixture.cpu);
+    machine.set_instructions_per_second(100);
+
+    machine.run_for(Duration::from_millis(35)).unwrap();
+
+    assert_eq!(machine.cpu.registers.v[1], 9);
+    assert_eq!(machine.cpu.registers.v[2], 9);
+    assert_eq!(machine.cpu.registers.delay_timer, 8);
+}
+
+#[test]
+fn test_run_frame_clears_key_edges() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xF30A, 0x1202]);
+    let mut machine = Machine::new(fixture.cpu);
+
+    machine.cpu.keypad.press_key(0x4);
+    machine.run_frame().unwrap();
+    assert!(!machine.cpu.keypad.key_was_just_pressed(0x4));
+    assert_eq!(machine.cpu.registers.v[3], 0);
+
+    machine.cpu.keypad.release_key(0x4);
+    machine.run_frame().unwrap();
+    assert_eq!(machine.cpu.registers.v[3], 0x4);
+}
@@ -0,0 +1,170 @@
+extern crate chip8_emulator;
+
+mod cpu_fixture;
+use chip8_emulator::error::{MovieError, StateError};
+use chip8_emulator::keypad::{KeyChange, Keypad};
+use chip8_emulator::machine::Machine;
+use chip8_emulator::movie::*;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::variant::Variant;
+use cpu_fixture::CpuFixture;
+
+// Waits for a key, adds a random byte to V1 per key and draws the font glyph for the key
+const ROM: [u8; 12] = [
+    0xF0, 0x0A, // LD V0, K
+    0xC2, 0xFF, // RND V2, 0xFF
+    0x81, 0x24, // ADD V1, V2
+    0xF0, 0x29, // LD F, V0
+    0xD3, 0x45, // DRW V3, V4, 5
+    0x12, 0x00, // JP 0x200
+];
+
+fn machine() -> Machine {
+    Machine::new(CpuFixture::new().cpu)
+}
+
+fn record() -> (Movie, Vec<u8>) {
+    let mut machine = machine();
+    let mut recorder = MovieRecorder::start(&mut machine, &ROM, 1234, 10);
+
+    for frame in 0..60 {
+        match frame {
+            5 => machine.cpu.keypad.press_key(0x3),
+            8 => machine.cpu.keypad.release_key(0x3),
+            20 => {
+                machine.cpu.keypad.press_key(0xA);
+                machine.cpu.keypad.press_key(0x1);
+            },
+            30 => machine.cpu.keypad.release_all_keys(),
+            _ => {},
+        }
+        recorder.run_frame(&mut machine).unwrap();
+    }
+
+    (recorder.finish(&mut machine), machine.cpu.save_state())
+}
+
+#[test]
+fn test_keypad_tracks_changes() {
+    let mut keypad = Keypad::new();
+    keypad.press_key(1);
+    assert!(keypad.take_changes().is_empty());
+
+    keypad.set_change_tracking(true);
+    keypad.press_key(1);
+    keypad.press_key(2);
+    keypad.release_all_keys();
+
+    assert_eq!(keypad.take_changes(), vec![
+        KeyChange { key: 2, pressed: true },
+        KeyChange { key: 1, pressed: false },
+        KeyChange { key: 2, pressed: false },
+    ]);
+    assert!(keypad.take_changes().is_empty());
+}
+
+#[test]
+fn test_recorder_tags_changes_with_frames() {
+    let (movie, _) = record();
+
+    assert_eq!(movie.frames, 60);
+    assert_eq!(movie.header.seed, 1234);
+    assert_eq!(movie.events.len(), 6);
+    assert_eq!(movie.events[0], MovieEvent { frame: 5, change: KeyChange { key: 0x3, pressed: true } });
+    assert_eq!(movie.events[5].frame, 30);
+    assert_eq!(movie.checksums.iter().map(|&(frame, _)| frame).collect::<Vec<u32>>(), vec![10, 20, 30, 40, 50, 60]);
+}
+
+#[test]
+fn test_playback_reproduces_recording() {
+    let (movie, recorded_state) = record();
+    let mut machine = machine();
+    machine.cpu.keypad.press_key(0x7);
+
+    let mut player = MoviePlayer::start(movie, &mut machine, &ROM).unwrap();
+    player.play_to_end(&mut machine).unwrap();
+
+    assert!(player.is_finished());
+    assert_eq!(machine.cpu.save_state(), recorded_state);
+    assert_ne!(machine.cpu.registers.v[1], 0);
+}
+
+// A SUPER-CHIP machine that already saved 0x2A to the RPL flags
+fn machine_with_rpl_flags() -> Machine {
+    let mut fixture = CpuFixture::with_variant(Variant::SuperChip, Quirks::schip());
+    fixture.load_op_codes(&[0x602A, 0xF075]);
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.execute_next_op_code().unwrap();
+    Machine::new(fixture.cpu)
+}
+
+#[test]
+fn test_movies_start_without_leftover_rpl_flags() {
+    // Loads V0 from the RPL flags and spins
+    let rom = [0xF0, 0x85, 0x12, 0x02];
+    let mut machine = machine_with_rpl_flags();
+
+    let mut recorder = MovieRecorder::start(&mut machine, &rom, 1, 10);
+    for _ in 0..20 {
+        recorder.run_frame(&mut machine).unwrap();
+    }
+    let movie = recorder.finish(&mut machine);
+    assert_eq!(machine.cpu.registers.v[0], 0);
+
+    let mut machine = Machine::new(CpuFixture::with_variant(Variant::SuperChip, Quirks::schip()).cpu);
+    let mut player = MoviePlayer::start(movie.clone(), &mut machine, &rom).unwrap();
+    assert_eq!(player.play_to_end(&mut machine), Ok(()));
+
+    let mut machine = machine_with_rpl_flags();
+    let mut player = MoviePlayer::start(movie, &mut machine, &rom).unwrap();
+    assert_eq!(player.play_to_end(&mut machine), Ok(()));
+}
+
+#[test]
+fn test_playback_detects_desync() {
+    let (mut movie, _) = record();
+    movie.events.remove(3);
+    let mut machine = machine();
+
+    let mut player = MoviePlayer::start(movie, &mut machine, &ROM).unwrap();
+
+    match player.play_to_end(&mut machine) {
+        Err(MovieError::Desync { frame, .. }) => assert_eq!(frame, 30),
+        result => panic!("expected a desync, got {:?}", result),
+    }
+}
+
+#[test]
+fn test_playback_rejects_other_rom() {
+    let (movie, _) = record();
+    let mut machine = machine();
+
+    let result = MoviePlayer::start(movie, &mut machine, &ROM[..10]);
+
+    assert!(matches!(result, Err(MovieError::RomMismatch { .. })));
+}
+
+#[test]
+fn test_movie_round_trip() {
+    let (movie, _) = record();
+
+    let bytes = movie.to_bytes();
+
+    assert_eq!(&bytes[..4], MOVIE_MAGIC);
+    assert_eq!(Movie::from_bytes(&bytes), Ok(movie));
+}
+
+#[test]
+fn test_corrupt_movie_is_rejected() {
+    let (movie, _) = record();
+    let mut bytes = movie.to_bytes();
+
+    let last = bytes.len() - 5;
+    bytes[last] ^= 0xFF;
+    assert!(matches!(
+        Movie::from_bytes(&bytes),
+        Err(MovieError::Format(StateError::ChecksumMismatch { .. }))
+    ));
+    assert_eq!(Movie::from_bytes(b"C8ST"), Err(MovieError::Format(StateError::InvalidMagic)));
+    assert_eq!(Movie::from_bytes(&bytes[..6]), Err(MovieError::Format(StateError::Truncated)));
+}
@@ -0,0 +1,165 @@
+extern crate chip8_emulator;
+extern crate proptest;
+
+use proptest::prelude::*;
+use chip8_emulator::cpu::*;
+use chip8_emulator::instruction::Instruction;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::random::SeededRandom;
+use chip8_emulator::variant::Variant;
+
+const STEPS: usize = 1000;
+
+fn variant () -> impl Strategy<Value = Variant> {
+    prop_oneof![Just(Variant::Chip8), Just(Variant::SuperChip), Just(Variant::XoChip)]
+}
+
+fn quirks () -> impl Strategy<Value = Quirks> {
+    any::<u8>().prop_map(Quirks::from_bits)
+}
+
+// Keys toggled before a step, as (step, key)
+fn key_toggles () -> impl Strategy<Value = Vec<(usize, u8)>> {
+    prop::collection::vec((0..STEPS, 0..16u8), 0..32).prop_map(|mut toggles| {
+        toggles.sort();
+        toggles
+    })
+}
+
+fn cpu_with (rom: &[u8], variant: Variant, quirks: Quirks) -> Cpu {
+    let mut cpu = Cpu::new(Keypad::new(), variant, quirks);
+    cpu.set_random_source(Box::new(SeededRandom::new(1)));
+    cpu.load_program(rom);
+    cpu
+}
+
+// Runs until the first error, toggling keys on the way. Errors have to blame the instruction
+// that was about to run, and the program counter has to stay inside memory
+fn run (cpu: &mut Cpu, toggles: &[(usize, u8)]) -> Result<(), TestCaseError> {
+    let mut next_toggle = 0;
+    for step in 0..STEPS {
+        while let Some(&(_, key)) = toggles.get(next_toggle).filter(|&&(at, _)| at <= step) {
+            next_toggle += 1;
+            if cpu.keypad.key_is_pressed(key) {
+                cpu.keypad.release_key(key);
+            } else {
+                cpu.keypad.press_key(key);
+            }
+        }
+        if step % 8 == 0 {
+            cpu.tick_timers();
+        }
+
+        let pc = cpu.program_counter;
+        if let Err(error) = cpu.execute_next_op_code() {
+            prop_assert_eq!(error.pc(), pc);
+            return Ok(());
+        }
+        prop_assert!((cpu.program_counter as usize) < cpu.memory.len());
+        prop_assert!(cpu.stack_pointer <= cpu.stack.len());
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn test_random_roms_never_panic (
+        rom in prop::collection::vec(any::<u8>(), 0..512),
+        variant in variant(),
+        quirks in quirks(),
+        toggles in key_toggles(),
+    ) {
+        let mut cpu = cpu_with(&rom, variant, quirks);
+        run(&mut cpu, &toggles)?;
+    }
+
+    #[test]
+    fn test_random_instructions_never_panic (
+        opcodes in prop::collection::vec(any::<u16>(), 1..64),
+        variant in variant(),
+        quirks in quirks(),
+        toggles in key_toggles(),
+        i in any::<u16>(),
+    ) {
+        // Decodable opcodes only, so most runs get past the first instruction
+        let rom = opcodes.iter()
+            .filter(|&&opcode| Instruction::decode(opcode).is_some())
+            .flat_map(|&opcode| vec![(opcode >> 8) as u8, opcode as u8])
+            .collect::<Vec<u8>>();
+        let mut cpu = cpu_with(&rom, variant, quirks);
+        cpu.registers.i = i;
+        run(&mut cpu, &toggles)?;
+    }
+
+    #[test]
+    fn test_states_round_trip_after_random_runs (
+        rom in prop::collection::vec(any::<u8>(), 0..256),
+        variant in variant(),
+        toggles in key_toggles(),
+    ) {
+        let mut cpu = cpu_with(&rom, variant, variant.default_quirks());
+        run(&mut cpu, &toggles)?;
+        let state = cpu.save_state();
+
+        let mut other = cpu_with(&[], Variant::Chip8, Quirks::default());
+        other.load_state(&state).unwrap();
+
+        prop_assert_eq!(other.save_state(), state);
+    }
+
+    #[test]
+    fn test_store_then_load_restores_registers (
+        v in any::<[u8; 16]>(),
+        x in 0..16u8,
+        i in 0x300..0xFF0u16,
+        quirks in quirks(),
+    ) {
+        // LD [I], Vx ; LD I, nnn ; clobbered registers ; LD Vx, [I]
+        let store = 0xF055 | ((x as u16) << 8);
+        let load = 0xF065 | ((x as u16) << 8);
+        let rom = [(store >> 8) as u8, store as u8, 0xA0 | (i >> 8) as u8, i as u8, (load >> 8) as u8, load as u8];
+        let mut cpu = cpu_with(&rom, Variant::Chip8, quirks);
+        cpu.registers.v = v;
+        cpu.registers.i = i;
+
+        cpu.execute_next_op_code().unwrap();
+        cpu.execute_next_op_code().unwrap();
+        cpu.registers.v = [0xAA; 16];
+        cpu.execute_next_op_code().unwrap();
+
+        let x = x as usize;
+        prop_assert_eq!(&cpu.registers.v[..=x], &v[..=x]);
+        prop_assert!(cpu.registers.v[x + 1..].iter().all(|&value| value == 0xAA));
+    }
+
+    #[test]
+    fn test_add_and_sub_match_wide_arithmetic (a in any::<u8>(), b in any::<u8>(), x in 0..15u8, y in 0..15u8) {
+        prop_assume!(x != y);
+        for &(operation, result, flag) in &[
+            (0x4, (a as u16 + b as u16) as u8, (a as u16 + b as u16 > 0xFF) as u8),
+            (0x5, a.wrapping_sub(b), (a >= b) as u8),
+            (0x7, b.wrapping_sub(a), (b >= a) as u8),
+        ] {
+            let opcode = 0x8000 | ((x as u16) << 8) | ((y as u16) << 4) | operation;
+            let mut cpu = cpu_with(&[(opcode >> 8) as u8, opcode as u8], Variant::Chip8, Quirks::default());
+            cpu.registers.v[x as usize] = a;
+            cpu.registers.v[y as usize] = b;
+
+            cpu.execute_next_op_code().unwrap();
+
+            prop_assert_eq!(cpu.registers.v[x as usize], result);
+            prop_assert_eq!(cpu.registers.v[0xF], flag);
+        }
+    }
+
+    #[test]
+    fn test_any_opcode_decodes_without_panicking (opcode in any::<u16>()) {
+        let cpu = cpu_with(&[], Variant::XoChip, Quirks::default());
+        if let Ok(instruction) = cpu.decode_op_code(opcode) {
+            prop_assert!(!instruction.to_string().is_empty());
+        }
+    }
+}
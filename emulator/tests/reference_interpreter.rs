@@ -0,0 +1,309 @@
+#![allow(dead_code)]
+
+extern crate chip8_emulator;
+
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::random::{RandomSource, SeededRandom};
+
+// A deliberately naive CHIP-8 interpreter written straight from the instruction list, used as
+// the reference Cpu is checked against in differential_test.rs. It only knows the original
+// 35 instructions, keeps no caches or lookup tables, and decodes every opcode with one match
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+const STACK_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Ran,
+    // Fx0A is waiting, the program counter stays on it
+    Waiting,
+    // Fx0A got its key and moved past itself, without running anything else
+    KeyTaken,
+}
+
+pub struct Reference {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub memory: Vec<u8>,
+    pub display: [[bool; WIDTH]; HEIGHT],
+    pub keys: [bool; 16],
+    pub quirks: Quirks,
+
+    // Keys pressed since the previous step
+    just_pressed: [bool; 16],
+    // Register Fx0A is filling, and the key pressed while waiting if any
+    waiting: Option<(usize, Option<u8>)>,
+    rng: SeededRandom,
+}
+
+impl Reference {
+    // `memory` is the whole address space with the font and program already in place
+    pub fn new (memory: Vec<u8>, quirks: Quirks, seed: u64) -> Reference {
+        Reference {
+            v: [0; 16],
+            i: 0,
+            pc: 0x200,
+            stack: Vec::new(),
+            delay_timer: 0,
+            sound_timer: 0,
+            memory: memory,
+            display: [[false; WIDTH]; HEIGHT],
+            keys: [false; 16],
+            quirks: quirks,
+            just_pressed: [false; 16],
+            waiting: None,
+            rng: SeededRandom::new(seed),
+        }
+    }
+
+    pub fn key_wait_register (&self) -> Option<u8> {
+        self.waiting.map(|(x, _)| x as u8)
+    }
+
+    pub fn press_key (&mut self, key: u8) {
+        if !self.keys[key as usize] {
+            self.just_pressed[key as usize] = true;
+        }
+        self.keys[key as usize] = true;
+    }
+
+    pub fn release_key (&mut self, key: u8) {
+        self.keys[key as usize] = false;
+    }
+
+    pub fn tick_timers (&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    pub fn step (&mut self) -> Result<Step, String> {
+        let result = match self.waiting {
+            Some((x, pressed)) => Ok(self.wait_for_key(x, pressed)),
+            None => self.execute(),
+        };
+        self.just_pressed = [false; 16];
+        result
+    }
+
+    // A key has to be pressed while waiting and counts once it is released, unless the
+    // key_wait_on_press quirk takes any held key straight away
+    fn wait_for_key (&mut self, x: usize, pressed: Option<u8>) -> Step {
+        let key = if self.quirks.key_wait_on_press {
+            (0..16).find(|&key| self.keys[key as usize])
+        } else {
+            let pressed = pressed.or_else(|| (0..16).find(|&key| self.just_pressed[key as usize]));
+            self.waiting = Some((x, pressed));
+            pressed.filter(|&key| !self.keys[key as usize])
+        };
+
+        match key {
+            Some(key) => {
+                self.v[x] = key;
+                self.waiting = None;
+                self.pc = ((self.pc as usize + 2) % self.memory.len()) as u16;
+                Step::KeyTaken
+            },
+            None => Step::Waiting,
+        }
+    }
+
+    fn read (&self, address: usize) -> Result<u8, String> {
+        match self.memory.get(address) {
+            Some(&value) => Ok(value),
+            None => Err(format!("read outside memory at {:#06X}", address)),
+        }
+    }
+
+    fn write (&mut self, address: usize, value: u8) -> Result<(), String> {
+        match self.memory.get_mut(address) {
+            Some(byte) => {
+                *byte = value;
+                Ok(())
+            },
+            None => Err(format!("write outside memory at {:#06X}", address)),
+        }
+    }
+
+    fn execute (&mut self) -> Result<Step, String> {
+        let pc = self.pc as usize;
+        let opcode = ((self.read(pc)? as u16) << 8) | self.read(pc + 1)? as u16;
+        let x = ((opcode >> 8) & 0xF) as usize;
+        let y = ((opcode >> 4) & 0xF) as usize;
+        let n = (opcode & 0xF) as usize;
+        let kk = (opcode & 0xFF) as u8;
+        let nnn = opcode & 0xFFF;
+        let unknown = || Err(format!("unknown opcode {:04X}", opcode));
+
+        let mut next_pc = pc + 2;
+        match opcode >> 12 {
+            0x0 if opcode == 0x00E0 => {
+                self.display = [[false; WIDTH]; HEIGHT];
+            },
+            0x0 if opcode == 0x00EE => {
+                match self.stack.pop() {
+                    Some(address) => next_pc = address as usize,
+                    None => return Err("return with an empty stack".to_string()),
+                }
+            },
+            0x1 => {
+                next_pc = nnn as usize;
+            },
+            0x2 => {
+                if self.stack.len() == STACK_SIZE {
+                    return Err("call with a full stack".to_string());
+                }
+                self.stack.push((pc + 2) as u16);
+                next_pc = nnn as usize;
+            },
+            0x3 => {
+                if self.v[x] == kk { next_pc += 2; }
+            },
+            0x4 => {
+                if self.v[x] != kk { next_pc += 2; }
+            },
+            0x5 if n == 0 => {
+                if self.v[x] == self.v[y] { next_pc += 2; }
+            },
+            0x6 => {
+                self.v[x] = kk;
+            },
+            0x7 => {
+                self.v[x] = self.v[x].wrapping_add(kk);
+            },
+            0x8 => {
+                let (a, b) = (self.v[x], self.v[y]);
+                // VF is written after the result, so it holds the flag when x is F
+                match n {
+                    0x0 => self.v[x] = b,
+                    0x1..=0x3 => {
+                        self.v[x] = match n { 0x1 => a | b, 0x2 => a & b, _ => a ^ b };
+                        if self.quirks.logic_resets_vf {
+                            self.v[0xF] = 0;
+                        }
+                    },
+                    0x4 => {
+                        let sum = a as u16 + b as u16;
+                        self.v[x] = (sum & 0xFF) as u8;
+                        self.v[0xF] = if sum > 0xFF { 1 } else { 0 };
+                    },
+                    0x5 => {
+                        self.v[x] = a.wrapping_sub(b);
+                        self.v[0xF] = if a >= b { 1 } else { 0 };
+                    },
+                    0x7 => {
+                        self.v[x] = b.wrapping_sub(a);
+                        self.v[0xF] = if b >= a { 1 } else { 0 };
+                    },
+                    0x6 => {
+                        let source = if self.quirks.shift_uses_vy { b } else { a };
+                        self.v[x] = source / 2;
+                        self.v[0xF] = source % 2;
+                    },
+                    0xE => {
+                        let source = if self.quirks.shift_uses_vy { b } else { a };
+                        self.v[x] = source.wrapping_mul(2);
+                        self.v[0xF] = if source >= 0x80 { 1 } else { 0 };
+                    },
+                    _ => return unknown(),
+                }
+            },
+            0x9 if n == 0 => {
+                if self.v[x] != self.v[y] { next_pc += 2; }
+            },
+            0xA => {
+                self.i = nnn;
+            },
+            0xB => {
+                let offset = if self.quirks.jump_uses_vx { self.v[x] } else { self.v[0] };
+                next_pc = nnn as usize + offset as usize;
+            },
+            0xC => {
+                self.v[x] = self.rng.next_byte() & kk;
+            },
+            0xD => {
+                self.draw(x, y, n)?;
+            },
+            0xE if kk == 0x9E => {
+                if self.keys[(self.v[x] & 0xF) as usize] { next_pc += 2; }
+            },
+            0xE if kk == 0xA1 => {
+                if !self.keys[(self.v[x] & 0xF) as usize] { next_pc += 2; }
+            },
+            0xF => {
+                match kk {
+                    0x07 => self.v[x] = self.delay_timer,
+                    0x0A => {
+                        self.waiting = Some((x, None));
+                        return Ok(Step::Waiting);
+                    },
+                    0x15 => self.delay_timer = self.v[x],
+                    0x18 => self.sound_timer = self.v[x],
+                    0x1E => self.i = self.i.wrapping_add(self.v[x] as u16),
+                    0x29 => self.i = (self.v[x] & 0xF) as u16 * 5,
+                    0x33 => {
+                        let i = self.i as usize;
+                        let value = self.v[x];
+                        self.write(i, value / 100)?;
+                        self.write(i + 1, value / 10 % 10)?;
+                        self.write(i + 2, value % 10)?;
+                    },
+                    0x55 => {
+                        for register in 0..=x {
+                            let value = self.v[register];
+                            let address = self.i as usize + register;
+                            self.write(address, value)?;
+                        }
+                        if self.quirks.load_store_increments_i {
+                            self.i = self.i.wrapping_add(x as u16 + 1);
+                        }
+                    },
+                    0x65 => {
+                        for register in 0..=x {
+                            self.v[register] = self.read(self.i as usize + register)?;
+                        }
+                        if self.quirks.load_store_increments_i {
+                            self.i = self.i.wrapping_add(x as u16 + 1);
+                        }
+                    },
+                    _ => return unknown(),
+                }
+            },
+            _ => return unknown(),
+        }
+
+        self.pc = (next_pc % self.memory.len()) as u16;
+        Ok(Step::Ran)
+    }
+
+    // Sprites are 8 pixels wide and `rows` tall, starting at Vx, Vy wrapped onto the screen.
+    // Pixels past the edges wrap around too, or are dropped with the clip_sprites quirk
+    fn draw (&mut self, x: usize, y: usize, rows: usize) -> Result<(), String> {
+        let left = self.v[x] as usize % WIDTH;
+        let top = self.v[y] as usize % HEIGHT;
+        self.v[0xF] = 0;
+
+        for row in 0..rows {
+            if self.quirks.clip_sprites && top + row >= HEIGHT {
+                break;
+            }
+            let byte = self.read(self.i as usize + row)?;
+            for column in 0..8 {
+                if self.quirks.clip_sprites && left + column >= WIDTH {
+                    break;
+                }
+                if byte & (0x80 >> column) != 0 {
+                    let pixel = &mut self.display[(top + row) % HEIGHT][(left + column) % WIDTH];
+                    if *pixel {
+                        self.v[0xF] = 1;
+                    }
+                    *pixel = !*pixel;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+extern crate chip8_emulator;
+
+mod cpu_fixture;
+use chip8_emulator::cpu::*;
+use chip8_emulator::rewind::*;
+use cpu_fixture::CpuFixture;
+
+fn counting_fixture() -> CpuFixture {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0x7001, 0x1200]);
+    fixture
+}
+
+fn run_frames(cpu: &mut Cpu, rewind: &mut Rewind, frames: u32) {
+    for _ in 0..frames {
+        cpu.execute_cycle().unwrap();
+        rewind.record_frame(cpu);
+    }
+}
+
+#[test]
+fn test_rewind_restores_earlier_frame() {
+    let mut fixture = counting_fixture();
+    let mut rewind = Rewind::new(100, 1);
+
+    run_frames(&mut fixture.cpu, &mut rewind, 10);
+    assert_eq!(fixture.cpu.registers.v[0], 40);
+
+    let rewound = rewind.rewind(&mut fixture.cpu, 3).unwrap();
+
+    assert_eq!(rewound, 3);
+    assert_eq!(fixture.cpu.registers.v[0], 28);
+}
+
+#[test]
+fn test_rewind_with_capture_interval() {
+    let mut fixture = counting_fixture();
+    let mut rewind = Rewind::new(100, 4);
+
+    run_frames(&mut fixture.cpu, &mut rewind, 10);
+    assert_eq!(rewind.len(), 3);
+
+    let rewound = rewind.rewind(&mut fixture.cpu, 3).unwrap();
+
+    assert_eq!(rewound, 5);
+    assert_eq!(fixture.cpu.registers.v[0], 20);
+}
+
+#[test]
+fn test_rewind_repeatedly() {
+    let mut fixture = counting_fixture();
+    let mut rewind = Rewind::new(100, 1);
+    run_frames(&mut fixture.cpu, &mut rewind, 10);
+
+    rewind.rewind(&mut fixture.cpu, 1).unwrap();
+    assert_eq!(fixture.cpu.registers.v[0], 36);
+    rewind.rewind(&mut fixture.cpu, 1).unwrap();
+    assert_eq!(fixture.cpu.registers.v[0], 32);
+
+    run_frames(&mut fixture.cpu, &mut rewind, 2);
+    assert_eq!(fixture.cpu.registers.v[0], 40);
+    rewind.rewind(&mut fixture.cpu, 2).unwrap();
+    assert_eq!(fixture.cpu.registers.v[0], 32);
+}
+
+#[test]
+fn test_rewind_is_bounded() {
+    let mut fixture = counting_fixture();
+    let mut rewind = Rewind::new(5, 1);
+    run_frames(&mut fixture.cpu, &mut rewind, 50);
+
+    assert_eq!(rewind.len(), 5);
+
+    let rewound = rewind.rewind(&mut fixture.cpu, 1000).unwrap();
+
+    assert_eq!(rewound, 4);
+    assert_eq!(fixture.cpu.registers.v[0], 184);
+}
+
+#[test]
+fn test_rewind_across_keyframes_after_eviction() {
+    let mut fixture = counting_fixture();
+    let mut rewind = Rewind::new(45, 1);
+    run_frames(&mut fixture.cpu, &mut rewind, 60);
+
+    rewind.rewind(&mut fixture.cpu, 44).unwrap();
+
+    assert_eq!(fixture.cpu.registers.v[0], (16 * 4) as u8);
+}
+
+#[test]
+fn test_rewind_snapshots_are_delta_compressed() {
+    let mut fixture = counting_fixture();
+    let mut rewind = Rewind::new(20, 1);
+    run_frames(&mut fixture.cpu, &mut rewind, 20);
+
+    let full_size = fixture.cpu.save_state().len();
+    assert!(rewind.size_in_bytes() < full_size * 2);
+}
+
+#[test]
+fn test_rewind_empty_buffer() {
+    let mut fixture = counting_fixture();
+    let mut rewind = Rewind::default();
+
+    assert!(rewind.is_empty());
+    assert_eq!(rewind.rewind(&mut fixture.cpu, 10).unwrap(), 0);
+    assert_eq!(fixture.cpu.program_counter, 0x200);
+}
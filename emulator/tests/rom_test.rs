@@ -0,0 +1,161 @@
+extern crate chip8_emulator;
+
+mod snapshot_file;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::keypad::{KeyChange, Keypad};
+use chip8_emulator::machine::Machine;
+use chip8_emulator::movie::MovieEvent;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::snapshot::{self, Snapshot};
+use chip8_emulator::variant::Variant;
+use snapshot_file::check_file_or_bless;
+
+// Each ROM runs headless with the given quirks and scripted key presses, and its display at
+// the listed frames has to match `tests/roms/<name>.snap`, see snapshot_file.rs
+struct RomCase {
+    name: &'static str,
+    // Relative to the crate root
+    path: &'static str,
+    variant: Variant,
+    quirks: fn() -> Quirks,
+    events: &'static [MovieEvent],
+    frames: &'static [u32],
+}
+
+const fn press (frame: u32, key: u8) -> MovieEvent {
+    MovieEvent { frame: frame, change: KeyChange { key: key, pressed: true } }
+}
+
+const fn release (frame: u32, key: u8) -> MovieEvent {
+    MovieEvent { frame: frame, change: KeyChange { key: key, pressed: false } }
+}
+
+const ROM_CASES: &[RomCase] = &[
+    RomCase {
+        name: "flags", path: "tests/roms/flags.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[], frames: &[120],
+    },
+    RomCase {
+        name: "display", path: "tests/roms/display.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[], frames: &[60],
+    },
+    RomCase {
+        name: "quirks_vip", path: "tests/roms/quirks.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[], frames: &[60],
+    },
+    RomCase {
+        name: "quirks_schip", path: "tests/roms/quirks.ch8", variant: Variant::Chip8, quirks: Quirks::schip,
+        events: &[], frames: &[60],
+    },
+    RomCase {
+        name: "quirks_xo_chip", path: "tests/roms/quirks.ch8", variant: Variant::Chip8, quirks: Quirks::xo_chip,
+        events: &[], frames: &[60],
+    },
+    RomCase {
+        name: "brix", path: "../web/roms/BRIX.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[press(120, 0x4), release(150, 0x4)], frames: &[60, 300],
+    },
+    RomCase {
+        name: "pong2", path: "../web/roms/PONG2.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[press(60, 0x1), release(90, 0x1)], frames: &[60, 300],
+    },
+    RomCase {
+        name: "tetris", path: "../web/roms/TETRIS.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[press(100, 0x6), release(110, 0x6), press(150, 0x4), release(160, 0x4)], frames: &[60, 300],
+    },
+    RomCase {
+        name: "ufo", path: "../web/roms/UFO.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[press(100, 0x5), release(105, 0x5)], frames: &[60, 300],
+    },
+];
+
+// Community test suites, fetched into tests/roms/suites by tests/roms/fetch-suites.sh since they
+// aren't redistributed here. Their snapshots live next to them and need blessing after a fetch.
+// Missing ROMs skip the test locally but fail it under CI, where the suites have to be fetched
+const SUITE_CASES: &[RomCase] = &[
+    RomCase {
+        name: "corax", path: "tests/roms/suites/3-corax+.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[], frames: &[120],
+    },
+    RomCase {
+        name: "flags", path: "tests/roms/suites/4-flags.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[], frames: &[120],
+    },
+    // The menu picks the platform, key 1 for CHIP-8
+    RomCase {
+        name: "quirks", path: "tests/roms/suites/5-quirks.ch8", variant: Variant::Chip8, quirks: Quirks::cosmac_vip,
+        events: &[press(30, 0x1), release(35, 0x1)], frames: &[300],
+    },
+];
+
+fn crate_path (path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(path)
+}
+
+fn run_rom (case: &RomCase) -> (Machine, Vec<Snapshot>) {
+    let rom = fs::read(crate_path(case.path)).unwrap();
+    let mut cpu = Cpu::new(Keypad::new(), case.variant, (case.quirks)());
+    cpu.load_program(&rom);
+    let mut machine = Machine::new(cpu);
+
+    let snapshots = snapshot::capture(&mut machine, case.events, case.frames).unwrap_or_else(|e| panic!("{}: {}", case.name, e));
+    (machine, snapshots)
+}
+
+fn check_snapshots (cases: &[RomCase], directory: &str) -> Vec<String> {
+    cases.iter()
+        .filter_map(|case| {
+            let path = crate_path(&format!("{}/{}.snap", directory, case.name));
+            check_file_or_bless(path, &run_rom(case).1).err()
+        })
+        .collect()
+}
+
+#[test]
+fn test_roms_match_snapshots() {
+    let failures = check_snapshots(ROM_CASES, "tests/roms");
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_community_suites_match_snapshots() {
+    let fetched = SUITE_CASES.iter().filter(|case| crate_path(case.path).exists()).collect::<Vec<&RomCase>>();
+    if fetched.is_empty() {
+        assert!(env::var_os("CI").is_none(), "the community test suites are missing, run tests/roms/fetch-suites.sh");
+        println!("skipped, run tests/roms/fetch-suites.sh to fetch the community test suites");
+        return;
+    }
+    assert_eq!(fetched.len(), SUITE_CASES.len(), "tests/roms/suites is incomplete, run tests/roms/fetch-suites.sh again");
+
+    let failures = check_snapshots(SUITE_CASES, "tests/roms/suites");
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_flags_rom_passes_every_check() {
+    // One check per `CALL check` in the source, so a new one can't be left out
+    let source = fs::read_to_string(crate_path("tests/roms/flags.asm")).unwrap();
+    let checks = source.lines().filter(|line| line.trim() == "CALL check").count();
+
+    let (machine, snapshots) = run_rom(&ROM_CASES[0]);
+    let v = machine.cpu.registers.v;
+    let display = &snapshots[0].display;
+
+    assert_eq!(v[0xE], 1, "flags.ch8 didn't finish\n{}", display);
+    assert_eq!(v[0xD] as usize, checks, "flags.ch8 ran {} of {} checks\n{}", v[0xD], checks, display);
+    assert_eq!(v[0xC], v[0xD], "{} of {} checks passed\n{}", v[0xC], v[0xD], display);
+}
+
+#[test]
+fn test_quirks_rom_detects_quirks() {
+    for case in ROM_CASES.iter().filter(|case| case.path == "tests/roms/quirks.ch8") {
+        let (machine, _) = run_rom(case);
+        // The ROM can't observe key_wait_on_press
+        let expected = (case.quirks)().to_bits() & 0x1F;
+
+        assert_eq!(machine.cpu.registers.v[0xE], expected, "{}", case.name);
+    }
+}
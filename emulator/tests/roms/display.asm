@@ -0,0 +1,61 @@
+; Draws every font glyph, a sprite crossing both screen edges and
+; shows the collision flag of an overlapping draw as a glyph.
+; Rebuild from ./assembler with `cargo run --bin chip8-asm -- ../emulator/tests/roms/display.asm -o ../emulator/tests/roms/display.ch8`
+
+    CLS
+    LD V0, 0
+    LD V1, 1
+    LD V2, 1
+
+glyphs:
+    LD F, V0
+    DRW V1, V2, 5
+    ADD V1, 5
+    ADD V0, 1
+    SE V0, 8
+    JP next
+    LD V1, 1
+    LD V2, 7
+next:
+    SE V0, 16
+    JP glyphs
+
+    ; Fx29 only looks at the low nibble, so 0x3A draws an A
+    LD V0, 0x3A
+    LD F, V0
+    LD V1, 44
+    LD V2, 1
+    DRW V1, V2, 5
+
+    ; A box starting near the bottom right corner, clipped or wrapped depending on the quirk
+    LD I, box
+    LD V1, 124
+    LD V2, 61
+    DRW V1, V2, 4
+
+    ; Overlapping draw, the glyph for VF shows whether the collision was seen
+    LD I, box
+    LD V1, 44
+    LD V2, 14
+    DRW V1, V2, 4
+    DRW V1, V2, 4
+    LD V3, VF
+    LD F, V3
+    LD V1, 50
+    DRW V1, V2, 5
+
+    ; Draw once more without overlap, VF has to be cleared
+    LD I, box
+    LD V1, 44
+    LD V2, 22
+    DRW V1, V2, 4
+    LD V3, VF
+    LD F, V3
+    LD V1, 50
+    DRW V1, V2, 5
+
+end:
+    JP end
+
+box:
+    DB 0b11110000, 0b10010000, 0b10010000, 0b11110000
@@ -0,0 +1,16 @@
+#!/bin/sh
+# Fetches Timendus' CHIP-8 test suite, which bundles corax+ and the flags and quirks tests,
+# into tests/roms/suites for rom_test.rs. The ROMs are GPL-3.0 licensed and aren't committed.
+# Takes the suite's commit hash so a run always gets the same ROMs, bless their snapshots with
+# `CHIP8_BLESS=1 cargo test --test rom_test` after checking them by eye and commit the .snap files
+set -e
+
+REVISION=${1:?usage: fetch-suites.sh <commit hash of Timendus/chip8-test-suite>}
+BASE=https://raw.githubusercontent.com/Timendus/chip8-test-suite/$REVISION
+SUITES=$(dirname "$0")/suites
+
+mkdir -p "$SUITES"
+for file in bin/3-corax+.ch8 bin/4-flags.ch8 bin/5-quirks.ch8 LICENSE; do
+    curl -fsSL "$BASE/$file" -o "$SUITES/$(basename "$file")"
+done
+echo "fetched into $SUITES"
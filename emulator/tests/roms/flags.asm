@@ -0,0 +1,200 @@
+; Checks 8xyN results and VF flags, including VF as the destination.
+; Every check draws a mark left to right, a tick passes and a cross fails. VD counts the
+; checks, VC the ones that passed, and VE is set to 1 once they have all run.
+; Rebuild from ./assembler with `cargo run --bin chip8-asm -- ../emulator/tests/roms/flags.asm -o ../emulator/tests/roms/flags.ch8`
+
+    LD VA, 1
+    LD VB, 1
+
+    ; 8xy4 with carry
+    LD V2, 0xFF
+    LD V3, 0x01
+    ADD V2, V3
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0x00
+    CALL check
+    LD V0, V4
+    LD V1, 1
+    CALL check
+
+    ; 8xy4 without carry
+    LD V2, 0x10
+    LD V3, 0x20
+    ADD V2, V3
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0x30
+    CALL check
+    LD V0, V4
+    LD V1, 0
+    CALL check
+
+    ; 8xy4 into VF keeps the carry
+    LD VF, 0xFF
+    LD V3, 0x02
+    ADD VF, V3
+    LD V0, VF
+    LD V1, 1
+    CALL check
+
+    ; 8xy5 without borrow
+    LD V2, 0x30
+    LD V3, 0x10
+    SUB V2, V3
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0x20
+    CALL check
+    LD V0, V4
+    LD V1, 1
+    CALL check
+
+    ; 8xy5 with equal operands
+    LD V2, 0x42
+    LD V3, 0x42
+    SUB V2, V3
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0x00
+    CALL check
+    LD V0, V4
+    LD V1, 1
+    CALL check
+
+    ; 8xy5 with borrow wraps
+    LD V2, 0x10
+    LD V3, 0x30
+    SUB V2, V3
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0xE0
+    CALL check
+    LD V0, V4
+    LD V1, 0
+    CALL check
+
+    ; 8xy5 into VF keeps the flag
+    LD VF, 0x30
+    LD V3, 0x10
+    SUB VF, V3
+    LD V0, VF
+    LD V1, 1
+    CALL check
+
+    ; 8xy7 without borrow
+    LD V2, 0x10
+    LD V3, 0x30
+    SUBN V2, V3
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0x20
+    CALL check
+    LD V0, V4
+    LD V1, 1
+    CALL check
+
+    ; 8xy7 with borrow wraps
+    LD V2, 0x30
+    LD V3, 0x10
+    SUBN V2, V3
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0xE0
+    CALL check
+    LD V0, V4
+    LD V1, 0
+    CALL check
+
+    ; 8xy6 shifts out a one
+    LD V2, 0x05
+    SHR V2
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0x02
+    CALL check
+    LD V0, V4
+    LD V1, 1
+    CALL check
+
+    ; 8xy6 into VF keeps the flag
+    LD VF, 0x04
+    SHR VF
+    LD V0, VF
+    LD V1, 0
+    CALL check
+
+    ; 8xyE shifts out a one
+    LD V2, 0x81
+    SHL V2
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0x02
+    CALL check
+    LD V0, V4
+    LD V1, 1
+    CALL check
+
+    ; 8xyE into VF keeps the flag
+    LD VF, 0x40
+    SHL VF
+    LD V0, VF
+    LD V1, 0
+    CALL check
+
+    ; 7xkk wraps and leaves VF alone
+    LD VF, 0x05
+    LD V2, 0xFF
+    ADD V2, 0x02
+    LD V4, VF
+    LD V0, V2
+    LD V1, 0x01
+    CALL check
+    LD V0, V4
+    LD V1, 0x05
+    CALL check
+
+    ; Fx33 splits 255 into digits
+    LD V2, 255
+    LD I, scratch
+    LD B, V2
+    LD V2, [I]
+    LD V5, V0
+    LD V6, V1
+    LD V7, V2
+    LD V0, V5
+    LD V1, 2
+    CALL check
+    LD V0, V6
+    LD V1, 5
+    CALL check
+    LD V0, V7
+    LD V1, 5
+    CALL check
+
+    LD VE, 1
+end:
+    JP end
+
+; Draws a tick when V0 == V1 and a cross otherwise at VA, VB, then moves along
+check:
+    ADD VD, 1
+    LD I, pass
+    SE V0, V1
+    LD I, fail
+    SNE V0, V1
+    ADD VC, 1
+    DRW VA, VB, 4
+    ADD VA, 5
+    SE VA, 61
+    RET
+    LD VA, 1
+    ADD VB, 6
+    RET
+
+pass:
+    DB 0b00010000, 0b00100000, 0b10100000, 0b01000000
+fail:
+    DB 0b10010000, 0b01100000, 0b01100000, 0b10010000
+scratch:
+    DB 0, 0, 0
@@ -0,0 +1,71 @@
+; Detects which quirks the interpreter runs with and leaves them in VE, laid out like
+; Quirks::to_bits, then shows VE in decimal in the top-left corner.
+; Rebuild from ./assembler with `cargo run --bin chip8-asm -- ../emulator/tests/roms/quirks.asm -o ../emulator/tests/roms/quirks.ch8`
+
+    LD VE, 0
+
+    ; jump_uses_vx, B2nn jumps through V2 instead of V0. Has to stay below 0x300 for that
+    LD V0, 0
+    LD V2, 2
+    JP V0, jumps
+jumps:
+    JP jumped
+    ADD VE, 4
+jumped:
+
+    ; shift_uses_vy, 8xy6 shifts Vy into Vx
+    LD V1, 0x04
+    LD V2, 0x10
+    SHR V1, V2
+    SNE V1, 0x08
+    ADD VE, 1
+
+    ; load_store_increments_i, a second Fx65 reads the next byte
+    LD I, pair
+    LD V0, [I]
+    LD V0, [I]
+    SNE V0, 0x22
+    ADD VE, 2
+
+    ; clip_sprites, a row drawn across the right edge doesn't come back on the left
+    CLS
+    LD I, row
+    LD V1, 60
+    LD V2, 0
+    DRW V1, V2, 1
+    LD I, dot
+    LD V1, 0
+    DRW V1, V2, 1
+    SNE VF, 0
+    ADD VE, 8
+    CLS
+
+    ; logic_resets_vf, 8xy1 clears VF
+    LD VF, 5
+    OR V1, V2
+    SNE VF, 0
+    ADD VE, 16
+
+    LD I, digits
+    LD B, VE
+    LD I, digits
+    LD V2, [I]
+    LD V3, 1
+    LD V4, 1
+    LD F, V1
+    DRW V3, V4, 5
+    LD V3, 6
+    LD F, V2
+    DRW V3, V4, 5
+
+end:
+    JP end
+
+pair:
+    DB 0x11, 0x22
+row:
+    DB 0xFF
+dot:
+    DB 0x80
+digits:
+    DB 0, 0, 0
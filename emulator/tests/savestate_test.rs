@@ -0,0 +1,199 @@
+extern crate chip8_emulator;
+
+mod cpu_fixture;
+use chip8_emulator::checksum::crc32;
+use chip8_emulator::error::*;
+use chip8_emulator::quirks::*;
+use chip8_emulator::savestate::*;
+use chip8_emulator::variant::*;
+use cpu_fixture::CpuFixture;
+
+fn running_fixture() -> CpuFixture {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[
+        0xA600, // I = 0x600
+        0xC0FF, // V0 = random
+        0xF055, // store V0
+        0xD015, // draw
+        0x7101, // V1 += 1
+        0x2210, // call 0x210
+        0x1202, // loop
+        0x0000,
+        0xF115, // delay = V1
+        0x00EE,
+    ]);
+    fixture
+}
+
+fn step(fixture: &mut CpuFixture, count: usize) {
+    for _ in 0..count {
+        fixture.cpu.execute_next_op_code().unwrap();
+    }
+}
+
+fn fix_checksum(state: &mut [u8]) {
+    let len = state.len();
+    let checksum = crc32(&state[..len - 4]);
+    state[len - 4] = checksum as u8;
+    state[len - 3] = (checksum >> 8) as u8;
+    state[len - 2] = (checksum >> 16) as u8;
+    state[len - 1] = (checksum >> 24) as u8;
+}
+
+#[test]
+fn test_save_state_header() {
+    let fixture = running_fixture();
+    let state = fixture.cpu.save_state();
+
+    assert_eq!(&state[0..4], STATE_MAGIC);
+    assert_eq!(state[4] as u16 | (state[5] as u16) << 8, STATE_VERSION);
+}
+
+#[test]
+fn test_load_state_restores_machine() {
+    let mut fixture = running_fixture();
+    step(&mut fixture, 5);
+    fixture.cpu.keypad.press_key(0x7);
+    fixture.cpu.registers.sound_timer = 9;
+    let state = fixture.cpu.save_state();
+
+    let mut other = CpuFixture::new();
+    other.cpu.load_state(&state).unwrap();
+
+    assert_eq!(other.cpu.program_counter, fixture.cpu.program_counter);
+    assert_eq!(other.cpu.registers.v, fixture.cpu.registers.v);
+    assert_eq!(other.cpu.registers.i, fixture.cpu.registers.i);
+    assert_eq!(other.cpu.registers.sound_timer, 9);
+    assert_eq!(other.cpu.stack, fixture.cpu.stack);
+    assert_eq!(other.cpu.stack_pointer, fixture.cpu.stack_pointer);
+    assert_eq!(other.cpu.memory, fixture.cpu.memory);
+    assert_eq!(other.cpu.video_memory, fixture.cpu.video_memory);
+    assert!(other.cpu.keypad.key_is_pressed(0x7));
+    assert_eq!(other.cpu.save_state(), state);
+}
+
+#[test]
+fn test_load_state_restores_key_wait() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xF30A, 0x1202]);
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.keypad.press_key(0xC);
+    fixture.cpu.execute_next_op_code().unwrap();
+    let state = fixture.cpu.save_state();
+
+    let mut other = CpuFixture::new();
+    other.cpu.load_state(&state).unwrap();
+    other.cpu.keypad.release_key(0xC);
+    other.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(other.cpu.registers.v[3], 0xC);
+    assert_eq!(other.cpu.program_counter, 0x202);
+}
+
+#[test]
+fn test_load_state_reads_version_1() {
+    // Version 1 had no awaited key, the wait starts over and takes the next key pressed
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xF30A, 0x1202]);
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.keypad.press_key(0xC);
+    fixture.cpu.execute_next_op_code().unwrap();
+    let mut state = fixture.cpu.save_state();
+    state[4] = 1;
+    state[5] = 0;
+    fix_checksum(&mut state);
+
+    let mut other = CpuFixture::new();
+    other.cpu.load_state(&state).unwrap();
+    other.cpu.keypad.release_key(0xC);
+    other.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(other.cpu.key_wait_register(), Some(3));
+    assert_eq!(other.cpu.registers.v[3], 0);
+
+    other.cpu.keypad.press_key(0x5);
+    other.cpu.keypad.release_key(0x5);
+    other.cpu.execute_next_op_code().unwrap();
+
+    assert_eq!(other.cpu.key_wait_register(), None);
+    assert_eq!(other.cpu.registers.v[3], 0x5);
+}
+
+#[test]
+fn test_load_state_replays_exactly() {
+    let mut fixture = running_fixture();
+    step(&mut fixture, 3);
+    let state = fixture.cpu.save_state();
+
+    step(&mut fixture, 40);
+    let expected = fixture.cpu.save_state();
+
+    fixture.cpu.load_state(&state).unwrap();
+    step(&mut fixture, 40);
+
+    assert_eq!(fixture.cpu.save_state(), expected);
+}
+
+#[test]
+fn test_load_state_restores_variant_and_quirks() {
+    let mut fixture = CpuFixture::with_variant(Variant::XoChip, Quirks::xo_chip());
+    fixture.load_op_codes(&[0x00FF, 0xF201]);
+    step(&mut fixture, 2);
+    let state = fixture.cpu.save_state();
+
+    let mut other = CpuFixture::new();
+    other.cpu.load_state(&state).unwrap();
+
+    assert_eq!(other.cpu.variant, Variant::XoChip);
+    assert_eq!(other.cpu.quirks, Quirks::xo_chip());
+    assert_eq!(other.cpu.memory.len(), 0x10000);
+    assert!(other.cpu.is_hires());
+    assert_eq!(other.cpu.selected_planes(), 2);
+    assert_eq!(other.cpu.video_memory.len(), 64);
+}
+
+#[test]
+fn test_load_state_rejects_bad_magic() {
+    let mut fixture = CpuFixture::new();
+
+    assert_eq!(fixture.cpu.load_state(b"NOPE1234"), Err(StateError::InvalidMagic));
+    assert_eq!(fixture.cpu.load_state(&[]), Err(StateError::InvalidMagic));
+}
+
+#[test]
+fn test_load_state_rejects_corruption() {
+    let mut fixture = running_fixture();
+    step(&mut fixture, 5);
+    let mut state = fixture.cpu.save_state();
+    state[40] ^= 0xFF;
+
+    let mut other = CpuFixture::new();
+    match other.cpu.load_state(&state) {
+        Err(StateError::ChecksumMismatch { .. }) => {},
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(other.cpu.program_counter, 0x200);
+    assert_eq!(other.cpu.registers.v, [0; 16]);
+}
+
+#[test]
+fn test_load_state_rejects_other_versions() {
+    let fixture = running_fixture();
+    let mut state = fixture.cpu.save_state();
+    state[4] = 0xFF;
+    fix_checksum(&mut state);
+
+    let mut other = CpuFixture::new();
+    assert_eq!(other.cpu.load_state(&state), Err(StateError::UnsupportedVersion(0xFF)));
+}
+
+#[test]
+fn test_load_state_rejects_truncated() {
+    let fixture = running_fixture();
+    let state = fixture.cpu.save_state();
+    let mut truncated = state[..state.len() - 100].to_vec();
+    fix_checksum(&mut truncated);
+
+    let mut other = CpuFixture::new();
+    assert_eq!(other.cpu.load_state(&truncated), Err(StateError::Truncated));
+}
@@ -0,0 +1,33 @@
+#![allow(dead_code)]
+
+extern crate chip8_emulator;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use chip8_emulator::snapshot::{compare, parse, to_text, Snapshot};
+
+// Snapshot files checked by the tests, see snapshot.rs for the format. Setting BLESS_ENV_VAR
+// rewrites each file from the run instead of comparing against it
+pub const BLESS_ENV_VAR: &str = "CHIP8_BLESS";
+
+// `check_file`, blessing when BLESS_ENV_VAR is set
+pub fn check_file_or_bless<P: AsRef<Path>> (path: P, actual: &[Snapshot]) -> Result<(), String> {
+    check_file(path, actual, env::var_os(BLESS_ENV_VAR).is_some())
+}
+
+// Compares against the snapshot file at `path`, or rewrites it from `actual` when blessing
+pub fn check_file<P: AsRef<Path>> (path: P, actual: &[Snapshot], bless: bool) -> Result<(), String> {
+    let path = path.as_ref();
+    if bless {
+        return fs::write(path, to_text(actual)).map_err(|e| format!("cannot write {}: {}", path.display(), e));
+    }
+
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("cannot read {}: {}, run with {}=1 to create it", path.display(), e, BLESS_ENV_VAR))?;
+    let expected = parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    match compare(&expected, actual) {
+        Some(report) => Err(format!("{} doesn't match, run with {}=1 to update it\n{}", path.display(), BLESS_ENV_VAR, report)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,115 @@
+extern crate chip8_emulator;
+
+mod cpu_fixture;
+mod snapshot_file;
+use std::env;
+use std::fs;
+use chip8_emulator::keypad::KeyChange;
+use chip8_emulator::machine::Machine;
+use chip8_emulator::movie::MovieEvent;
+use chip8_emulator::snapshot::*;
+use cpu_fixture::CpuFixture;
+use snapshot_file::*;
+
+fn snapshot (frame: u32, display: &str) -> Snapshot {
+    Snapshot { frame: frame, display: display.to_string() }
+}
+
+#[test]
+fn test_capture_applies_events() {
+    // Wait for a key and draw its glyph in the top-left corner
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xF00A, 0xF029, 0xD115, 0x1206]);
+    let mut machine = Machine::new(fixture.cpu);
+    let events = [
+        MovieEvent { frame: 2, change: KeyChange { key: 0x1, pressed: true } },
+        MovieEvent { frame: 3, change: KeyChange { key: 0x1, pressed: false } },
+    ];
+
+    let snapshots = capture(&mut machine, &events, &[1, 4]).unwrap();
+
+    assert_eq!(snapshots.iter().map(|snapshot| snapshot.frame).collect::<Vec<u32>>(), vec![1, 4]);
+    assert!(!snapshots[0].display.contains('#'));
+    let rows = snapshots[1].display.lines().take(5).map(|row| &row[..4]).collect::<Vec<&str>>();
+    assert_eq!(rows, vec!["..#.", ".##.", "..#.", "..#.", ".###"]);
+}
+
+#[test]
+fn test_to_ascii() {
+    // Draws the top row of the 0 glyph at 0,0
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xA000, 0xD001]);
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    let ascii = to_ascii(&fixture.cpu);
+    let lines = ascii.lines().collect::<Vec<&str>>();
+
+    assert_eq!(lines.len(), 32);
+    assert_eq!(lines[0], format!("####{}", ".".repeat(60)));
+    assert_eq!(lines[1], ".".repeat(64));
+}
+
+#[test]
+fn test_text_round_trip() {
+    let snapshots = vec![snapshot(60, "#.\n.#\n"), snapshot(120, "..\n##\n")];
+
+    let text = to_text(&snapshots);
+
+    assert_eq!(text, "# frame 60\n#.\n.#\n# frame 120\n..\n##\n");
+    assert_eq!(parse(&text), Ok(snapshots));
+    assert_eq!(parse("#.\n"), Err("line 1: expected '# frame <n>'".to_string()));
+    assert_eq!(parse("# frame x\n"), Err("line 1: invalid frame number".to_string()));
+}
+
+#[test]
+fn test_side_by_side_diff() {
+    let diff = side_by_side_diff("#.\n.#\n", "#.\n##\n");
+
+    assert_eq!(diff, "  expected | actual\n  #.       | #.\n> .#       | ##\n");
+}
+
+#[test]
+fn test_compare_reports_differences() {
+    let expected = vec![snapshot(60, "#.\n.#\n"), snapshot(90, "..\n..\n")];
+    let actual = vec![snapshot(60, "#.\n##\n"), snapshot(120, "..\n..\n")];
+
+    assert_eq!(compare(&expected, &expected), None);
+
+    let report = compare(&expected, &actual).unwrap();
+    assert!(report.starts_with("frame 60: 1 pixels differ\n"));
+    assert!(report.contains("> .#       | ##\n"));
+    assert!(report.contains("frame 120: no snapshot recorded\n"));
+    assert!(report.contains("frame 90: snapshot recorded but not captured\n"));
+}
+
+#[test]
+fn test_check_file() {
+    let path = env::temp_dir().join(format!("chip8-snapshot-test-{}.snap", std::process::id()));
+    let snapshots = vec![snapshot(60, "#.\n.#\n")];
+    let _ = fs::remove_file(&path);
+
+    let missing = check_file(&path, &snapshots, false).unwrap_err();
+    assert!(missing.contains(BLESS_ENV_VAR));
+
+    fs::write(&path, to_text(&snapshots)).unwrap();
+    assert_eq!(check_file(&path, &snapshots, false), Ok(()));
+    let mismatch = check_file(&path, &[snapshot(60, "##\n.#\n")], false);
+    fs::remove_file(&path).unwrap();
+
+    assert!(mismatch.unwrap_err().contains("> #.       | ##\n"));
+}
+
+#[test]
+fn test_check_file_blesses() {
+    let path = env::temp_dir().join(format!("chip8-snapshot-bless-test-{}.snap", std::process::id()));
+    let snapshots = vec![snapshot(60, "#.\n.#\n")];
+    fs::write(&path, "# frame 60\n..\n..\n").unwrap();
+
+    let blessed = check_file(&path, &snapshots, true);
+    let text = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(blessed, Ok(()));
+    assert_eq!(text, to_text(&snapshots));
+}
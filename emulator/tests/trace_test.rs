@@ -0,0 +1,112 @@
+extern crate chip8_emulator;
+
+mod cpu_fixture;
+use std::cell::RefCell;
+use std::rc::Rc;
+use chip8_emulator::debugger::Register;
+use chip8_emulator::instruction::Instruction;
+use chip8_emulator::trace::*;
+use cpu_fixture::CpuFixture;
+
+#[test]
+fn test_trace_records_register_changes() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0x6042]);
+    fixture.cpu.set_trace_sink(TraceSink::Buffer(TraceBuffer::new(8)));
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    let entries = fixture.cpu.trace_buffer().unwrap().entries().cloned().collect::<Vec<TraceEntry>>();
+    assert_eq!(entries, vec![TraceEntry {
+        pc: 0x200,
+        opcode: 0x6042,
+        instruction: Some(Instruction::LoadImm(0, 0x42)),
+        register_changes: vec![RegisterChange { register: Register::V(0), old: 0, new: 0x42 }],
+        memory_writes: vec![],
+    }]);
+}
+
+#[test]
+fn test_trace_records_memory_writes() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xA300, 0x6042, 0xF055]);
+    fixture.cpu.set_trace_sink(TraceSink::Buffer(TraceBuffer::new(8)));
+
+    for _ in 0..3 {
+        fixture.cpu.execute_next_op_code().unwrap();
+    }
+
+    assert_eq!(fixture.cpu.trace_buffer().unwrap().to_string(), "\
+0200 A300 I=0300 ; LD I, 0x300
+0202 6042 V0=42 ; LD V0, 0x42
+0204 F055 [0300]=42 ; LD [I], V0
+");
+    assert!(fixture.cpu.last_memory_accesses().is_empty());
+}
+
+#[test]
+fn test_trace_buffer_keeps_latest_entries() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0x7001, 0x1200]);
+    fixture.cpu.set_trace_sink(TraceSink::Buffer(TraceBuffer::new(3)));
+
+    for _ in 0..10 {
+        fixture.cpu.execute_next_op_code().unwrap();
+    }
+
+    let buffer = fixture.cpu.trace_buffer().unwrap();
+    assert_eq!(buffer.len(), 3);
+    assert_eq!(buffer.to_string(), "\
+0202 1200 ; JP 0x200
+0200 7001 V0=05 ; ADD V0, 0x01
+0202 1200 ; JP 0x200
+");
+}
+
+#[test]
+fn test_trace_writer_sink() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0x6105]);
+    fixture.cpu.set_trace_sink(TraceSink::Writer(Box::new(Vec::new())));
+
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    assert!(fixture.cpu.trace_buffer().is_none());
+    assert!(fixture.cpu.take_trace_sink().is_some());
+    assert!(fixture.cpu.take_trace_sink().is_none());
+}
+
+#[test]
+fn test_trace_callback_sink() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0x6105, 0x0123]);
+    let pcs = Rc::new(RefCell::new(Vec::new()));
+    let recorded = pcs.clone();
+    fixture.cpu.set_trace_sink(TraceSink::Callback(Box::new(move |entry: &TraceEntry| {
+        recorded.borrow_mut().push((entry.pc, entry.instruction.is_some()));
+    })));
+
+    fixture.cpu.execute_next_op_code().unwrap();
+    assert!(fixture.cpu.execute_next_op_code().is_err());
+
+    assert_eq!(*pcs.borrow(), vec![(0x200, true), (0x202, false)]);
+}
+
+#[test]
+fn test_trace_skips_key_wait() {
+    let mut fixture = CpuFixture::new();
+    fixture.load_op_codes(&[0xF30A, 0x6105]);
+    fixture.cpu.set_trace_sink(TraceSink::Buffer(TraceBuffer::new(8)));
+
+    for _ in 0..5 {
+        fixture.cpu.execute_next_op_code().unwrap();
+    }
+    fixture.cpu.keypad.press_key(7);
+    fixture.cpu.execute_next_op_code().unwrap();
+    fixture.cpu.keypad.release_key(7);
+    fixture.cpu.execute_next_op_code().unwrap();
+
+    let buffer = fixture.cpu.trace_buffer().unwrap();
+    assert_eq!(buffer.len(), 2);
+    assert_eq!(buffer.entries().last().unwrap().to_string(), "0202 6105 V1=05 V3=07 ; LD V1, 0x05");
+}
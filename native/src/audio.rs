@@ -0,0 +1,57 @@
+#[cfg(feature = "audio")]
+use rodio::{self, Sink, Source};
+#[cfg(feature = "audio")]
+use rodio::source::SineWave;
+
+#[cfg(feature = "audio")]
+const BEEP_FREQUENCY: u32 = 440;
+
+// Plays a tone while the sound timer runs. Without the `audio` feature, or without an
+// output device, it only keeps track of the state
+pub struct Beeper {
+    #[cfg(feature = "audio")]
+    sink: Option<Sink>,
+    active: bool,
+}
+
+impl Beeper {
+    #[cfg(feature = "audio")]
+    pub fn new() -> Beeper {
+        let sink = rodio::default_output_device().map(|device| {
+            let sink = Sink::new(&device);
+            sink.append(SineWave::new(BEEP_FREQUENCY).amplify(0.2));
+            sink.pause();
+            sink
+        });
+        Beeper { sink: sink, active: false }
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub fn new() -> Beeper {
+        Beeper { active: false }
+    }
+
+    pub fn is_active (&self) -> bool {
+        self.active
+    }
+
+    pub fn set_active (&mut self, active: bool) {
+        if active == self.active {
+            return;
+        }
+        self.active = active;
+
+        #[cfg(feature = "audio")]
+        {
+            if let Some(ref sink) = self.sink {
+                if active { sink.play() } else { sink.pause() }
+            }
+        }
+    }
+}
+
+impl Default for Beeper {
+    fn default() -> Beeper {
+        Beeper::new()
+    }
+}
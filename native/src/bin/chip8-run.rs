@@ -0,0 +1,178 @@
+extern crate chip8_emulator;
+extern crate chip8_native;
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::debugger::Condition;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::machine::Machine;
+use chip8_emulator::movie::{Movie, MoviePlayer};
+use chip8_emulator::random::SeededRandom;
+use chip8_emulator::snapshot::to_ascii;
+use chip8_emulator::variant::Variant;
+use chip8_native::options::parse_variant;
+use chip8_native::runner::{registers_json, KeyPress, Runner};
+use chip8_native::screenshot;
+
+fn usage() -> ! {
+    eprintln!("usage: chip8-run <rom.ch8> [options]");
+    eprintln!();
+    eprintln!("  --variant chip8|schip|xochip  instruction set and quirks (default chip8)");
+    eprintln!("  --ips <n>                     instructions per second");
+    eprintln!("  --seed <n>                    seed for the random number generator");
+    eprintln!("  --frames <n>                  frames to run at most (default 600)");
+    eprintln!("  --until-pc <addr>             stop before executing the instruction at addr");
+    eprintln!("  --until <condition>           stop once a condition such as 'V3 == 0x10' holds");
+    eprintln!("  --key <frame>:<key>[:<n>]     hold hex key for n frames (default 1), repeatable");
+    eprintln!("  --movie <file>                play back a recorded movie instead, verifying its checksums");
+    eprintln!("  --screenshot <file>           write the display as .png, .pbm or ASCII text");
+    eprintln!("  --ascii                       print the display as ASCII");
+    eprintln!("  --registers <file|->          write the final registers as JSON");
+    eprintln!();
+    eprintln!("exits with 1 when the ROM fails with a CPU error or the movie desyncs");
+    process::exit(2);
+}
+
+fn fail (message: String) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(2);
+}
+
+fn parse_number (text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse::<u64>().ok(),
+    }
+}
+
+fn write_output (path: &str, bytes: &[u8]) {
+    let result = if path == "-" {
+        io::stdout().write_all(bytes)
+    } else {
+        fs::write(path, bytes)
+    };
+    if let Err(e) = result {
+        fail(format!("cannot write {}: {}", path, e));
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut rom_path = None;
+    let mut variant = Variant::Chip8;
+    let mut instructions_per_second = None;
+    let mut seed = None;
+    let mut runner = Runner::default();
+    let mut screenshot_path = None;
+    let mut print_ascii = false;
+    let mut registers_path = None;
+    let mut movie_path = None;
+
+    let mut index = 0;
+    while index < args.len() {
+        let option = args[index].as_str();
+        let mut value = || {
+            index += 1;
+            args.get(index).map(|s| s.as_str()).unwrap_or_else(|| fail(format!("missing value for {}", option)))
+        };
+        match option {
+            "--variant" => {
+                let name = value();
+                variant = parse_variant(name).unwrap_or_else(|| fail(format!("unknown variant '{}'", name)));
+            },
+            "--ips" => {
+                instructions_per_second = Some(parse_number(value())
+                    .filter(|&ips| ips > 0 && ips <= u32::MAX as u64)
+                    .unwrap_or_else(|| fail("--ips expects a positive number".to_string())) as u32);
+            },
+            "--seed" => {
+                seed = Some(parse_number(value()).unwrap_or_else(|| fail("--seed expects a number".to_string())));
+            },
+            "--frames" => {
+                runner.frames = parse_number(value()).unwrap_or_else(|| fail("--frames expects a number".to_string()));
+            },
+            "--until-pc" => {
+                runner.until_pc = Some(parse_number(value())
+                    .filter(|&address| address <= 0xFFFF)
+                    .unwrap_or_else(|| fail("--until-pc expects an address".to_string())) as u16);
+            },
+            "--until" => {
+                let condition = value();
+                runner.until = Some(Condition::parse(condition)
+                    .unwrap_or_else(|| fail(format!("invalid condition '{}'", condition))));
+            },
+            "--key" => {
+                let press = value();
+                runner.key_presses.push(KeyPress::parse(press)
+                    .unwrap_or_else(|| fail(format!("invalid key press '{}'", press))));
+            },
+            "--movie" => movie_path = Some(value().to_string()),
+            "--screenshot" => screenshot_path = Some(value().to_string()),
+            "--ascii" => print_ascii = true,
+            "--registers" => registers_path = Some(value().to_string()),
+            "-h" | "--help" => usage(),
+            option if option.starts_with('-') => fail(format!("unknown option '{}'", option)),
+            path if rom_path.is_none() => rom_path = Some(path.to_string()),
+            _ => usage(),
+        }
+        index += 1;
+    }
+
+    let rom_path = rom_path.unwrap_or_else(|| usage());
+    let rom = fs::read(&rom_path).unwrap_or_else(|e| fail(format!("cannot read {}: {}", rom_path, e)));
+
+    let mut cpu = Cpu::new(Keypad::new(), variant, variant.default_quirks());
+    if let Some(seed) = seed {
+        cpu.set_random_source(Box::new(SeededRandom::new(seed)));
+    }
+    cpu.load_program(&rom);
+    let mut machine = Machine::new(cpu);
+    if let Some(ips) = instructions_per_second {
+        machine.set_instructions_per_second(ips);
+    }
+
+    let result = match movie_path {
+        Some(ref path) => {
+            let bytes = fs::read(path).unwrap_or_else(|e| fail(format!("cannot read {}: {}", path, e)));
+            let movie = Movie::from_bytes(&bytes).unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+            MoviePlayer::start(movie, &mut machine, &rom)
+                .and_then(|mut player| player.play_to_end(&mut machine))
+                .map(|_| "movie".to_string())
+                .map_err(|e| e.to_string())
+        },
+        None => runner.run(&mut machine)
+            .map(|outcome| outcome.to_string())
+            .map_err(|e| e.to_string()),
+    };
+    let outcome = match result {
+        Ok(ref outcome) => outcome.as_str(),
+        Err(_) => "error",
+    };
+
+    let cpu = &machine.cpu;
+    if let Some(ref path) = screenshot_path {
+        let extension = Path::new(path).extension().and_then(|extension| extension.to_str());
+        let bytes = match extension {
+            Some("png") => screenshot::to_png(cpu),
+            Some("pbm") => screenshot::to_pbm(cpu),
+            _ => to_ascii(cpu).into_bytes(),
+        };
+        write_output(path, &bytes);
+    }
+    if print_ascii {
+        print!("{}", to_ascii(cpu));
+    }
+    if let Some(ref path) = registers_path {
+        let json = registers_json(cpu, machine.frame_count(), outcome);
+        write_output(path, format!("{}\n", json).as_bytes());
+    }
+
+    if let Err(e) = result {
+        eprintln!("error: {}: {}", rom_path, e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,174 @@
+#[macro_use]
+extern crate crossterm;
+extern crate chip8_emulator;
+extern crate chip8_native;
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+use std::time::{Duration, Instant};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use chip8_emulator::cpu::AudioEvent;
+use chip8_native::keymap::chip8_key;
+use chip8_native::options::{random_seed, Options};
+use chip8_native::terminal::{half_block_rows, register_panel, HeldKeys, LineDiff};
+use chip8_native::{Command, Frontend};
+
+const FRAME_DURATION: Duration = Duration::from_micros(16_667);
+const BELL: &str = "\x07";
+
+fn usage() -> ! {
+    eprintln!("usage: chip8-tui <rom.ch8> [--variant chip8|schip|xochip] [--ips <instructions per second>] [--record <movie.c8m>]");
+    eprintln!();
+    eprintln!("keys: 1234/QWER/ASDF/ZXCV keypad, P pause, F5 reset, -/= speed, Esc quit");
+    process::exit(2);
+}
+
+// Puts the terminal back the way it was, also when bailing out with an error
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter (out: &mut io::Stdout) -> io::Result<RawTerminal> {
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+enum Input {
+    Quit,
+    Command(Command),
+    Key(u8),
+    Redraw,
+}
+
+fn translate (event: Event) -> Option<Input> {
+    match event {
+        Event::Key(KeyEvent { code, modifiers, kind, .. }) if kind != KeyEventKind::Release => match code {
+            KeyCode::Esc => Some(Input::Quit),
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Some(Input::Quit),
+            KeyCode::Char('p') | KeyCode::Char('P') => Some(Input::Command(Command::TogglePause)),
+            KeyCode::Char('=') | KeyCode::Char('+') => Some(Input::Command(Command::SpeedUp)),
+            KeyCode::Char('-') => Some(Input::Command(Command::SpeedDown)),
+            KeyCode::F(5) => Some(Input::Command(Command::Reset)),
+            KeyCode::Char(c) => chip8_key(c).map(Input::Key),
+            _ => None,
+        },
+        Event::Resize(_, _) => Some(Input::Redraw),
+        _ => None,
+    }
+}
+
+fn draw (out: &mut io::Stdout, column: u16, changes: Vec<(usize, String)>) -> io::Result<()> {
+    for (row, line) in changes {
+        queue!(out, MoveTo(column, row as u16), Print(line))?;
+    }
+    Ok(())
+}
+
+fn run (frontend: &mut Frontend, out: &mut io::Stdout) -> Result<(), String> {
+    let mut held_keys = HeldKeys::new();
+    let mut screen = LineDiff::new();
+    let mut panel = LineDiff::new();
+    let mut status = LineDiff::new();
+    let mut display_width = frontend.machine.cpu.display_width();
+
+    loop {
+        let deadline = Instant::now() + FRAME_DURATION;
+        let mut timeout = FRAME_DURATION;
+        while event::poll(timeout).map_err(|e| e.to_string())? {
+            match translate(event::read().map_err(|e| e.to_string())?) {
+                Some(Input::Quit) => return Ok(()),
+                Some(Input::Command(command)) => frontend.handle_command(command),
+                Some(Input::Key(key)) => held_keys.press(key),
+                Some(Input::Redraw) => {
+                    queue!(out, Clear(ClearType::All)).map_err(|e| e.to_string())?;
+                    screen.invalidate();
+                    panel.invalidate();
+                    status.invalidate();
+                },
+                None => {},
+            }
+            timeout = deadline.saturating_duration_since(Instant::now());
+        }
+
+        frontend.set_pressed_keys(&held_keys.pressed());
+        if !frontend.is_paused() {
+            held_keys.tick();
+        }
+        frontend.run_frame().map_err(|e| e.to_string())?;
+
+        let cpu = &mut frontend.machine.cpu;
+        if cpu.display_width() != display_width {
+            display_width = cpu.display_width();
+            queue!(out, Clear(ClearType::All)).map_err(|e| e.to_string())?;
+            screen.invalidate();
+            panel.invalidate();
+            status.invalidate();
+        }
+        if cpu.poll_audio_event() == Some(AudioEvent::Started) {
+            queue!(out, Print(BELL)).map_err(|e| e.to_string())?;
+        }
+
+        let rows = half_block_rows(cpu);
+        let status_row = rows.len() + 1;
+        draw(out, 0, screen.changes(rows)).map_err(|e| e.to_string())?;
+        draw(out, display_width as u16 + 2, panel.changes(register_panel(cpu))).map_err(|e| e.to_string())?;
+        let title = frontend.title();
+        let changes = status.changes(vec![title])
+            .into_iter()
+            .map(|(row, line)| (row + status_row, line))
+            .collect();
+        draw(out, 0, changes).map_err(|e| e.to_string())?;
+        out.flush().map_err(|e| e.to_string())?;
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        usage();
+    }
+    let options = Options::parse(&args).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        usage();
+    });
+
+    let rom = fs::read(&options.rom_path).unwrap_or_else(|e| {
+        eprintln!("error: cannot read {}: {}", options.rom_path, e);
+        process::exit(1);
+    });
+    let mut frontend = Frontend::new(options.build_machine(), &rom);
+    if options.record_path.is_some() {
+        frontend.start_recording(random_seed());
+    }
+
+    let mut out = io::stdout();
+    let result = RawTerminal::enter(&mut out)
+        .map_err(|e| format!("cannot set up terminal: {}", e))
+        .and_then(|_guard| run(&mut frontend, &mut out));
+
+    // Saved even when the ROM failed, the movie then reproduces the failure
+    let saved = match options.record_path {
+        Some(ref path) => frontend.save_recording(path),
+        None => Ok(()),
+    };
+    if let Err(e) = saved {
+        eprintln!("error: {}", e);
+    }
+    if let Err(e) = result {
+        eprintln!("error: {}: {}", options.rom_path, e);
+        process::exit(1);
+    }
+}
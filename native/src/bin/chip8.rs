@@ -0,0 +1,117 @@
+extern crate chip8_native;
+extern crate minifb;
+
+use std::env;
+use std::fs;
+use std::process;
+use std::time::Duration;
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use chip8_native::audio::Beeper;
+use chip8_native::frontend::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use chip8_native::keymap::chip8_key;
+use chip8_native::options::{random_seed, Options};
+use chip8_native::{Command, Frontend};
+
+const FRAME_DURATION: Duration = Duration::from_micros(16_667);
+
+fn usage() -> ! {
+    eprintln!("usage: chip8 <rom.ch8> [--variant chip8|schip|xochip] [--ips <instructions per second>] [--record <movie.c8m>]");
+    eprintln!();
+    eprintln!("keys: 1234/QWER/ASDF/ZXCV keypad, P pause, F5 reset, -/= speed, Esc quit");
+    process::exit(2);
+}
+
+fn host_char (key: Key) -> Option<char> {
+    let host_char = match key {
+        Key::Key1 => '1', Key::Key2 => '2', Key::Key3 => '3', Key::Key4 => '4',
+        Key::Q => 'Q', Key::W => 'W', Key::E => 'E', Key::R => 'R',
+        Key::A => 'A', Key::S => 'S', Key::D => 'D', Key::F => 'F',
+        Key::Z => 'Z', Key::X => 'X', Key::C => 'C', Key::V => 'V',
+        _ => return None,
+    };
+    Some(host_char)
+}
+
+fn command (key: Key) -> Option<Command> {
+    match key {
+        Key::P => Some(Command::TogglePause),
+        Key::F5 => Some(Command::Reset),
+        Key::Equal => Some(Command::SpeedUp),
+        Key::Minus => Some(Command::SpeedDown),
+        _ => None,
+    }
+}
+
+fn save_recording (frontend: &mut Frontend, options: &Options) {
+    if let Some(ref path) = options.record_path {
+        if let Err(e) = frontend.save_recording(path) {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        usage();
+    }
+    let options = Options::parse(&args).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        usage();
+    });
+
+    let rom = fs::read(&options.rom_path).unwrap_or_else(|e| {
+        eprintln!("error: cannot read {}: {}", options.rom_path, e);
+        process::exit(1);
+    });
+    let mut frontend = Frontend::new(options.build_machine(), &rom);
+    if options.record_path.is_some() {
+        frontend.start_recording(random_seed());
+    }
+
+    let mut window = Window::new(&frontend.title(), WINDOW_WIDTH, WINDOW_HEIGHT, WindowOptions::default())
+        .unwrap_or_else(|e| {
+            eprintln!("error: cannot open window: {}", e);
+            process::exit(1);
+        });
+    window.limit_update_rate(Some(FRAME_DURATION));
+
+    let mut beeper = Beeper::new();
+    let mut buffer = vec![0; WINDOW_WIDTH * WINDOW_HEIGHT];
+    let mut title = frontend.title();
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        for key in window.get_keys_pressed(KeyRepeat::No) {
+            if let Some(command) = command(key) {
+                frontend.handle_command(command);
+            }
+        }
+
+        let keys = window.get_keys()
+            .into_iter()
+            .filter_map(host_char)
+            .filter_map(chip8_key)
+            .collect::<Vec<u8>>();
+        frontend.set_pressed_keys(&keys);
+
+        if let Err(e) = frontend.run_frame() {
+            eprintln!("error: {}: {}", options.rom_path, e);
+            save_recording(&mut frontend, &options);
+            process::exit(1);
+        }
+        beeper.set_active(frontend.is_beeping());
+
+        if frontend.title() != title {
+            title = frontend.title();
+            window.set_title(&title);
+        }
+        frontend.render(&mut buffer, WINDOW_WIDTH, WINDOW_HEIGHT);
+        if let Err(e) = window.update_with_buffer(&buffer, WINDOW_WIDTH, WINDOW_HEIGHT) {
+            eprintln!("error: cannot update window: {}", e);
+            process::exit(1);
+        }
+    }
+
+    save_recording(&mut frontend, &options);
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use chip8_emulator::error::CpuError;
+use chip8_emulator::machine::Machine;
+use chip8_emulator::movie::{Movie, MovieRecorder, DEFAULT_CHECKSUM_INTERVAL};
+
+pub const WINDOW_WIDTH: usize = 512;
+pub const WINDOW_HEIGHT: usize = 256;
+pub const PALETTE: [u32; 4] = [0x000000, 0x26CDFF, 0xFF6E26, 0xFFFFFF];
+pub const MIN_INSTRUCTIONS_PER_SECOND: u32 = 60;
+pub const MAX_INSTRUCTIONS_PER_SECOND: u32 = 61_440;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    TogglePause,
+    Reset,
+    SpeedUp,
+    SpeedDown,
+}
+
+// Everything a native front-end does besides talking to the window system, so it can be tested headless
+pub struct Frontend {
+    pub machine: Machine,
+    rom: Vec<u8>,
+    paused: bool,
+    recording: Option<(MovieRecorder, u64)>,
+}
+
+impl Frontend {
+    pub fn new(mut machine: Machine, rom: &[u8]) -> Frontend {
+        machine.cpu.load_program(rom);
+        Frontend {
+            machine: machine,
+            rom: rom.to_vec(),
+            paused: false,
+            recording: None,
+        }
+    }
+
+    // Restarts the ROM and records from there on, see `finish_recording`
+    pub fn start_recording (&mut self, seed: u64) {
+        let recorder = MovieRecorder::start(&mut self.machine, &self.rom, seed, DEFAULT_CHECKSUM_INTERVAL);
+        self.recording = Some((recorder, seed));
+    }
+
+    pub fn is_recording (&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn finish_recording (&mut self) -> Option<Movie> {
+        let machine = &mut self.machine;
+        self.recording.take().map(|(recorder, _)| recorder.finish(machine))
+    }
+
+    pub fn save_recording (&mut self, path: &str) -> Result<(), String> {
+        match self.finish_recording() {
+            Some(movie) => fs::write(path, movie.to_bytes())
+                .map_err(|e| format!("cannot write {}: {}", path, e)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_paused (&self) -> bool {
+        self.paused
+    }
+
+    pub fn handle_command (&mut self, command: Command) {
+        match command {
+            Command::TogglePause => self.paused = !self.paused,
+            // A movie has to start from a fresh machine, so resetting starts the recording over
+            Command::Reset => match self.recording.take() {
+                Some((_, seed)) => self.start_recording(seed),
+                None => {
+                    self.machine.reset();
+                    self.machine.cpu.load_program(&self.rom);
+                },
+            },
+            // The speed is part of the movie header and can't change during a recording
+            Command::SpeedUp | Command::SpeedDown if self.is_recording() => {},
+            Command::SpeedUp => {
+                let speed = self.machine.instructions_per_second().saturating_mul(2);
+                self.machine.set_instructions_per_second(speed.min(MAX_INSTRUCTIONS_PER_SECOND));
+            },
+            Command::SpeedDown => {
+                let speed = self.machine.instructions_per_second() / 2;
+                self.machine.set_instructions_per_second(speed.max(MIN_INSTRUCTIONS_PER_SECOND));
+            },
+        }
+    }
+
+    pub fn set_pressed_keys (&mut self, keys: &[u8]) {
+        // Only touch keys that changed so a recording doesn't log every held key each frame
+        let keypad = &mut self.machine.cpu.keypad;
+        for key in 0..16 {
+            if keys.iter().any(|&pressed| pressed & 0xF == key) {
+                keypad.press_key(key);
+            } else {
+                keypad.release_key(key);
+            }
+        }
+    }
+
+    pub fn run_frame (&mut self) -> Result<(), CpuError> {
+        if self.paused {
+            return Ok(());
+        }
+        match self.recording {
+            Some((ref mut recorder, _)) => recorder.run_frame(&mut self.machine),
+            None => self.machine.run_frame(),
+        }
+    }
+
+    pub fn is_beeping (&self) -> bool {
+        !self.paused && self.machine.cpu.is_sound_active()
+    }
+
+    pub fn title (&self) -> String {
+        let state = if self.paused {
+            " (paused)"
+        } else if self.machine.cpu.has_exited() {
+            " (exited)"
+        } else if self.is_recording() {
+            " (recording)"
+        } else {
+            ""
+        };
+        format!("CHIP-8 - {} ips{}", self.machine.instructions_per_second(), state)
+    }
+
+    // Scales the display by the largest whole factor that fits and centers it in the buffer
+    pub fn render (&self, buffer: &mut [u32], width: usize, height: usize) {
+        let cpu = &self.machine.cpu;
+        let display_width = cpu.display_width();
+        let display_height = cpu.display_height();
+        let scale = (width / display_width).min(height / display_height).max(1);
+        let left = width.saturating_sub(display_width * scale) / 2;
+        let top = height.saturating_sub(display_height * scale) / 2;
+
+        for pixel in buffer.iter_mut() {
+            *pixel = PALETTE[0];
+        }
+        for y in 0..height.min(display_height * scale + top).saturating_sub(top) {
+            for x in 0..width.min(display_width * scale + left).saturating_sub(left) {
+                let color = PALETTE[(cpu.pixel(x / scale, y / scale) & 0x3) as usize];
+                buffer[(top + y) * width + left + x] = color;
+            }
+        }
+    }
+}
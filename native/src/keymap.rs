@@ -0,0 +1,19 @@
+// The common layout, the left side of a QWERTY keyboard stands in for the COSMAC VIP hex keypad:
+//   1 2 3 C        1 2 3 4
+//   4 5 6 D   <-   Q W E R
+//   7 8 9 E        A S D F
+//   A 0 B F        Z X C V
+pub const KEY_LAYOUT: [(char, u8); 16] = [
+    ('1', 0x1), ('2', 0x2), ('3', 0x3), ('4', 0xC),
+    ('Q', 0x4), ('W', 0x5), ('E', 0x6), ('R', 0xD),
+    ('A', 0x7), ('S', 0x8), ('D', 0x9), ('F', 0xE),
+    ('Z', 0xA), ('X', 0x0), ('C', 0xB), ('V', 0xF),
+];
+
+pub fn chip8_key (host_key: char) -> Option<u8> {
+    let host_key = host_key.to_ascii_uppercase();
+    KEY_LAYOUT
+        .iter()
+        .find(|&&(key, _)| key == host_key)
+        .map(|&(_, chip8_key)| chip8_key)
+}
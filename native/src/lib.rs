@@ -0,0 +1,13 @@
+extern crate chip8_emulator;
+#[cfg(feature = "audio")]
+extern crate rodio;
+
+pub mod audio;
+pub mod frontend;
+pub mod keymap;
+pub mod options;
+pub mod runner;
+pub mod screenshot;
+pub mod terminal;
+
+pub use frontend::{Command, Frontend};
@@ -0,0 +1,91 @@
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::machine::Machine;
+use chip8_emulator::random::{OsRandom, RandomSource, DEFAULT_SEED};
+use chip8_emulator::variant::Variant;
+
+// Command line options shared by the front-end binaries
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub rom_path: String,
+    pub variant: Variant,
+    pub instructions_per_second: Option<u32>,
+    pub record_path: Option<String>,
+}
+
+impl Options {
+    pub fn parse (args: &[String]) -> Result<Options, String> {
+        let mut rom_path = None;
+        let mut variant = Variant::Chip8;
+        let mut instructions_per_second = None;
+        let mut record_path = None;
+
+        let mut index = 0;
+        while index < args.len() {
+            match args[index].as_str() {
+                "--variant" => {
+                    index += 1;
+                    variant = match args.get(index).map(|s| s.as_str()) {
+                        Some(name) => parse_variant(name)
+                            .ok_or_else(|| format!("unknown variant '{}'", name))?,
+                        None => return Err("missing value for --variant".to_string()),
+                    };
+                },
+                "--ips" => {
+                    index += 1;
+                    instructions_per_second = match args.get(index).and_then(|s| s.parse::<u32>().ok()) {
+                        Some(ips) if ips > 0 => Some(ips),
+                        _ => return Err("--ips expects a positive number".to_string()),
+                    };
+                },
+                "--record" => {
+                    index += 1;
+                    record_path = match args.get(index) {
+                        Some(path) => Some(path.to_string()),
+                        None => return Err("missing value for --record".to_string()),
+                    };
+                },
+                option if option.starts_with('-') => return Err(format!("unknown option '{}'", option)),
+                path if rom_path.is_none() => rom_path = Some(path.to_string()),
+                _ => return Err("only one ROM can be given".to_string()),
+            }
+            index += 1;
+        }
+
+        Ok(Options {
+            rom_path: rom_path.ok_or_else(|| "missing ROM path".to_string())?,
+            variant: variant,
+            instructions_per_second: instructions_per_second,
+            record_path: record_path,
+        })
+    }
+
+    pub fn build_machine (&self) -> Machine {
+        let mut cpu = Cpu::new(Keypad::new(), self.variant, self.variant.default_quirks());
+        if let Ok(rng) = OsRandom::new() {
+            cpu.set_random_source(Box::new(rng));
+        }
+        let mut machine = Machine::new(cpu);
+        if let Some(ips) = self.instructions_per_second {
+            machine.set_instructions_per_second(ips);
+        }
+        machine
+    }
+}
+
+pub fn parse_variant (name: &str) -> Option<Variant> {
+    match name {
+        "chip8" => Some(Variant::Chip8),
+        "schip" => Some(Variant::SuperChip),
+        "xochip" => Some(Variant::XoChip),
+        _ => None,
+    }
+}
+
+// Seed for a new recording, movies store it so playback doesn't need the OS generator
+pub fn random_seed () -> u64 {
+    match OsRandom::new() {
+        Ok(mut rng) => (0..8).fold(0, |seed, _| (seed << 8) | rng.next_byte() as u64),
+        Err(_) => DEFAULT_SEED,
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::debugger::Condition;
+use chip8_emulator::error::CpuError;
+use chip8_emulator::machine::{Event, Machine};
+
+pub const DEFAULT_FRAMES: u64 = 600;
+
+// Holds `key` down from `frame` for `frames` frames
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyPress {
+    pub frame: u64,
+    pub key: u8,
+    pub frames: u64,
+}
+
+impl KeyPress {
+    // FRAME:KEY[:FRAMES] with a hex key, e.g. `30:5` or `30:A:10`
+    pub fn parse (text: &str) -> Option<KeyPress> {
+        let parts = text.split(':').collect::<Vec<&str>>();
+        if parts.len() < 2 || parts.len() > 3 {
+            return None;
+        }
+        let frame = parts[0].trim().parse::<u64>().ok()?;
+        let key = u8::from_str_radix(parts[1].trim(), 16).ok().filter(|&key| key < 16)?;
+        let frames = match parts.get(2) {
+            Some(frames) => frames.trim().parse::<u64>().ok().filter(|&frames| frames > 0)?,
+            None => 1,
+        };
+        Some(KeyPress { frame: frame, key: key, frames: frames })
+    }
+
+    pub fn is_held_at (&self, frame: u64) -> bool {
+        frame >= self.frame && frame - self.frame < self.frames
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    FramesElapsed,
+    ReachedPc(u16),
+    ConditionMet,
+    Exited,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Outcome::FramesElapsed => write!(f, "frames"),
+            Outcome::ReachedPc(_) => write!(f, "pc"),
+            Outcome::ConditionMet => write!(f, "condition"),
+            Outcome::Exited => write!(f, "exited"),
+        }
+    }
+}
+
+// Runs a machine without a display for scripted checks
+pub struct Runner {
+    pub frames: u64,
+    pub until_pc: Option<u16>,
+    pub until: Option<Condition>,
+    pub key_presses: Vec<KeyPress>,
+}
+
+impl Default for Runner {
+    fn default() -> Runner {
+        Runner {
+            frames: DEFAULT_FRAMES,
+            until_pc: None,
+            until: None,
+            key_presses: Vec::new(),
+        }
+    }
+}
+
+impl Runner {
+    // Stops before executing `until_pc` and after the instruction that satisfies `until`
+    pub fn run (&self, machine: &mut Machine) -> Result<Outcome, CpuError> {
+        while machine.frame_count() < self.frames {
+            let frame = machine.frame_count();
+            // Only touch keys that changed, a held key would otherwise look freshly pressed every frame
+            let keypad = &mut machine.cpu.keypad;
+            for key in 0..16 {
+                if self.key_presses.iter().any(|press| press.key == key && press.is_held_at(frame)) {
+                    keypad.press_key(key);
+                } else {
+                    keypad.release_key(key);
+                }
+            }
+
+            machine.schedule_frame();
+            while let Some(event) = machine.pending_event() {
+                if event == Event::Instruction && Some(machine.cpu.program_counter) == self.until_pc {
+                    return Ok(Outcome::ReachedPc(machine.cpu.program_counter));
+                }
+                machine.run_pending_event()?;
+                if machine.cpu.has_exited() {
+                    return Ok(Outcome::Exited);
+                }
+                if event == Event::Instruction && self.until.map(|until| until.matches(&machine.cpu)) == Some(true) {
+                    return Ok(Outcome::ConditionMet);
+                }
+            }
+        }
+        Ok(Outcome::FramesElapsed)
+    }
+}
+
+// Final machine state as a single JSON object, numbers are decimal
+pub fn registers_json (cpu: &Cpu, frames: u64, outcome: &str) -> String {
+    let list = |values: Vec<String>| format!("[{}]", values.join(", "));
+    let v = cpu.registers.v.iter().map(|value| value.to_string()).collect();
+    let stack = cpu.stack[..cpu.stack_pointer.min(cpu.stack.len())]
+        .iter()
+        .map(|address| address.to_string())
+        .collect();
+
+    format!(
+        "{{\"outcome\": \"{}\", \"frames\": {}, \"pc\": {}, \"i\": {}, \"v\": {}, \"delay_timer\": {}, \"sound_timer\": {}, \"stack\": {}}}",
+        outcome,
+        frames,
+        cpu.program_counter,
+        cpu.registers.i,
+        list(v),
+        cpu.registers.delay_timer,
+        cpu.registers.sound_timer,
+        list(stack),
+    )
+}
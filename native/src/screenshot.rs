@@ -0,0 +1,84 @@
+use chip8_emulator::checksum::{crc32, crc32_update};
+use chip8_emulator::cpu::Cpu;
+use frontend::PALETTE;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+// Plain PBM, any lit plane is black
+pub fn to_pbm (cpu: &Cpu) -> Vec<u8> {
+    let mut pbm = format!("P1\n{} {}\n", cpu.display_width(), cpu.display_height());
+    for y in 0..cpu.display_height() {
+        let row = (0..cpu.display_width())
+            .map(|x| if cpu.pixel(x, y) != 0 { "1" } else { "0" })
+            .collect::<Vec<&str>>();
+        pbm.push_str(&row.join(" "));
+        pbm.push('\n');
+    }
+    pbm.into_bytes()
+}
+
+// Palette PNG with uncompressed deflate blocks, the display is small enough not to bother
+pub fn to_png (cpu: &Cpu) -> Vec<u8> {
+    let width = cpu.display_width();
+    let height = cpu.display_height();
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bit depth, palette color, default compression, filter and no interlacing
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+
+    let palette = PALETTE.iter()
+        .flat_map(|&color| vec![(color >> 16) as u8, (color >> 8) as u8, color as u8])
+        .collect::<Vec<u8>>();
+
+    let mut scanlines = Vec::with_capacity((width + 1) * height);
+    for y in 0..height {
+        scanlines.push(0);
+        scanlines.extend((0..width).map(|x| cpu.pixel(x, y) & 0x3));
+    }
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"PLTE", &palette);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk (png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32_update(crc32(kind), data);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored (data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_last = blocks.peek().is_none();
+        let length = block.len() as u16;
+        stream.push(if is_last { 1 } else { 0 });
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn adler32 (data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
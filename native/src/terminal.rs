@@ -0,0 +1,114 @@
+use chip8_emulator::cpu::Cpu;
+
+// Terminals only report key presses, so a key counts as held for a few frames after its
+// last press or auto-repeat
+pub const KEY_HOLD_FRAMES: u8 = 8;
+
+pub struct HeldKeys {
+    frames_left: [u8; 16],
+}
+
+impl HeldKeys {
+    pub fn new() -> HeldKeys {
+        HeldKeys { frames_left: [0; 16] }
+    }
+
+    pub fn press (&mut self, key: u8) {
+        self.frames_left[(key & 0xF) as usize] = KEY_HOLD_FRAMES;
+    }
+
+    pub fn pressed (&self) -> Vec<u8> {
+        (0..16u8).filter(|&key| self.frames_left[key as usize] > 0).collect()
+    }
+
+    // Call once per emulated frame
+    pub fn tick (&mut self) {
+        for frames in self.frames_left.iter_mut() {
+            *frames = frames.saturating_sub(1);
+        }
+    }
+}
+
+impl Default for HeldKeys {
+    fn default() -> HeldKeys {
+        HeldKeys::new()
+    }
+}
+
+// Each character cell covers two display rows
+pub fn half_block_rows (cpu: &Cpu) -> Vec<String> {
+    let width = cpu.display_width();
+    let height = cpu.display_height();
+
+    (0..height / 2)
+        .map(|row| {
+            (0..width)
+                .map(|x| match (cpu.pixel(x, row * 2) != 0, cpu.pixel(x, row * 2 + 1) != 0) {
+                    (false, false) => ' ',
+                    (true, false) => '\u{2580}',
+                    (false, true) => '\u{2584}',
+                    (true, true) => '\u{2588}',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn register_panel (cpu: &Cpu) -> Vec<String> {
+    let registers = &cpu.registers;
+    let mut lines = vec![
+        format!("PC {:04X}  I  {:04X}", cpu.program_counter, registers.i),
+        format!("DT {:02X}    ST {:02X}", registers.delay_timer, registers.sound_timer),
+    ];
+    for pair in 0..8 {
+        let x = pair * 2;
+        lines.push(format!("V{:X} {:02X}    V{:X} {:02X}", x, registers.v[x], x + 1, registers.v[x + 1]));
+    }
+    lines.push(format!("SP {:X}", cpu.stack_pointer));
+    for depth in (0..cpu.stack_pointer.min(cpu.stack.len())).rev() {
+        lines.push(format!("   {:04X}", cpu.stack[depth]));
+    }
+    lines
+}
+
+// Remembers what is on screen so only changed lines are redrawn
+pub struct LineDiff {
+    previous: Vec<String>,
+}
+
+impl LineDiff {
+    pub fn new() -> LineDiff {
+        LineDiff { previous: Vec::new() }
+    }
+
+    // Returns the lines to draw, shorter lines are padded so they cover what was there before
+    pub fn changes (&mut self, lines: Vec<String>) -> Vec<(usize, String)> {
+        let mut changes = Vec::new();
+        for index in 0..lines.len().max(self.previous.len()) {
+            let previous = self.previous.get(index);
+            let line = lines.get(index);
+            if previous == line {
+                continue;
+            }
+            let previous_width = previous.map(|line| line.chars().count()).unwrap_or(0);
+            let mut text = line.cloned().unwrap_or_default();
+            let width = text.chars().count();
+            if width < previous_width {
+                text.push_str(&" ".repeat(previous_width - width));
+            }
+            changes.push((index, text));
+        }
+        self.previous = lines;
+        changes
+    }
+
+    pub fn invalidate (&mut self) {
+        self.previous.clear();
+    }
+}
+
+impl Default for LineDiff {
+    fn default() -> LineDiff {
+        LineDiff::new()
+    }
+}
@@ -0,0 +1,149 @@
+extern crate chip8_emulator;
+extern crate chip8_native;
+
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::machine::Machine;
+use chip8_emulator::movie::MoviePlayer;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::variant::Variant;
+use chip8_native::frontend::*;
+use chip8_native::keymap::chip8_key;
+
+fn frontend(rom: &[u8]) -> Frontend {
+    let cpu = Cpu::new(Keypad::new(), Variant::Chip8, Quirks::default());
+    Frontend::new(Machine::new(cpu), rom)
+}
+
+#[test]
+fn test_keymap_uses_standard_layout() {
+    assert_eq!(chip8_key('1'), Some(0x1));
+    assert_eq!(chip8_key('4'), Some(0xC));
+    assert_eq!(chip8_key('q'), Some(0x4));
+    assert_eq!(chip8_key('X'), Some(0x0));
+    assert_eq!(chip8_key('V'), Some(0xF));
+    assert_eq!(chip8_key('P'), None);
+}
+
+#[test]
+fn test_pause_stops_execution() {
+    // V0 += 1, jump back
+    let mut frontend = frontend(&[0x70, 0x01, 0x12, 0x00]);
+    frontend.handle_command(Command::TogglePause);
+
+    frontend.run_frame().unwrap();
+
+    assert!(frontend.is_paused());
+    assert_eq!(frontend.machine.cpu.registers.v[0], 0);
+    assert!(frontend.title().ends_with("(paused)"));
+}
+
+#[test]
+fn test_reset_reloads_rom() {
+    let mut frontend = frontend(&[0x70, 0x01, 0x12, 0x00]);
+    frontend.run_frame().unwrap();
+    assert!(frontend.machine.cpu.registers.v[0] > 0);
+
+    frontend.handle_command(Command::Reset);
+
+    assert_eq!(frontend.machine.cpu.registers.v[0], 0);
+    assert_eq!(frontend.machine.cpu.program_counter, 0x200);
+    assert_eq!(&frontend.machine.cpu.memory[0x200..0x204], &[0x70, 0x01, 0x12, 0x00]);
+}
+
+#[test]
+fn test_speed_is_clamped() {
+    let mut frontend = frontend(&[]);
+    for _ in 0..20 {
+        frontend.handle_command(Command::SpeedUp);
+    }
+    assert_eq!(frontend.machine.instructions_per_second(), MAX_INSTRUCTIONS_PER_SECOND);
+
+    for _ in 0..20 {
+        frontend.handle_command(Command::SpeedDown);
+    }
+    assert_eq!(frontend.machine.instructions_per_second(), MIN_INSTRUCTIONS_PER_SECOND);
+}
+
+#[test]
+fn test_set_pressed_keys_replaces_keypad_state() {
+    let mut frontend = frontend(&[]);
+    frontend.set_pressed_keys(&[0x1, 0xF]);
+    frontend.set_pressed_keys(&[0x5]);
+
+    let keypad = &mut frontend.machine.cpu.keypad;
+    assert!(!keypad.key_is_pressed(0x1));
+    assert!(!keypad.key_is_pressed(0xF));
+    assert!(keypad.key_is_pressed(0x5));
+}
+
+#[test]
+fn test_beeps_while_sound_timer_runs() {
+    // V0 := 10, ST := V0
+    let mut frontend = frontend(&[0x60, 0x0A, 0xF0, 0x18, 0x12, 0x04]);
+    assert!(!frontend.is_beeping());
+
+    frontend.run_frame().unwrap();
+    assert!(frontend.is_beeping());
+
+    frontend.handle_command(Command::TogglePause);
+    assert!(!frontend.is_beeping());
+}
+
+#[test]
+fn test_render_scales_display() {
+    // Draw the top-left pixel of the 0 font glyph
+    let mut frontend = frontend(&[0x00, 0xE0, 0xA0, 0x00, 0xD0, 0x01, 0x12, 0x06]);
+    frontend.run_frame().unwrap();
+
+    let mut buffer = vec![0xDEADBEEF; WINDOW_WIDTH * WINDOW_HEIGHT];
+    frontend.render(&mut buffer, WINDOW_WIDTH, WINDOW_HEIGHT);
+
+    let scale = WINDOW_WIDTH / 64;
+    assert_eq!(buffer[0], PALETTE[1]);
+    assert_eq!(buffer[(scale - 1) * WINDOW_WIDTH + scale * 4 - 1], PALETTE[1]);
+    assert_eq!(buffer[scale * WINDOW_WIDTH], PALETTE[0]);
+    assert_eq!(buffer[scale * 4], PALETTE[0]);
+    assert!(buffer.iter().all(|&pixel| pixel == PALETTE[0] || pixel == PALETTE[1]));
+}
+
+#[test]
+fn test_recording_plays_back() {
+    // Wait for a key into V1, add it to V2, repeat
+    let rom = [0xF1, 0x0A, 0x82, 0x14, 0x12, 0x00];
+    let mut frontend = frontend(&rom);
+    frontend.start_recording(7);
+    for frame in 0..130 {
+        frontend.set_pressed_keys(if frame % 20 < 5 { &[0x3] } else { &[] });
+        frontend.run_frame().unwrap();
+    }
+    assert!(frontend.title().ends_with("(recording)"));
+    let v2 = frontend.machine.cpu.registers.v[2];
+    assert!(v2 > 0);
+
+    let movie = frontend.finish_recording().unwrap();
+    assert!(!frontend.is_recording());
+    assert_eq!(movie.frames, 130);
+    assert_eq!(movie.checksums.len(), 2);
+
+    let mut machine = Machine::new(Cpu::new(Keypad::new(), Variant::Chip8, Quirks::default()));
+    MoviePlayer::start(movie, &mut machine, &rom).unwrap().play_to_end(&mut machine).unwrap();
+    assert_eq!(machine.cpu.registers.v[2], v2);
+}
+
+#[test]
+fn test_reset_restarts_recording() {
+    let mut frontend = frontend(&[0x70, 0x01, 0x12, 0x00]);
+    frontend.start_recording(7);
+    frontend.set_pressed_keys(&[0x1]);
+    frontend.run_frame().unwrap();
+    frontend.handle_command(Command::SpeedUp);
+
+    frontend.handle_command(Command::Reset);
+    frontend.run_frame().unwrap();
+
+    let movie = frontend.finish_recording().unwrap();
+    assert_eq!(movie.frames, 1);
+    assert!(movie.events.is_empty());
+    assert_eq!(movie.header.instructions_per_second, frontend.machine.instructions_per_second());
+}
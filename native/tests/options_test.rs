@@ -0,0 +1,37 @@
+extern crate chip8_emulator;
+extern crate chip8_native;
+
+use chip8_emulator::variant::Variant;
+use chip8_native::options::*;
+
+fn args(text: &[&str]) -> Vec<String> {
+    text.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn test_parse_defaults() {
+    assert_eq!(Options::parse(&args(&["game.ch8"])), Ok(Options {
+        rom_path: "game.ch8".to_string(),
+        variant: Variant::Chip8,
+        instructions_per_second: None,
+        record_path: None,
+    }));
+}
+
+#[test]
+fn test_parse_variant_and_speed() {
+    let options = Options::parse(&args(&["--variant", "schip", "game.ch8", "--ips", "1000"])).unwrap();
+
+    assert_eq!(options.variant, Variant::SuperChip);
+    assert_eq!(options.instructions_per_second, Some(1000));
+    assert_eq!(options.build_machine().instructions_per_second(), 1000);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(Options::parse(&args(&[])), Err("missing ROM path".to_string()));
+    assert_eq!(Options::parse(&args(&["a.ch8", "--variant", "nes"])), Err("unknown variant 'nes'".to_string()));
+    assert_eq!(Options::parse(&args(&["a.ch8", "--ips", "0"])), Err("--ips expects a positive number".to_string()));
+    assert_eq!(Options::parse(&args(&["a.ch8", "b.ch8"])), Err("only one ROM can be given".to_string()));
+    assert_eq!(Options::parse(&args(&["a.ch8", "--fast"])), Err("unknown option '--fast'".to_string()));
+}
@@ -0,0 +1,173 @@
+extern crate chip8_emulator;
+extern crate chip8_native;
+
+use std::env;
+use std::fs;
+use std::process::Command;
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::debugger::Condition;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::machine::Machine;
+use chip8_emulator::movie::MovieRecorder;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::variant::Variant;
+use chip8_native::runner::*;
+
+fn machine_with(rom: &[u8]) -> Machine {
+    let mut cpu = Cpu::new(Keypad::new(), Variant::Chip8, Quirks::default());
+    cpu.load_program(rom);
+    Machine::new(cpu)
+}
+
+// V0 += 1, jump back
+const COUNTER: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+#[test]
+fn test_parse_key_press() {
+    assert_eq!(KeyPress::parse("30:a"), Some(KeyPress { frame: 30, key: 0xA, frames: 1 }));
+    assert_eq!(KeyPress::parse("0:F:10"), Some(KeyPress { frame: 0, key: 0xF, frames: 10 }));
+    assert_eq!(KeyPress::parse("0:10"), None);
+    assert_eq!(KeyPress::parse("0:1:0"), None);
+    assert_eq!(KeyPress::parse("5"), None);
+}
+
+#[test]
+fn test_runs_for_frames() {
+    let mut machine = machine_with(&COUNTER);
+    let runner = Runner { frames: 3, ..Runner::default() };
+
+    assert_eq!(runner.run(&mut machine), Ok(Outcome::FramesElapsed));
+    assert_eq!(machine.frame_count(), 3);
+    assert_eq!(machine.cpu.registers.v[0], 12);
+}
+
+#[test]
+fn test_runs_until_pc() {
+    let mut machine = machine_with(&[0x60, 0x05, 0x22, 0x08, 0x12, 0x04, 0x00, 0x00, 0x00, 0xEE]);
+    let runner = Runner { until_pc: Some(0x208), ..Runner::default() };
+
+    assert_eq!(runner.run(&mut machine), Ok(Outcome::ReachedPc(0x208)));
+    assert_eq!(machine.cpu.program_counter, 0x208);
+    assert_eq!(machine.cpu.stack_pointer, 1);
+}
+
+#[test]
+fn test_runs_until_condition() {
+    let mut machine = machine_with(&COUNTER);
+    let runner = Runner { until: Condition::parse("V0 == 0x20"), ..Runner::default() };
+
+    assert_eq!(runner.run(&mut machine), Ok(Outcome::ConditionMet));
+    assert_eq!(machine.cpu.registers.v[0], 0x20);
+}
+
+#[test]
+fn test_scripted_key_presses() {
+    // Wait for a key into V1, then spin
+    let mut machine = machine_with(&[0xF1, 0x0A, 0x12, 0x02]);
+    let runner = Runner {
+        frames: 20,
+        key_presses: vec![KeyPress { frame: 10, key: 0x7, frames: 2 }],
+        ..Runner::default()
+    };
+
+    runner.run(&mut machine).unwrap();
+
+    assert_eq!(machine.cpu.registers.v[1], 0x7);
+    assert!(!machine.cpu.keypad.key_is_pressed(0x7));
+}
+
+#[test]
+fn test_held_key_does_not_answer_next_key_wait() {
+    // Wait for a key into V0, then into V1, then spin. 7 is pressed during the first wait
+    // after 5 and still held when the second one starts, so only the first wait is answered
+    let mut machine = machine_with(&[0xF0, 0x0A, 0xF1, 0x0A, 0x12, 0x04]);
+    let runner = Runner {
+        frames: 30,
+        key_presses: vec![
+            KeyPress { frame: 1, key: 0x5, frames: 2 },
+            KeyPress { frame: 1, key: 0x7, frames: 20 },
+        ],
+        ..Runner::default()
+    };
+
+    runner.run(&mut machine).unwrap();
+
+    assert_eq!(machine.cpu.registers.v[0], 0x5);
+    assert_eq!(machine.cpu.registers.v[1], 0x0);
+    assert_eq!(machine.cpu.program_counter, 0x204);
+}
+
+#[test]
+fn test_registers_json() {
+    let mut machine = machine_with(&[0x61, 0x2A, 0x22, 0x06, 0x00, 0x00, 0xA1, 0x23, 0x12, 0x08]);
+    Runner { frames: 1, ..Runner::default() }.run(&mut machine).unwrap();
+
+    let json = registers_json(&machine.cpu, machine.frame_count(), "frames");
+
+    assert_eq!(json, "{\"outcome\": \"frames\", \"frames\": 1, \"pc\": 520, \"i\": 291, \
+\"v\": [0, 42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], \"delay_timer\": 0, \"sound_timer\": 0, \"stack\": [516]}");
+}
+
+#[test]
+fn test_chip8_run_exits_with_error() {
+    let rom_path = env::temp_dir().join(format!("chip8-run-test-{}.ch8", std::process::id()));
+    fs::write(&rom_path, [0x60, 0x01, 0xFF, 0xFF]).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_chip8-run"))
+        .arg(&rom_path)
+        .args(["--ascii", "--registers", "-"])
+        .output()
+        .unwrap();
+    fs::remove_file(&rom_path).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 33);
+    assert!(stdout.contains("\"outcome\": \"error\""));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("unknown opcode FFFF at 0202"));
+}
+
+#[test]
+fn test_chip8_run_plays_movie() {
+    // Wait for a key into V1, then spin
+    let rom = [0xF1, 0x0A, 0x12, 0x02];
+    let mut machine = machine_with(&[]);
+    let mut recorder = MovieRecorder::start(&mut machine, &rom, 1, 10);
+    for frame in 0..20 {
+        match frame {
+            5 => machine.cpu.keypad.press_key(0xB),
+            8 => machine.cpu.keypad.release_key(0xB),
+            _ => {},
+        }
+        recorder.run_frame(&mut machine).unwrap();
+    }
+    let mut movie = recorder.finish(&mut machine);
+
+    let base = env::temp_dir().join(format!("chip8-run-movie-{}", std::process::id()));
+    let rom_path = base.with_extension("ch8");
+    let movie_path = base.with_extension("c8m");
+    fs::write(&rom_path, rom).unwrap();
+    fs::write(&movie_path, movie.to_bytes()).unwrap();
+    let run = || Command::new(env!("CARGO_BIN_EXE_chip8-run"))
+        .arg(&rom_path)
+        .arg("--movie")
+        .arg(&movie_path)
+        .args(["--registers", "-"])
+        .output()
+        .unwrap();
+
+    let output = run();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"outcome\": \"movie\", \"frames\": 20"));
+    assert!(stdout.contains("\"v\": [0, 11,"));
+
+    movie.checksums[1].1 ^= 1;
+    fs::write(&movie_path, movie.to_bytes()).unwrap();
+    let output = run();
+    fs::remove_file(&rom_path).unwrap();
+    fs::remove_file(&movie_path).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("desync"));
+}
@@ -0,0 +1,73 @@
+extern crate chip8_emulator;
+extern crate chip8_native;
+
+use chip8_emulator::checksum::crc32;
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::variant::Variant;
+use chip8_native::screenshot::*;
+
+// Draws the top row of the 0 glyph at 0,0
+fn cpu_with_pixels() -> Cpu {
+    let mut cpu = Cpu::new(Keypad::new(), Variant::Chip8, Quirks::default());
+    cpu.load_program(&[0xA0, 0x00, 0xD0, 0x01]);
+    cpu.execute_next_op_code().unwrap();
+    cpu.execute_next_op_code().unwrap();
+    cpu
+}
+
+fn read_u32 (bytes: &[u8]) -> u32 {
+    ((bytes[0] as u32) << 24) | ((bytes[1] as u32) << 16) | ((bytes[2] as u32) << 8) | bytes[3] as u32
+}
+
+#[test]
+fn test_to_pbm() {
+    let cpu = cpu_with_pixels();
+
+    let pbm = String::from_utf8(to_pbm(&cpu)).unwrap();
+    let lines = pbm.lines().collect::<Vec<&str>>();
+
+    assert_eq!(lines[0], "P1");
+    assert_eq!(lines[1], "64 32");
+    assert!(lines[2].starts_with("1 1 1 1 0 0"));
+    assert_eq!(lines.len(), 34);
+}
+
+#[test]
+fn test_to_png() {
+    let cpu = cpu_with_pixels();
+
+    let png = to_png(&cpu);
+
+    assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']);
+
+    // Walk the chunks, checking lengths and CRCs
+    let mut offset = 8;
+    let mut kinds = Vec::new();
+    let mut image_data = Vec::new();
+    while offset < png.len() {
+        let length = read_u32(&png[offset..]) as usize;
+        let kind = &png[offset + 4..offset + 8];
+        let data = &png[offset + 8..offset + 8 + length];
+        let crc = read_u32(&png[offset + 8 + length..]);
+        assert_eq!(crc, crc32(&png[offset + 4..offset + 8 + length]));
+        if kind == b"IHDR" {
+            assert_eq!(read_u32(data), 64);
+            assert_eq!(read_u32(&data[4..]), 32);
+        }
+        if kind == b"IDAT" {
+            image_data.extend_from_slice(data);
+        }
+        kinds.push(String::from_utf8(kind.to_vec()).unwrap());
+        offset += length + 12;
+    }
+    assert_eq!(kinds, vec!["IHDR", "PLTE", "IDAT", "IEND"]);
+
+    // A single stored deflate block holds the filtered scanlines
+    let scanlines = &image_data[7..image_data.len() - 4];
+    assert_eq!(image_data[2], 1);
+    assert_eq!(scanlines.len(), 65 * 32);
+    assert_eq!(&scanlines[..6], &[0, 1, 1, 1, 1, 0]);
+    assert!(scanlines[65..].iter().all(|&byte| byte == 0));
+}
@@ -0,0 +1,77 @@
+extern crate chip8_emulator;
+extern crate chip8_native;
+
+use chip8_emulator::cpu::Cpu;
+use chip8_emulator::keypad::Keypad;
+use chip8_emulator::quirks::Quirks;
+use chip8_emulator::variant::Variant;
+use chip8_native::terminal::*;
+
+fn cpu_with(rom: &[u8], steps: usize) -> Cpu {
+    let mut cpu = Cpu::new(Keypad::new(), Variant::Chip8, Quirks::default());
+    cpu.load_program(rom);
+    for _ in 0..steps {
+        cpu.execute_next_op_code().unwrap();
+    }
+    cpu
+}
+
+#[test]
+fn test_half_block_rows() {
+    // Draw the 0 font glyph, rows F0 90 90 90 F0
+    let cpu = cpu_with(&[0xA0, 0x00, 0xD0, 0x05], 2);
+
+    let rows = half_block_rows(&cpu);
+
+    assert_eq!(rows.len(), 16);
+    assert_eq!(rows[0].chars().count(), 64);
+    assert_eq!(rows[0].chars().take(5).collect::<String>(), "\u{2588}\u{2580}\u{2580}\u{2588} ");
+    assert_eq!(rows[1].chars().take(5).collect::<String>(), "\u{2588}  \u{2588} ");
+    assert_eq!(rows[2].chars().take(5).collect::<String>(), "\u{2580}\u{2580}\u{2580}\u{2580} ");
+    assert!(rows[3].chars().all(|c| c == ' '));
+}
+
+#[test]
+fn test_register_panel() {
+    // V1 := 0x2A, call 0x206, I := 0x123
+    let cpu = cpu_with(&[0x61, 0x2A, 0x22, 0x06, 0x00, 0x00, 0xA1, 0x23], 3);
+
+    let panel = register_panel(&cpu);
+
+    assert_eq!(panel[0], "PC 0208  I  0123");
+    assert_eq!(panel[2], "V0 00    V1 2A");
+    assert_eq!(panel[10], "SP 1");
+    assert_eq!(panel[11], "   0204");
+    assert_eq!(panel.len(), 12);
+}
+
+#[test]
+fn test_line_diff_only_returns_changes() {
+    let mut diff = LineDiff::new();
+    let lines = |text: &[&str]| text.iter().map(|line| line.to_string()).collect::<Vec<String>>();
+
+    assert_eq!(diff.changes(lines(&["ab", "cd"])), vec![(0, "ab".to_string()), (1, "cd".to_string())]);
+    assert_eq!(diff.changes(lines(&["ab", "cd"])), vec![]);
+    assert_eq!(diff.changes(lines(&["ab", "c"])), vec![(1, "c ".to_string())]);
+    assert_eq!(diff.changes(lines(&["ab"])), vec![(1, " ".to_string())]);
+
+    diff.invalidate();
+    assert_eq!(diff.changes(lines(&["ab"])), vec![(0, "ab".to_string())]);
+}
+
+#[test]
+fn test_held_keys_expire() {
+    let mut keys = HeldKeys::new();
+    keys.press(0x5);
+    keys.press(0x1A);
+
+    assert_eq!(keys.pressed(), vec![0x5, 0xA]);
+    for _ in 0..KEY_HOLD_FRAMES - 1 {
+        keys.tick();
+    }
+    assert_eq!(keys.pressed(), vec![0x5, 0xA]);
+
+    keys.press(0x5);
+    keys.tick();
+    assert_eq!(keys.pressed(), vec![0x5]);
+}
@@ -0,0 +1,44 @@
+export const createAudio = () => {
+  const frequency = 440;
+  const volume = 0.1;
+  let context = null;
+  let oscillator = null;
+
+  const start = () => {
+    if (oscillator) {
+      return;
+    }
+    context = context || new (window.AudioContext || window.webkitAudioContext)();
+
+    const gain = context.createGain();
+    gain.gain.value = volume;
+    gain.connect(context.destination);
+
+    oscillator = context.createOscillator();
+    oscillator.type = 'square';
+    oscillator.frequency.value = frequency;
+    oscillator.connect(gain);
+    oscillator.start();
+  };
+
+  const stop = () => {
+    if (!oscillator) {
+      return;
+    }
+    oscillator.stop();
+    oscillator.disconnect();
+    oscillator = null;
+  };
+
+  const setActive = active => {
+    if (active) {
+      start();
+    } else {
+      stop();
+    }
+  };
+
+  return {
+    setActive
+  };
+};
@@ -1,23 +1,26 @@
 export const createDisplay = () => {
-  const multiplier = 8;
+  const canvasWidth = 512;
+  const canvasHeight = 256;
+  const palette = [null, "#26cdff", "#ff6e26", "#ffffff"];
   const canvas = document.querySelector("canvas");
   const canvasContext = canvas.getContext("2d");
 
 
   const setupDisplay = () => {
-    canvas.width = 64 * multiplier;
-    canvas.height = 32 * multiplier;
+    canvas.width = canvasWidth;
+    canvas.height = canvasHeight;
   }
 
-  const drawPixels = (pixels) => {
-    for (let row = 0; row < 32; row++) {
-      const startOfRow = row * 64;
-      const rowNumbers = pixels.slice(startOfRow, startOfRow + 64);
+  const drawPixels = (pixels, width, height) => {
+    const multiplier = canvasWidth / width;
+    for (let row = 0; row < height; row++) {
+      const startOfRow = row * width;
+      const rowNumbers = pixels.slice(startOfRow, startOfRow + width);
 
-      for (let column = 0; column < 64; column++) {
+      for (let column = 0; column < width; column++) {
         const pixel = rowNumbers[column];
         if (pixel) {
-          canvasContext.fillStyle = "#26cdff";
+          canvasContext.fillStyle = palette[pixel];
           canvasContext.fillRect(column * multiplier, row * multiplier, multiplier, multiplier);
         } else {
           canvasContext.clearRect(column * multiplier, row * multiplier, multiplier, multiplier);
@@ -3,17 +3,26 @@ import {roms, fetchRom} from './roms';
 import {setupKeypad} from './keypad';
 import {createDisplay} from './display';
 import {createUI} from './ui';
+import {createAudio} from './audio';
+import {writeSlot, readSlot} from './savestates';
 
 let currentRomIndex = 0;
 let isStopped = true;
 let isLoading = false;
+let isRewinding = false;
+const rewindKeyCode = 8;
 let display;
 let ui;
+let audio;
 
 const updateScreen = () => {
   const pixels = emu.get_contents();
 
-  display.drawPixels(pixels);
+  display.drawPixels(
+    pixels,
+    emu.get_display_width(),
+    emu.get_display_height()
+  );
   ui.updateDebugInfo(
     emu.get_program_counter(),
     emu.get_i_register(),
@@ -21,13 +30,35 @@ const updateScreen = () => {
   );
 };
 
+const handleEmulatorError = error => {
+  stop();
+  console.error(`${getCurrentRom().name}: ${error}`);
+};
+
 const executeCycle = () => {
-  emu.execute_cycle();
+  try {
+    if (isRewinding) {
+      emu.rewind(1);
+    } else {
+      const stopReason = emu.execute_cycle();
+      if (stopReason) {
+        stop();
+        ui.setStopReason(stopReason);
+      }
+    }
+  } catch (error) {
+    handleEmulatorError(error);
+  }
+  audio.setActive(!isStopped && emu.is_sound_active());
   updateScreen();
 };
 
-const executeNextOpCode = () => {
-  emu.execute_next_op_code();
+const runDebuggerCommand = command => {
+  try {
+    ui.setStopReason(command());
+  } catch (error) {
+    handleEmulatorError(error);
+  }
   updateScreen();
 };
 
@@ -50,15 +81,64 @@ const update = () => {
 
 };
 
+const resume = () => {
+  ui.setStopReason('');
+  start();
+};
+
 const stop = () => {
   isStopped = true;
+  audio.setActive(false);
 };
 const start = () => {
   isStopped = false;
 };
 
 const step = () => {
-  executeNextOpCode();
+  runDebuggerCommand(() => emu.step());
+};
+
+const stepOver = () => {
+  runDebuggerCommand(() => emu.step_over());
+};
+
+const stepOut = () => {
+  runDebuggerCommand(() => emu.step_out());
+};
+
+const toggleBreakpoint = (address, condition) => {
+  if (ui.hasBreakpoint(address)) {
+    emu.remove_breakpoint(address);
+    ui.removeBreakpoint(address);
+    return;
+  }
+  try {
+    if (condition) {
+      emu.add_conditional_breakpoint(address, condition);
+    } else {
+      emu.add_breakpoint(address);
+    }
+    ui.addBreakpoint(address, condition);
+  } catch (error) {
+    console.error(`Could not add breakpoint: ${error}`);
+  }
+};
+
+const saveState = slot => {
+  writeSlot(getCurrentRom().name, slot, emu.save_state());
+};
+
+const loadState = slot => {
+  const state = readSlot(getCurrentRom().name, slot);
+  if (!state) {
+    return;
+  }
+  try {
+    emu.load_state(state);
+  } catch (error) {
+    console.error(`Could not load state: ${error}`);
+  }
+  updateScreen();
 };
 
 const getCurrentRom = () => {
@@ -81,6 +161,8 @@ const selectRomIndex = romIndex => {
   }
   isLoading = true;
   emu.reset();
+  emu.clear_debugger();
+  ui.clearBreakpoints();
   currentRomIndex = romIndex;
   const rom = roms[romIndex];
   ui.setGameLabel(rom.name);
@@ -94,11 +176,23 @@ const selectRomIndex = romIndex => {
 
 const emu = new Chip8();
 display = createDisplay();
-ui = createUI(stop, step, selectNextGame, selectPrevGame);
+audio = createAudio();
+ui = createUI(stop, resume, step, stepOver, stepOut, toggleBreakpoint, selectNextGame, selectPrevGame, saveState, loadState);
 setupKeypad(
   () => getCurrentRom().keyMappings,
   key => emu.press_key(key),
   key => emu.release_key(key)
 );
+window.addEventListener('keydown', e => {
+  if (e.keyCode === rewindKeyCode) {
+    isRewinding = true;
+    e.preventDefault();
+  }
+});
+window.addEventListener('keyup', e => {
+  if (e.keyCode === rewindKeyCode) {
+    isRewinding = false;
+  }
+});
 selectRomIndex(0);
 window.requestAnimationFrame(update);
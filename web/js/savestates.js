@@ -0,0 +1,20 @@
+const slotKey = (romName, slot) => `chip8-savestate-${romName}-${slot}`;
+
+const toBase64 = bytes => {
+  let binary = '';
+  bytes.forEach(byte => {
+    binary += String.fromCharCode(byte);
+  });
+  return btoa(binary);
+};
+
+const fromBase64 = text => Uint8Array.from(atob(text), char => char.charCodeAt(0));
+
+export const writeSlot = (romName, slot, bytes) => {
+  localStorage.setItem(slotKey(romName, slot), toBase64(bytes));
+};
+
+export const readSlot = (romName, slot) => {
+  const text = localStorage.getItem(slotKey(romName, slot));
+  return text ? fromBase64(text) : null;
+};
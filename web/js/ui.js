@@ -1,20 +1,43 @@
-export const createUI = (onHalt, onStep, onNextGame, onPrevGame) => {
+export const createUI = (onHalt, onResume, onStep, onStepOver, onStepOut, onToggleBreakpoint, onNextGame, onPrevGame, onSaveState, onLoadState) => {
   const controlsDiv = document.querySelector('.controls');
   const debugDiv = document.querySelector('.debug-box');
   const debugInfoDiv = document.querySelector('.debug');
   const haltBtn = document.querySelector('.halt-button');
+  const resumeBtn = document.querySelector('.resume-button');
   const stepBtn = document.querySelector('.step-button');
+  const stepOverBtn = document.querySelector('.step-over-button');
+  const stepOutBtn = document.querySelector('.step-out-button');
+  const breakpointInput = document.querySelector('.breakpoint-input');
+  const conditionInput = document.querySelector('.condition-input');
+  const breakpointBtn = document.querySelector('.breakpoint-button');
+  const breakpointsDiv = document.querySelector('.breakpoints');
+  const stopReasonDiv = document.querySelector('.stop-reason');
+  const saveBtn = document.querySelector('.save-button');
+  const loadBtn = document.querySelector('.load-button');
+  const slotSelect = document.querySelector('.slot-select');
   const debugBtn = document.querySelector('.debug-button');
   const nextGameBtn = document.querySelector('.next-game');
   const prevGameBtn = document.querySelector(".prev-game");
   const activeGameLabel = document.querySelector(".game-info-active");
 
   let debugInfoEntries = {};
+  let breakpointEntries = {};
   let showDebug = false;
 
   const setupEvents = () => {
     haltBtn.addEventListener("click", onHalt);
+    resumeBtn.addEventListener("click", onResume);
     stepBtn.addEventListener("click", onStep);
+    stepOverBtn.addEventListener("click", onStepOver);
+    stepOutBtn.addEventListener("click", onStepOut);
+    breakpointBtn.addEventListener("click", () => {
+      const address = parseInt(breakpointInput.value, 16);
+      if (!isNaN(address)) {
+        onToggleBreakpoint(address, conditionInput.value.trim());
+      }
+    });
+    saveBtn.addEventListener("click", () => onSaveState(slotSelect.value));
+    loadBtn.addEventListener("click", () => onLoadState(slotSelect.value));
     debugBtn.addEventListener("click", toggleDebug);
     nextGameBtn.addEventListener("click", onNextGame);
     prevGameBtn.addEventListener("click", onPrevGame);
@@ -59,6 +82,32 @@ export const createUI = (onHalt, onStep, onNextGame, onPrevGame) => {
     })
   }
 
+  const setStopReason = (reason) => {
+    stopReasonDiv.innerText = reason;
+  }
+
+  const hasBreakpoint = (address) => {
+    return address in breakpointEntries;
+  }
+
+  const addBreakpoint = (address, condition) => {
+    const label = address.toString(16).toUpperCase().padStart(4, '0');
+    breakpointEntries[address] = addInfoBoxEntry(
+      breakpointsDiv,
+      condition ? `${label} if ${condition}` : label
+    );
+  }
+
+  const removeBreakpoint = (address) => {
+    breakpointEntries[address].remove();
+    delete breakpointEntries[address];
+  }
+
+  const clearBreakpoints = () => {
+    Object.keys(breakpointEntries).forEach((address) => removeBreakpoint(address));
+    setStopReason('');
+  }
+
   const updateControls = (keyMappings) => {
     while (controlsDiv.firstChild) {
       controlsDiv.firstChild.remove();
@@ -76,6 +125,11 @@ export const createUI = (onHalt, onStep, onNextGame, onPrevGame) => {
   return {
     setGameLabel,
     updateDebugInfo,
+    setStopReason,
+    hasBreakpoint,
+    addBreakpoint,
+    removeBreakpoint,
+    clearBreakpoints,
     updateControls
   }
 };
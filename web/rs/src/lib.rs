@@ -1,11 +1,25 @@
 extern crate wasm_bindgen;
 extern crate chip8_emulator;
 
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
+use chip8_emulator::debugger::{Condition, Debugger, Register, StopReason, Watchpoint};
+use chip8_emulator::machine::Machine;
+use chip8_emulator::random::{OsRandom, SeededRandom};
+use chip8_emulator::rewind::Rewind;
 
 #[wasm_bindgen]
 pub struct Chip8 {
-    cpu: chip8_emulator::cpu::Cpu,
+    machine: Machine,
+    rewind: Rewind,
+    debugger: Debugger,
+}
+
+fn stop_reason_to_js(reason: StopReason) -> Option<String> {
+    return match reason {
+        StopReason::FrameComplete | StopReason::Exited => None,
+        reason => Some(reason.to_string()),
+    };
 }
 
 #[wasm_bindgen]
@@ -13,60 +27,191 @@ impl Chip8 {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Chip8 {
         let keypad = chip8_emulator::keypad::Keypad::new();
-        Chip8 { cpu: chip8_emulator::cpu::Cpu::new(keypad) }
+        let variant = chip8_emulator::variant::Variant::Chip8;
+        let quirks = chip8_emulator::quirks::Quirks::default();
+        let mut cpu = chip8_emulator::cpu::Cpu::new(keypad, variant, quirks);
+        if let Ok(rng) = OsRandom::new() {
+            cpu.set_random_source(Box::new(rng));
+        }
+        Chip8 { machine: Machine::new(cpu), rewind: Rewind::default(), debugger: Debugger::new() }
+    }
+
+    pub fn set_random_seed(&mut self, seed: u32) {
+        self.machine.cpu.set_random_source(Box::new(SeededRandom::new(seed as u64)));
+    }
+
+    pub fn get_display_width(&mut self) -> usize {
+        return self.machine.cpu.display_width();
+    }
+
+    pub fn get_display_height(&mut self) -> usize {
+        return self.machine.cpu.display_height();
     }
 
     pub fn get_contents(&mut self) -> Vec<u8> {
-        return self.cpu.video_memory
-            .iter()
-            .cloned()
-            .collect::<Vec<_>>()
-            .iter()
-            .flat_map(|x| x.into_iter())
-            .map(|x| *x as u8)
+        let width = self.machine.cpu.display_width();
+        let height = self.machine.cpu.display_height();
+        let cpu = &self.machine.cpu;
+
+        return (0..height)
+            .flat_map(|y| (0..width).map(move |x| cpu.pixel(x, y)))
             .collect::<Vec<u8>>();
     }
 
     pub fn load_content(&mut self, data: &[u8]) {
-        self.cpu.load_program(data);
+        self.machine.cpu.load_program(data);
+        self.rewind.clear();
+    }
+
+    // Returns a description of the breakpoint or watchpoint that interrupted the frame
+    pub fn execute_cycle(&mut self) -> Result<Option<String>, JsValue> {
+        let result = self.debugger.run_frame(&mut self.machine);
+        self.rewind.record_frame(&self.machine.cpu);
+        return result
+            .map(stop_reason_to_js)
+            .map_err(|e| JsValue::from_str(&e.to_string()));
+    }
+
+    pub fn step(&mut self) -> Result<String, JsValue> {
+        return self.debugger.step(&mut self.machine)
+            .map(|reason| reason.to_string())
+            .map_err(|e| JsValue::from_str(&e.to_string()));
+    }
+
+    pub fn step_over(&mut self) -> Result<String, JsValue> {
+        return self.debugger.step_over(&mut self.machine)
+            .map(|reason| reason.to_string())
+            .map_err(|e| JsValue::from_str(&e.to_string()));
+    }
+
+    pub fn step_out(&mut self) -> Result<String, JsValue> {
+        return self.debugger.step_out(&mut self.machine)
+            .map(|reason| reason.to_string())
+            .map_err(|e| JsValue::from_str(&e.to_string()));
+    }
+
+    pub fn run_until_frame(&mut self, frame: f64) -> Result<String, JsValue> {
+        return self.debugger.run_until_frame(&mut self.machine, frame as u64)
+            .map(|reason| reason.to_string())
+            .map_err(|e| JsValue::from_str(&e.to_string()));
+    }
+
+    pub fn get_frame_count(&mut self) -> f64 {
+        return self.machine.frame_count() as f64;
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.debugger.add_breakpoint(address);
+    }
+
+    pub fn add_conditional_breakpoint(&mut self, address: u16, condition: &str) -> Result<(), JsValue> {
+        let condition = Condition::parse(condition)
+            .ok_or_else(|| JsValue::from_str(&format!("invalid condition '{}'", condition)))?;
+        self.debugger.add_conditional_breakpoint(address, condition);
+        return Ok(());
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.debugger.remove_breakpoint(address);
+    }
+
+    pub fn add_read_watchpoint(&mut self, start: usize, end: usize) {
+        self.debugger.add_watchpoint(Watchpoint::MemoryRead { start: start, end: end });
+    }
+
+    pub fn add_write_watchpoint(&mut self, start: usize, end: usize) {
+        self.debugger.add_watchpoint(Watchpoint::MemoryWrite { start: start, end: end });
     }
 
-    pub fn execute_cycle(&mut self) {
-        self.cpu.execute_cycle();
+    pub fn add_register_watchpoint(&mut self, register: &str) -> Result<(), JsValue> {
+        let register = Register::parse(register)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown register '{}'", register)))?;
+        self.debugger.add_watchpoint(Watchpoint::Register(register));
+        return Ok(());
     }
 
-    pub fn execute_next_op_code(&mut self) {
-        self.cpu.execute_next_op_code();
+    pub fn clear_debugger(&mut self) {
+        self.debugger.clear();
+    }
+
+    pub fn rewind(&mut self, frames: u32) -> Result<u32, JsValue> {
+        return self.rewind.rewind(&mut self.machine.cpu, frames)
+            .map_err(|e| JsValue::from_str(&e.to_string()));
+    }
+
+    pub fn run_for(&mut self, milliseconds: f64) -> Result<(), JsValue> {
+        let duration = Duration::from_micros((milliseconds * 1000.0) as u64);
+        return self.machine.run_for(duration)
+            .map_err(|e| JsValue::from_str(&e.to_string()));
+    }
+
+    pub fn get_instructions_per_second(&mut self) -> u32 {
+        return self.machine.instructions_per_second();
+    }
+
+    pub fn set_instructions_per_second(&mut self, instructions_per_second: u32) {
+        self.machine.set_instructions_per_second(instructions_per_second);
+    }
+
+    pub fn execute_next_op_code(&mut self) -> Result<(), JsValue> {
+        return self.machine.cpu.execute_next_op_code()
+            .map_err(|e| JsValue::from_str(&e.to_string()));
     }
 
     pub fn press_key(&mut self, key: u8) {
-        self.cpu.keypad.press_key(key);
+        self.machine.cpu.keypad.press_key(key);
     }
 
     pub fn release_key(&mut self, key: u8) {
-        self.cpu.keypad.release_key(key);
+        self.machine.cpu.keypad.release_key(key);
     }
 
     pub fn release_all_keys(&mut self) {
-        self.cpu.keypad.release_all_keys();
+        self.machine.cpu.keypad.release_all_keys();
     }
 
     pub fn get_v_register(&mut self) -> Vec<u8> {
-        return self.cpu.registers.v
+        return self.machine.cpu.registers.v
             .iter()
             .cloned()
             .collect::<Vec<u8>>();
     }
 
     pub fn get_i_register(&mut self) -> u16 {
-        return self.cpu.registers.i;
+        return self.machine.cpu.registers.i;
     }
 
     pub fn get_program_counter(&mut self) -> u16 {
-        return self.cpu.program_counter;
+        return self.machine.cpu.program_counter;
+    }
+
+    pub fn is_sound_active(&mut self) -> bool {
+        return self.machine.cpu.is_sound_active();
+    }
+
+    pub fn get_audio_pattern(&mut self) -> Vec<u8> {
+        return match self.machine.cpu.audio_pattern() {
+            Some(pattern) => pattern.to_vec(),
+            None => Vec::new(),
+        };
+    }
+
+    pub fn get_audio_playback_rate(&mut self) -> f64 {
+        return self.machine.cpu.audio_playback_rate();
+    }
+
+    pub fn save_state(&mut self) -> Vec<u8> {
+        return self.machine.cpu.save_state();
+    }
+
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        self.rewind.clear();
+        return self.machine.cpu.load_state(data)
+            .map_err(|e| JsValue::from_str(&e.to_string()));
     }
 
     pub fn reset(&mut self) {
-        self.cpu.reset();
+        self.machine.reset();
+        self.rewind.clear();
     }
 }